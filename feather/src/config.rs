@@ -0,0 +1,460 @@
+// This file loads the user configuration from `config.toml` in the Feather config directory
+use crate::cookies::{CookieError, Cookies};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+/// User settings. Every section falls back to its defaults when missing from the file.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub resume: ResumeConfig,              // Per-track resume positions
+    pub seek: SeekConfig,                  // Seek step sizes
+    pub playback: PlaybackConfig,          // Playback behaviour
+    pub filters: FilterConfig,             // Equalizer and audio filter presets
+    pub loudness: LoudnessConfig,          // Loudness normalization
+    pub stream: StreamConfig,              // Audio stream selection
+    pub scrobble: ScrobbleConfig,          // Submitting listens to ListenBrainz and Last.fm
+    pub notifications: NotificationConfig, // Desktop notifications
+    pub status: StatusConfig,              // Now-playing status for bars and prompts
+    pub youtube: YoutubeConfig,            // Access to YouTube
+}
+
+/// Settings for remembering where long tracks were left off.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ResumeConfig {
+    pub enabled: bool,        // Remember positions at all
+    pub min_length_secs: u64, // Only tracks at least this long are remembered
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_length_secs: 20 * 60,
+        }
+    }
+}
+
+/// Step sizes used by the seek keys.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SeekConfig {
+    pub small_step_secs: f64, // Step of the arrow keys
+    pub large_step_secs: f64, // Step of the shifted arrow keys
+}
+
+impl Default for SeekConfig {
+    fn default() -> Self {
+        Self {
+            small_step_secs: 5.0,
+            large_step_secs: 60.0,
+        }
+    }
+}
+
+/// Settings for playback behaviour.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PlaybackConfig {
+    pub remember_speed: bool, // Save the playback speed per track
+    pub crossfade_secs: f64,  // Crossfade between queue items (0-12 seconds, 0 disables it)
+}
+
+/// Settings for playing every track at the same loudness.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LoudnessConfig {
+    pub enabled: bool,    // Normalize loudness at all
+    pub target_lufs: f64, // Loudness every track is brought to
+}
+
+impl Default for LoudnessConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            target_lufs: -14.0,
+        }
+    }
+}
+
+/// Settings for choosing which audio stream of a song is played.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StreamConfig {
+    pub codec: Codec,                  // Preferred codec
+    pub max_bitrate_kbps: Option<u32>, // Upper bitrate limit, e.g. for metered connections
+    pub quality: Quality,              // Pick the best or the smallest fitting stream
+}
+
+/// Audio codec preference.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    #[default]
+    Opus, // Opus in WebM
+    M4a, // AAC in MP4
+    Any, // No preference
+}
+
+/// Whether to favor quality or data usage when several streams fit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Quality {
+    #[default]
+    Best, // Highest bitrate
+    DataSaver, // Lowest bitrate
+}
+
+/// Settings for submitting listens to ListenBrainz and Last.fm. Each service is only used
+/// when its section is present.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ScrobbleConfig {
+    pub min_percent: f64,    // Share of a track that has to be heard for a listen
+    pub max_secs: u64,       // Hearing this long is enough for a listen, however long the track
+    pub min_track_secs: u64, // Shorter tracks are never submitted
+    pub now_playing: bool,   // Announce the song that just started
+    pub listenbrainz: Option<ListenBrainzConfig>, // ListenBrainz account
+    pub lastfm: Option<LastfmConfig>, // Last.fm account
+}
+
+impl Default for ScrobbleConfig {
+    fn default() -> Self {
+        Self {
+            min_percent: 50.0,
+            max_secs: 240,
+            min_track_secs: 30,
+            now_playing: true,
+            listenbrainz: None,
+            lastfm: None,
+        }
+    }
+}
+
+impl ScrobbleConfig {
+    /// Returns whether any service is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.listenbrainz.is_some() || self.lastfm.is_some()
+    }
+
+    /// Returns whether hearing `listened_secs` of a track of `duration_secs` counts as a listen:
+    /// `min_percent` of it or `max_secs`, whichever comes first.
+    pub fn is_listen(&self, duration_secs: f64, listened_secs: f64) -> bool {
+        if duration_secs < self.min_track_secs as f64 {
+            return false;
+        }
+        let threshold = (duration_secs * self.min_percent / 100.0).min(self.max_secs as f64);
+        listened_secs >= threshold
+    }
+}
+
+/// ListenBrainz account, see <https://listenbrainz.org/settings/>.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ListenBrainzConfig {
+    pub token: String, // User token
+    pub url: String,   // API root, for self-hosted servers
+}
+
+impl Default for ListenBrainzConfig {
+    fn default() -> Self {
+        Self {
+            token: String::new(),
+            url: "https://api.listenbrainz.org".to_string(),
+        }
+    }
+}
+
+/// Last.fm account and API application, see <https://www.last.fm/api/account/create>.
+/// Without a session key one is requested with the username and password and kept.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LastfmConfig {
+    pub api_key: String,             // API key of the application
+    pub api_secret: String,          // Shared secret of the application
+    pub session_key: Option<String>, // Session key of the user
+    pub username: Option<String>,    // Used to get a session key
+    pub password: Option<String>,    // Used to get a session key
+    pub url: String,                 // API root
+}
+
+impl Default for LastfmConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            api_secret: String::new(),
+            session_key: None,
+            username: None,
+            password: None,
+            url: "https://ws.audioscrobbler.com/2.0/".to_string(),
+        }
+    }
+}
+
+/// Settings for desktop notifications about track changes and playback failures.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub when: NotifyWhen,       // When to show notifications
+    pub min_interval_secs: f64, // Shortest time between two notifications
+    pub timeout_ms: i32,        // How long a notification stays, -1 for the desktop's default
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            when: NotifyWhen::Never,
+            min_interval_secs: 5.0,
+            timeout_ms: -1,
+        }
+    }
+}
+
+/// When desktop notifications are shown.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyWhen {
+    Always,    // On every track change
+    Unfocused, // Only while no Feather TUI has focus
+    #[default]
+    Never, // Not at all
+}
+
+/// Settings for publishing what is playing to status bars and shell prompts.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct StatusConfig {
+    pub file: bool,     // Keep the status files in the runtime directory up to date
+    pub format: String, // Text of the status, e.g. "{artist} - {title} [{position}/{duration}]"
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            file: true,
+            format: "{artist} - {title}".to_string(),
+        }
+    }
+}
+
+/// Settings for accessing YouTube.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct YoutubeConfig {
+    pub cookies_file: Option<PathBuf>, // Netscape cookies.txt with a YouTube login, `~/` allowed
+}
+
+impl YoutubeConfig {
+    /// Reads and checks the cookies file, if one is set.
+    pub fn load_cookies(&self) -> Result<Option<Cookies>, CookieError> {
+        let Some(path) = &self.cookies_file else {
+            return Ok(None);
+        };
+        let path = match (path.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(relative), Some(home)) => home.join(relative),
+            _ => path.clone(),
+        };
+        Cookies::load(&path).map(Some)
+    }
+}
+
+/// Equalizer and filter presets that can be switched at runtime.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FilterConfig {
+    pub default: String,            // Name of the preset applied on startup
+    pub presets: Vec<FilterPreset>, // Available presets, in switching order
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            default: "Off".to_string(),
+            presets: vec![
+                FilterPreset::new("Off", vec![], vec![]),
+                FilterPreset::new(
+                    "Bass boost",
+                    vec![EqBand::new(60.0, 6.0), EqBand::new(150.0, 3.0)],
+                    vec![],
+                ),
+                FilterPreset::new(
+                    "Vocal",
+                    vec![
+                        EqBand::new(150.0, -3.0),
+                        EqBand::new(1500.0, 2.0),
+                        EqBand::new(3000.0, 4.0),
+                    ],
+                    vec![],
+                ),
+                FilterPreset::new(
+                    "Night mode",
+                    vec![],
+                    vec![
+                        "acompressor=threshold=0.1:ratio=6:attack=20:release=250:makeup=2"
+                            .to_string(),
+                    ],
+                ),
+                FilterPreset::new(
+                    "Mono",
+                    vec![],
+                    vec!["pan=mono|c0=0.5*c0+0.5*c1".to_string()],
+                ),
+            ],
+        }
+    }
+}
+
+impl FilterConfig {
+    /// Returns the index of the preset with the given name, if any.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.presets
+            .iter()
+            .position(|preset| preset.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A named chain of equalizer bands followed by extra libavfilter filters.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FilterPreset {
+    pub name: String,         // Name shown in the player
+    pub bands: Vec<EqBand>,   // Parametric equalizer bands
+    pub filters: Vec<String>, // Extra libavfilter filters, e.g. "acompressor=ratio=4"
+}
+
+impl FilterPreset {
+    /// Creates a new preset.
+    pub fn new(name: &str, bands: Vec<EqBand>, filters: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            bands,
+            filters,
+        }
+    }
+
+    /// Builds the value of mpv's `af` property for this preset.
+    /// An empty string clears all filters.
+    pub fn af(&self) -> String {
+        let chain: Vec<String> = self
+            .bands
+            .iter()
+            .map(EqBand::filter)
+            .chain(self.filters.iter().cloned())
+            .collect();
+        if chain.is_empty() {
+            String::new()
+        } else {
+            // The brackets keep commas and colons inside the chain away from mpv's own parser
+            format!("lavfi=[{}]", chain.join(","))
+        }
+    }
+}
+
+/// A single peaking equalizer band.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EqBand {
+    pub freq: f64,  // Center frequency in Hz
+    pub gain: f64,  // Gain in dB, negative to cut
+    pub width: f64, // Bandwidth in octaves
+}
+
+impl Default for EqBand {
+    fn default() -> Self {
+        Self {
+            freq: 1000.0,
+            gain: 0.0,
+            width: 1.0,
+        }
+    }
+}
+
+impl EqBand {
+    /// Creates a one octave wide band.
+    pub fn new(freq: f64, gain: f64) -> Self {
+        Self {
+            freq,
+            gain,
+            ..Self::default()
+        }
+    }
+
+    // Formats the band as a libavfilter `equalizer` filter
+    fn filter(&self) -> String {
+        format!(
+            "equalizer=f={}:t=o:w={}:g={}",
+            self.freq, self.width, self.gain
+        )
+    }
+}
+
+/// Represents possible errors that can occur while loading the configuration.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+impl Config {
+    /// Returns the path of the configuration file.
+    pub fn path() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/config.toml");
+        path
+    }
+
+    /// Loads the configuration, using the defaults if the file does not exist.
+    pub fn load() -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(Self::path()) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Parses the contents of a config file and checks the values TOML cannot rule out
+    fn parse(content: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(content)?;
+        let interval = config.notifications.min_interval_secs;
+        if Duration::try_from_secs_f64(interval).is_err() {
+            return Err(ConfigError::Invalid(format!(
+                "notifications.min_interval_secs must be a number of seconds of 0 or more, not {}",
+                interval
+            )));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_notification_intervals() {
+        let config = Config::parse("[notifications]\nmin_interval_secs = 2").unwrap();
+        assert_eq!(config.notifications.min_interval_secs, 2.0);
+        let config = Config::parse("[notifications]\nmin_interval_secs = 0.5").unwrap();
+        assert_eq!(config.notifications.min_interval_secs, 0.5);
+        assert!(Config::parse("").is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_notification_intervals() {
+        for interval in ["-1", "inf", "nan", "1e300"] {
+            let content = format!("[notifications]\nmin_interval_secs = {}", interval);
+            assert!(
+                matches!(Config::parse(&content), Err(ConfigError::Invalid(_))),
+                "{}",
+                interval
+            );
+        }
+    }
+}
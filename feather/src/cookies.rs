@@ -0,0 +1,342 @@
+// This file reads and checks cookies exported from a browser in the Netscape cookies.txt format
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Cookies that carry a YouTube login. Without one of them YouTube treats requests as
+/// signed out, whatever else the file holds.
+const LOGIN_COOKIES: [&str; 2] = ["SAPISID", "__Secure-3PAPISID"];
+
+/// A single cookie from a cookies.txt file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    pub domain: String,           // Domain the cookie is sent to, e.g. ".youtube.com"
+    pub include_subdomains: bool, // Whether it is also sent to subdomains
+    pub path: String,             // Path the cookie is sent to
+    pub secure: bool,             // Whether it is only sent over HTTPS
+    pub expires: i64,             // Unix time it expires at, 0 for session cookies
+    pub name: String,             // Name of the cookie
+    pub value: String,            // Value of the cookie
+    pub http_only: bool,          // Whether scripts may not read it
+}
+
+impl Cookie {
+    /// Returns whether the cookie is sent to YouTube.
+    pub fn is_youtube(&self) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        domain == "youtube.com" || domain.ends_with(".youtube.com")
+    }
+
+    /// Returns whether the cookie has expired at Unix time `now`.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires > 0 && self.expires <= now
+    }
+}
+
+/// Represents possible errors that can occur while loading cookies.
+#[derive(Error, Debug)]
+pub enum CookieError {
+    #[error("Failed to read cookies from {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Malformed cookies in {path}, line {line}: {reason}")]
+    Malformed {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    #[error("{0} holds no YouTube login; export the cookies of youtube.com while signed in")]
+    NotSignedIn(PathBuf),
+    #[error("The YouTube login in {path} expired {ago} ago; export the cookies again")]
+    Expired { path: PathBuf, ago: String },
+}
+
+/// The cookies of a cookies.txt file, checked to hold a YouTube login that has not expired.
+#[derive(Debug, Clone)]
+pub struct Cookies {
+    path: PathBuf,        // File the cookies were read from
+    cookies: Vec<Cookie>, // Cookies in file order
+}
+
+impl Cookies {
+    /// Reads and checks a cookies.txt file, as exported by browser extensions or
+    /// `yt-dlp --cookies-from-browser`.
+    pub fn load(path: &Path) -> Result<Self, CookieError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| CookieError::Io(path.to_path_buf(), e))?;
+        let cookies = parse(&text).map_err(|(line, reason)| CookieError::Malformed {
+            path: path.to_path_buf(),
+            line,
+            reason,
+        })?;
+        let cookies = Self {
+            path: path.to_path_buf(),
+            cookies,
+        };
+        cookies.check_login(now())?;
+        Ok(cookies)
+    }
+
+    /// Returns the file the cookies were read from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns all cookies in the file.
+    pub fn cookies(&self) -> &[Cookie] {
+        &self.cookies
+    }
+
+    /// Builds the value of the `Cookie` header sent to YouTube from the cookies that
+    /// have not expired.
+    pub fn header(&self) -> String {
+        let now = now();
+        self.cookies
+            .iter()
+            .filter(|cookie| cookie.is_youtube() && !cookie.is_expired(now))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    // Fails unless a login cookie for YouTube is valid at Unix time `now`
+    fn check_login(&self, now: i64) -> Result<(), CookieError> {
+        let logins: Vec<&Cookie> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.is_youtube() && LOGIN_COOKIES.contains(&cookie.name.as_str()))
+            .collect();
+        if logins.iter().any(|cookie| !cookie.is_expired(now)) {
+            return Ok(());
+        }
+        match logins.iter().map(|cookie| cookie.expires).max() {
+            Some(expired) => Err(CookieError::Expired {
+                path: self.path.clone(),
+                ago: ago(now - expired),
+            }),
+            None => Err(CookieError::NotSignedIn(self.path.clone())),
+        }
+    }
+}
+
+// Parses the lines of a cookies.txt file, failing with the line number and the reason
+fn parse(text: &str) -> Result<Vec<Cookie>, (usize, String)> {
+    let mut cookies = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim_end_matches('\r');
+        // Cookies hidden from scripts are written as comments with this prefix
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            let reason = if !line.contains('\t') && line.contains('=') {
+                "this looks like a Cookie header, not a cookies.txt file".to_string()
+            } else {
+                format!("expected 7 tab-separated fields, found {}", fields.len())
+            };
+            return Err((number, reason));
+        }
+        let flag = |field: &str, name: &str| match field.to_ascii_uppercase().as_str() {
+            "TRUE" => Ok(true),
+            "FALSE" => Ok(false),
+            _ => Err((
+                number,
+                format!("{} must be TRUE or FALSE, not \"{}\"", name, field),
+            )),
+        };
+        let expires = fields[4].trim().parse::<i64>().map_err(|_| {
+            (
+                number,
+                format!("expiry must be a Unix time, not \"{}\"", fields[4]),
+            )
+        })?;
+        if fields[0].is_empty() || fields[5].is_empty() {
+            return Err((number, "the domain and name must not be empty".to_string()));
+        }
+        cookies.push(Cookie {
+            domain: fields[0].to_string(),
+            include_subdomains: flag(fields[1], "the subdomain flag")?,
+            path: fields[2].to_string(),
+            secure: flag(fields[3], "the secure flag")?,
+            expires,
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+            http_only,
+        });
+    }
+    Ok(cookies)
+}
+
+// Describes a number of seconds as minutes, hours or days
+fn ago(secs: i64) -> String {
+    let (count, unit) = match secs.max(0) {
+        secs if secs < 3600 => (secs / 60, "minute"),
+        secs if secs < 86_400 => (secs / 3600, "hour"),
+        secs => (secs / 86_400, "day"),
+    };
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+// Returns the current Unix time
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unix time the tests run at.
+    const NOW: i64 = 1_700_000_000;
+
+    // Checks the login of cookies parsed from `text` at `NOW`
+    fn check(text: &str) -> Result<(), CookieError> {
+        let cookies = Cookies {
+            path: PathBuf::from("cookies.txt"),
+            cookies: parse(text).unwrap(),
+        };
+        cookies.check_login(NOW)
+    }
+
+    #[test]
+    fn parses_cookies() {
+        let text = "# Netscape HTTP Cookie File\n\
+                    \n\
+                    .youtube.com\tTRUE\t/\tTRUE\t1800000000\tSAPISID\tabc\r\n\
+                    www.youtube.com\tfalse\t/watch\tFALSE\t0\tPREF\tf6=40000000\n";
+        let cookies = parse(text).unwrap();
+        assert_eq!(
+            cookies,
+            [
+                Cookie {
+                    domain: ".youtube.com".to_string(),
+                    include_subdomains: true,
+                    path: "/".to_string(),
+                    secure: true,
+                    expires: 1_800_000_000,
+                    name: "SAPISID".to_string(),
+                    value: "abc".to_string(),
+                    http_only: false,
+                },
+                Cookie {
+                    domain: "www.youtube.com".to_string(),
+                    include_subdomains: false,
+                    path: "/watch".to_string(),
+                    secure: false,
+                    expires: 0,
+                    name: "PREF".to_string(),
+                    value: "f6=40000000".to_string(),
+                    http_only: false,
+                },
+            ]
+        );
+        assert!(cookies.iter().all(Cookie::is_youtube));
+    }
+
+    #[test]
+    fn parses_http_only_cookies() {
+        let text = "#HttpOnly_.youtube.com\tTRUE\t/\tTRUE\t0\tSID\txyz\n";
+        let cookies = parse(text).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert!(cookies[0].http_only);
+        assert_eq!(cookies[0].domain, ".youtube.com");
+        assert_eq!(cookies[0].name, "SID");
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# Netscape HTTP Cookie File\n\
+                    # .youtube.com\tTRUE\t/\tTRUE\t0\tSAPISID\tabc\n\
+                    \x20\t\n\
+                    \r\n";
+        assert_eq!(parse(text).unwrap(), []);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let error = |text: &str| parse(text).unwrap_err();
+        assert_eq!(
+            error("# Cookies\n.youtube.com\tTRUE\t/\tTRUE\t0\tSID\n"),
+            (2, "expected 7 tab-separated fields, found 6".to_string())
+        );
+        let (line, reason) = error("SAPISID=abc; SID=xyz");
+        assert_eq!(line, 1);
+        assert!(reason.contains("Cookie header"), "{}", reason);
+        let (_, reason) = error(".youtube.com\tyes\t/\tTRUE\t0\tSID\txyz");
+        assert!(reason.contains("subdomain flag"), "{}", reason);
+        let (_, reason) = error(".youtube.com\tTRUE\t/\tTRUE\tsoon\tSID\txyz");
+        assert!(reason.contains("Unix time"), "{}", reason);
+        let (_, reason) = error(".youtube.com\tTRUE\t/\tTRUE\t0\t\txyz");
+        assert!(reason.contains("must not be empty"), "{}", reason);
+    }
+
+    #[test]
+    fn accepts_a_valid_login() {
+        assert!(check(".youtube.com\tTRUE\t/\tTRUE\t1800000000\tSAPISID\tabc").is_ok());
+        // Session cookies never expire, and one valid login cookie is enough
+        let text = ".youtube.com\tTRUE\t/\tTRUE\t1000\tSAPISID\told\n\
+                    .youtube.com\tTRUE\t/\tTRUE\t0\t__Secure-3PAPISID\tnew\n";
+        assert!(check(text).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_missing_login() {
+        assert!(matches!(check(""), Err(CookieError::NotSignedIn(_))));
+        let text = ".youtube.com\tTRUE\t/\tTRUE\t0\tSID\txyz\n\
+                    .google.com\tTRUE\t/\tTRUE\t0\tSAPISID\tabc\n";
+        assert!(matches!(check(text), Err(CookieError::NotSignedIn(_))));
+    }
+
+    #[test]
+    fn rejects_an_expired_login() {
+        let expired = NOW - 3 * 86_400 - 5;
+        let text = format!(
+            ".youtube.com\tTRUE\t/\tTRUE\t1000\tSAPISID\tabc\n\
+             .youtube.com\tTRUE\t/\tTRUE\t{}\t__Secure-3PAPISID\tabc\n",
+            expired
+        );
+        match check(&text) {
+            Err(CookieError::Expired { ago, .. }) => assert_eq!(ago, "3 days"),
+            result => panic!("{:?}", result),
+        }
+        // Expiring right now counts as expired
+        let text = format!(".youtube.com\tTRUE\t/\tTRUE\t{}\tSAPISID\tabc", NOW);
+        assert!(matches!(check(&text), Err(CookieError::Expired { .. })));
+    }
+
+    #[test]
+    fn describes_how_long_ago() {
+        assert_eq!(ago(-5), "0 minutes");
+        assert_eq!(ago(60), "1 minute");
+        assert_eq!(ago(3599), "59 minutes");
+        assert_eq!(ago(7200), "2 hours");
+        assert_eq!(ago(86_400), "1 day");
+    }
+
+    #[test]
+    fn builds_the_header_from_youtube_cookies() {
+        let text = ".youtube.com\tTRUE\t/\tTRUE\t0\tSAPISID\tabc\n\
+                    #HttpOnly_.youtube.com\tTRUE\t/\tTRUE\t0\tSID\txyz\n\
+                    .google.com\tTRUE\t/\tTRUE\t0\tNID\tg\n\
+                    .youtube.com\tTRUE\t/\tTRUE\t1000\tOLD\to\n";
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, text.as_bytes()).unwrap();
+        let cookies = Cookies::load(file.path()).unwrap();
+        assert_eq!(cookies.path(), file.path());
+        assert_eq!(cookies.cookies().len(), 4);
+        assert_eq!(cookies.header(), "SAPISID=abc; SID=xyz");
+    }
+}
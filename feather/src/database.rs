@@ -1,8 +1,12 @@
-// This file manages the history database and contains all necessary functions related to history management
-use crate::{ArtistName, SongId, SongName};
+// This file manages the history, playlist, session, resume, favorites, track settings, preferences, scrobble and lyrics databases and contains all necessary functions related to them
+use crate::lyrics::Lyrics;
+use crate::queue::{Queue, Repeat};
+use crate::schema::{self, Migration, SchemaError, Versioned};
+use crate::scrobble::{Listen, ScrobbleService};
+use crate::{ArtistName, PlaylistId, PlaylistName, SongId, SongName};
 use serde::{Deserialize, Serialize};
-use sled::Db;
-use std::path::PathBuf;
+use sled::{Db, Tree};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
@@ -13,6 +17,7 @@ pub struct HistoryEntry {
     pub song_id: SongId,              // Unique identifier for the song
     pub artist_name: Vec<ArtistName>, // List of artists associated with the song
     time_stamp: u64,                  // Timestamp when the song was played
+    pub scrobbled: bool,              // Whether this play was submitted to every scrobbling service
 }
 
 impl HistoryEntry {
@@ -28,8 +33,43 @@ impl HistoryEntry {
             song_id,
             artist_name,
             time_stamp,
+            scrobbled: false,
         })
     }
+
+    /// Returns when the song was played, in seconds since the Unix epoch.
+    pub fn time_stamp(&self) -> u64 {
+        self.time_stamp
+    }
+}
+
+impl Versioned for HistoryEntry {
+    const NAME: &'static str = "history entry";
+    // v1: versioned envelope around the original layout
+    // v2: `scrobbled` flag
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged, history_add_scrobbled];
+}
+
+// Layout of `HistoryEntry` before v2
+#[derive(Deserialize)]
+struct HistoryEntryV1 {
+    song_name: SongName,
+    song_id: SongId,
+    artist_name: Vec<ArtistName>,
+    time_stamp: u64,
+}
+
+// Adds the `scrobbled` flag, off for existing entries
+fn history_add_scrobbled(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: HistoryEntryV1 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    bincode::serialize(&HistoryEntry {
+        song_name: old.song_name,
+        song_id: old.song_id,
+        artist_name: old.artist_name,
+        time_stamp: old.time_stamp,
+        scrobbled: false,
+    })
+    .map_err(|e| e.to_string())
 }
 
 /// Database handler for managing song history.
@@ -44,12 +84,15 @@ pub enum HistoryError {
     DbError(#[from] sled::Error), // Errors related to the sled database
     #[error("Serialization error: {0}")]
     SerializationError(#[from] bincode::Error), // Errors during serialization/deserialization
+    #[error("Schema error: {0}")]
+    SchemaError(#[from] SchemaError), // Errors reading or migrating versioned records
     #[error("Basic error: {0}")]
     Error(Box<dyn std::error::Error>), // Generic error wrapper
 }
 
 impl HistoryDB {
-    pub fn new() -> Result<Self, sled::Error> {
+    /// Opens the history database and migrates old entries to the current version.
+    pub fn new() -> Result<Self, HistoryError> {
         let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
         path.push("Feather/history_db");
 
@@ -59,6 +102,7 @@ impl HistoryDB {
             .use_compression(true)
             .open()?;
 
+        schema::migrate::<HistoryEntry>(&db)?;
         Ok(HistoryDB { db })
     }
 
@@ -66,7 +110,7 @@ impl HistoryDB {
     /// Limits the total stored entries to 50.
     pub fn add_entry(&self, entry: &HistoryEntry) -> Result<(), HistoryError> {
         let key = entry.song_id.as_bytes();
-        let value = bincode::serialize(entry)?;
+        let value = schema::encode(entry)?;
         self.db.insert(key, value)?;
         self.limit_history_size(50)?;
         Ok(())
@@ -88,9 +132,7 @@ impl HistoryDB {
         let mut history = Vec::with_capacity(self.db.len().min(50)); // Pre-allocate vector
         for item in self.db.iter().take(50) {
             let (_, value) = item?;
-            if let Ok(entry) = bincode::deserialize::<HistoryEntry>(&value) {
-                history.push(entry);
-            }
+            history.push(schema::decode::<HistoryEntry>(&value)?);
         }
         history.sort_unstable_by(|e1, e2| e2.time_stamp.cmp(&e1.time_stamp)); // Sort by timestamp descending
         Ok(history)
@@ -108,185 +150,1073 @@ impl HistoryDB {
         Ok(())
     }
 
+    /// Marks the entry of a song as scrobbled, unless the song was played again after
+    /// `listened_at` and that newer play is still to be submitted.
+    pub fn mark_scrobbled(&self, song_id: &str, listened_at: u64) -> Result<(), HistoryError> {
+        let Some(value) = self.db.get(song_id.as_bytes())? else {
+            return Ok(());
+        };
+        let mut entry = schema::decode::<HistoryEntry>(&value)?;
+        if entry.time_stamp <= listened_at {
+            entry.scrobbled = true;
+            self.db
+                .insert(song_id.as_bytes(), schema::encode(&entry)?)?;
+        }
+        Ok(())
+    }
+
     /// Retrieves the most recently played song's ID, if available.
     pub fn get_last_played_song(&self) -> Result<Option<SongId>, HistoryError> {
-        if let Some((_, last_entry)) = self.db.last()? {
-            let entry: HistoryEntry = bincode::deserialize(&last_entry)?;
-            Ok(Some(entry.song_id))
-        } else {
-            Ok(None)
+        // Entries are keyed by song ID, so the newest one has to be found by timestamp
+        Ok(self.get_history()?.into_iter().next().map(|e| e.song_id))
+    }
+}
+
+/// A song stored in a local playlist.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SongEntry {
+    pub song_name: SongName,          // Name of the song
+    pub song_id: SongId,              // Unique identifier for the song
+    pub artist_name: Vec<ArtistName>, // List of artists associated with the song
+}
+
+impl SongEntry {
+    /// Creates a new song entry.
+    pub fn new(song_name: SongName, song_id: SongId, artist_name: Vec<ArtistName>) -> Self {
+        Self {
+            song_name,
+            song_id,
+            artist_name,
+        }
+    }
+}
+
+/// A playlist saved locally, optionally copied from a YouTube Music playlist.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserPlaylist {
+    pub playlist_name: PlaylistName, // Name of the playlist
+    pub source: Option<PlaylistId>,  // Remote playlist this was copied from, if any
+    pub songs: Vec<SongEntry>,       // Ordered songs of the playlist
+    last_synced: Vec<SongEntry>,     // Remote tracks as seen on the last sync
+    pub gapless: bool,               // Play without crossfading, e.g. live or classical albums
+}
+
+impl UserPlaylist {
+    /// Creates an empty local playlist.
+    pub fn new(playlist_name: PlaylistName) -> Self {
+        Self {
+            playlist_name,
+            source: None,
+            songs: Vec::new(),
+            last_synced: Vec::new(),
+            gapless: false,
+        }
+    }
+
+    /// Creates a local copy of a remote playlist, keeping the remote order.
+    pub fn from_remote(
+        playlist_name: PlaylistName,
+        playlist_id: PlaylistId,
+        songs: Vec<SongEntry>,
+    ) -> Self {
+        Self {
+            playlist_name,
+            source: Some(playlist_id),
+            last_synced: songs.clone(),
+            songs,
+            gapless: false,
+        }
+    }
+}
+
+impl Versioned for UserPlaylist {
+    const NAME: &'static str = "playlist";
+    // v1: versioned envelope around the original layout
+    // v2: `gapless` flag
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged, playlist_add_gapless];
+}
+
+// Layout of `UserPlaylist` before v2
+#[derive(Deserialize)]
+struct UserPlaylistV1 {
+    playlist_name: PlaylistName,
+    source: Option<PlaylistId>,
+    songs: Vec<SongEntry>,
+    last_synced: Vec<SongEntry>,
+}
+
+// Adds the `gapless` flag, off for existing playlists
+fn playlist_add_gapless(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: UserPlaylistV1 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    bincode::serialize(&UserPlaylist {
+        playlist_name: old.playlist_name,
+        source: old.source,
+        songs: old.songs,
+        last_synced: old.last_synced,
+        gapless: false,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Tracks added to or removed from a remote playlist since the last sync.
+#[derive(Debug, Default, Clone)]
+pub struct PlaylistDiff {
+    pub added: Vec<SongEntry>,   // Present remotely but not at the last sync
+    pub removed: Vec<SongEntry>, // Present at the last sync but gone remotely
+}
+
+impl PlaylistDiff {
+    /// Computes the difference between the last synced snapshot and the current remote tracks.
+    pub fn between(last_synced: &[SongEntry], remote: &[SongEntry]) -> Self {
+        let added = remote
+            .iter()
+            .filter(|song| !last_synced.iter().any(|s| s.song_id == song.song_id))
+            .cloned()
+            .collect();
+        let removed = last_synced
+            .iter()
+            .filter(|song| !remote.iter().any(|s| s.song_id == song.song_id))
+            .cloned()
+            .collect();
+        Self { added, removed }
+    }
+
+    /// Returns true if the remote playlist has not changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Represents possible errors that can occur in playlist operations.
+#[derive(Error, Debug)]
+pub enum PlaylistManagerError {
+    #[error("Database error: {0}")]
+    DbError(#[from] sled::Error),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] bincode::Error),
+    #[error("Schema error: {0}")]
+    SchemaError(#[from] SchemaError),
+    #[error("Playlist '{0}' not found")]
+    PlaylistNotFound(String),
+    #[error("Duplicate playlist name: '{0}'")]
+    DuplicatePlaylist(String),
+    #[error("Playlist '{0}' has no remote source")]
+    NoSource(String),
+}
+
+/// Database handler for managing local playlists.
+pub struct PlaylistManager {
+    db: Db, // Sled database instance
+}
+
+impl PlaylistManager {
+    /// Opens the playlist database and migrates old playlists to the current version.
+    pub fn new() -> Result<Self, PlaylistManagerError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/playlist_db");
+        Self::open(&path)
+    }
+
+    // Opens the playlist database at `path`, e.g. a temporary one in tests
+    fn open(path: &Path) -> Result<Self, PlaylistManagerError> {
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(256 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<UserPlaylist>(&db)?;
+        Ok(PlaylistManager { db })
+    }
+
+    /// Creates an empty playlist with the given name.
+    pub fn create_playlist(&self, name: &str) -> Result<(), PlaylistManagerError> {
+        self.insert_new(&UserPlaylist::new(name.to_string()))
+    }
+
+    /// Creates a playlist from the given songs.
+    pub fn save_playlist(
+        &self,
+        name: &str,
+        songs: Vec<SongEntry>,
+    ) -> Result<(), PlaylistManagerError> {
+        let mut playlist = UserPlaylist::new(name.to_string());
+        playlist.songs = songs;
+        self.insert_new(&playlist)
+    }
+
+    /// Saves a copy of a remote playlist under its own name.
+    /// Fails if a local playlist with that name already exists.
+    pub fn save_remote_playlist(
+        &self,
+        name: &str,
+        playlist_id: PlaylistId,
+        songs: Vec<SongEntry>,
+    ) -> Result<(), PlaylistManagerError> {
+        self.insert_new(&UserPlaylist::from_remote(
+            name.to_string(),
+            playlist_id,
+            songs,
+        ))
+    }
+
+    fn insert_new(&self, playlist: &UserPlaylist) -> Result<(), PlaylistManagerError> {
+        if self.db.get(&playlist.playlist_name)?.is_some() {
+            return Err(PlaylistManagerError::DuplicatePlaylist(
+                playlist.playlist_name.clone(),
+            ));
+        }
+        self.put(playlist)
+    }
+
+    fn put(&self, playlist: &UserPlaylist) -> Result<(), PlaylistManagerError> {
+        let value = schema::encode(playlist)?;
+        self.db.insert(playlist.playlist_name.as_bytes(), value)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Appends a song to a playlist, moving it to the end if already present.
+    pub fn add_song_to_playlist(
+        &self,
+        playlist_name: &str,
+        song: SongEntry,
+    ) -> Result<(), PlaylistManagerError> {
+        let mut playlist = self.get_playlist(playlist_name)?;
+        playlist.songs.retain(|s| s.song_id != song.song_id);
+        playlist.songs.push(song);
+        self.put(&playlist)
+    }
+
+    /// Removes a song from a playlist.
+    pub fn remove_song_from_playlist(
+        &self,
+        playlist_name: &str,
+        song_id: &str,
+    ) -> Result<(), PlaylistManagerError> {
+        let mut playlist = self.get_playlist(playlist_name)?;
+        playlist.songs.retain(|s| s.song_id != song_id);
+        self.put(&playlist)
+    }
+
+    /// Marks a playlist as gapless so it is played without crossfading.
+    pub fn set_gapless(
+        &self,
+        playlist_name: &str,
+        gapless: bool,
+    ) -> Result<(), PlaylistManagerError> {
+        let mut playlist = self.get_playlist(playlist_name)?;
+        playlist.gapless = gapless;
+        self.put(&playlist)
+    }
+
+    /// Retrieves a playlist by name.
+    pub fn get_playlist(&self, playlist_name: &str) -> Result<UserPlaylist, PlaylistManagerError> {
+        let data = self
+            .db
+            .get(playlist_name)?
+            .ok_or_else(|| PlaylistManagerError::PlaylistNotFound(playlist_name.to_string()))?;
+        Ok(schema::decode(&data)?)
+    }
+
+    /// Retrieves all local playlists, sorted by name.
+    pub fn get_playlists(&self) -> Result<Vec<UserPlaylist>, PlaylistManagerError> {
+        let mut playlists = Vec::with_capacity(self.db.len());
+        for item in self.db.iter() {
+            let (_, value) = item?;
+            playlists.push(schema::decode::<UserPlaylist>(&value)?);
+        }
+        Ok(playlists) // sled iterates in key order, so this is already sorted by name
+    }
+
+    /// Deletes a playlist by name.
+    pub fn delete_playlist(&self, playlist_name: &str) -> Result<(), PlaylistManagerError> {
+        self.db
+            .remove(playlist_name)?
+            .ok_or_else(|| PlaylistManagerError::PlaylistNotFound(playlist_name.to_string()))?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Shows what changed in the remote playlist since the last sync, without saving anything.
+    pub fn sync_diff(
+        &self,
+        playlist_name: &str,
+        remote: &[SongEntry],
+    ) -> Result<PlaylistDiff, PlaylistManagerError> {
+        let playlist = self.get_playlist(playlist_name)?;
+        if playlist.source.is_none() {
+            return Err(PlaylistManagerError::NoSource(playlist_name.to_string()));
+        }
+        Ok(PlaylistDiff::between(&playlist.last_synced, remote))
+    }
+
+    /// Applies the remote changes to the local playlist.
+    /// Added tracks are appended, removed tracks are dropped and local-only edits are kept.
+    pub fn apply_sync(
+        &self,
+        playlist_name: &str,
+        remote: Vec<SongEntry>,
+    ) -> Result<PlaylistDiff, PlaylistManagerError> {
+        let mut playlist = self.get_playlist(playlist_name)?;
+        if playlist.source.is_none() {
+            return Err(PlaylistManagerError::NoSource(playlist_name.to_string()));
+        }
+        let diff = PlaylistDiff::between(&playlist.last_synced, &remote);
+        playlist
+            .songs
+            .retain(|s| !diff.removed.iter().any(|r| r.song_id == s.song_id));
+        for song in &diff.added {
+            if !playlist.songs.iter().any(|s| s.song_id == song.song_id) {
+                playlist.songs.push(song.clone());
+            }
+        }
+        playlist.last_synced = remote;
+        self.put(&playlist)?;
+        Ok(diff)
+    }
+}
+
+/// Everything needed to pick up playback where the last session left off.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SessionState {
+    pub song: Option<SongEntry>, // Song that was loaded
+    pub position: f64,           // Playback position in seconds
+    pub queue: Queue,            // Play queue and its cursor
+    pub volume: f64,             // Player volume
+    pub pane: String,            // Name of the last active pane
+}
+
+impl Versioned for SessionState {
+    const NAME: &'static str = "session";
+    // v2: `gapless` flag of the queue
+    // v3: shuffled play order of the queue
+    const MIGRATIONS: &'static [Migration] = &[
+        schema::unchanged,
+        session_add_gapless,
+        session_add_shuffle_order,
+    ];
+}
+
+// Layout of `SessionState` before v2
+#[derive(Deserialize)]
+struct SessionStateV1 {
+    song: Option<SongEntry>,
+    position: f64,
+    queue: (Vec<SongEntry>, Option<usize>), // Songs and cursor of the queue
+    volume: f64,
+    pane: String,
+}
+
+// Adds the `gapless` flag to the saved queue, off for existing sessions
+fn session_add_gapless(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: SessionStateV1 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    let (songs, cursor) = old.queue;
+    bincode::serialize(&SessionStateV2 {
+        song: old.song,
+        position: old.position,
+        queue: (songs, cursor, false),
+        volume: old.volume,
+        pane: old.pane,
+    })
+    .map_err(|e| e.to_string())
+}
+
+// Layout of `SessionState` before v3
+#[derive(Serialize, Deserialize)]
+struct SessionStateV2 {
+    song: Option<SongEntry>,
+    position: f64,
+    queue: (Vec<SongEntry>, Option<usize>, bool), // Songs, cursor and gapless flag of the queue
+    volume: f64,
+    pane: String,
+}
+
+// Adds the shuffled play order to the saved queue, unshuffled for existing sessions
+fn session_add_shuffle_order(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: SessionStateV2 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    let (songs, cursor, gapless) = old.queue;
+    bincode::serialize(&SessionState {
+        song: old.song,
+        position: old.position,
+        queue: Queue::from_parts(songs, cursor, gapless),
+        volume: old.volume,
+        pane: old.pane,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Represents possible errors that can occur in session, resume position, favorites, track settings,
+/// preferences, scrobble queue and lyrics operations.
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Database error: {0}")]
+    DbError(#[from] sled::Error),
+    #[error("Schema error: {0}")]
+    SchemaError(#[from] SchemaError),
+}
+
+/// Database handler for saving and restoring the last session.
+pub struct SessionDB {
+    db: Db, // Sled database instance
+}
+
+impl SessionDB {
+    const KEY: &'static str = "last_session";
+
+    /// Opens the session database and migrates the stored session to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/session_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(64 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<SessionState>(&db)?;
+        Ok(SessionDB { db })
+    }
+
+    /// Saves the session, replacing the previous one.
+    pub fn save(&self, session: &SessionState) -> Result<(), SessionError> {
+        self.db.insert(Self::KEY, schema::encode(session)?)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Loads the last saved session, if any.
+    pub fn load(&self) -> Result<Option<SessionState>, SessionError> {
+        match self.db.get(Self::KEY)? {
+            Some(data) => Ok(Some(schema::decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Where a long track was left off.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResumePosition {
+    pub song: SongEntry, // The partly played song
+    pub position: f64,   // Playback position in seconds
+    pub duration: f64,   // Length of the song in seconds
+    time_stamp: u64,     // When the position was saved
+}
+
+impl ResumePosition {
+    /// Creates a resume position saved at the current time.
+    pub fn new(song: SongEntry, position: f64, duration: f64) -> Self {
+        let time_stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self {
+            song,
+            position,
+            duration,
+            time_stamp,
+        }
+    }
+}
+
+impl Versioned for ResumePosition {
+    const NAME: &'static str = "resume position";
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged];
+}
+
+/// Database handler for per-track resume positions.
+pub struct ResumeDB {
+    db: Db, // Sled database instance
+}
+
+impl ResumeDB {
+    /// Opens the resume database and migrates old positions to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/resume_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(64 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<ResumePosition>(&db)?;
+        Ok(ResumeDB { db })
+    }
+
+    /// Saves the position of a song, replacing any previous one.
+    pub fn set(&self, position: &ResumePosition) -> Result<(), SessionError> {
+        self.db
+            .insert(position.song.song_id.as_bytes(), schema::encode(position)?)?;
+        Ok(())
+    }
+
+    /// Retrieves the saved position of a song, if any.
+    pub fn get(&self, song_id: &str) -> Result<Option<ResumePosition>, SessionError> {
+        match self.db.get(song_id.as_bytes())? {
+            Some(data) => Ok(Some(schema::decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Forgets the position of a song.
+    pub fn clear(&self, song_id: &str) -> Result<(), SessionError> {
+        self.db.remove(song_id.as_bytes())?;
+        Ok(())
+    }
+
+    /// Retrieves all partly played songs, most recently played first.
+    pub fn get_all(&self) -> Result<Vec<ResumePosition>, SessionError> {
+        let mut positions = Vec::with_capacity(self.db.len());
+        for item in self.db.iter() {
+            let (_, value) = item?;
+            positions.push(schema::decode::<ResumePosition>(&value)?);
+        }
+        positions.sort_unstable_by(|p1, p2| p2.time_stamp.cmp(&p1.time_stamp));
+        Ok(positions)
+    }
+}
+
+/// A song marked as favorite.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Favorite {
+    pub song: SongEntry, // The liked song
+    time_stamp: u64,     // When the song was liked
+}
+
+impl Versioned for Favorite {
+    const NAME: &'static str = "favorite";
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged];
+}
+
+/// Database handler for favorite songs.
+pub struct FavoritesDB {
+    db: Db, // Sled database instance
+}
+
+impl FavoritesDB {
+    /// Opens the favorites database and migrates old entries to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/favorites_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(128 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<Favorite>(&db)?;
+        Ok(FavoritesDB { db })
+    }
+
+    /// Returns true if the song is a favorite.
+    pub fn is_favorite(&self, song_id: &str) -> bool {
+        self.db.contains_key(song_id.as_bytes()).unwrap_or(false)
+    }
+
+    /// Adds the song to favorites, or removes it if it already is one.
+    /// Returns true if the song is now a favorite.
+    pub fn toggle(&self, song: SongEntry) -> Result<bool, SessionError> {
+        if self.db.remove(song.song_id.as_bytes())?.is_some() {
+            self.db.flush()?;
+            return Ok(false);
+        }
+        let time_stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let favorite = Favorite { song, time_stamp };
+        self.db
+            .insert(favorite.song.song_id.as_bytes(), schema::encode(&favorite)?)?;
+        self.db.flush()?;
+        Ok(true)
+    }
+
+    /// Retrieves all favorite songs, most recently liked first.
+    pub fn get_all(&self) -> Result<Vec<SongEntry>, SessionError> {
+        let mut favorites = Vec::with_capacity(self.db.len());
+        for item in self.db.iter() {
+            let (_, value) = item?;
+            favorites.push(schema::decode::<Favorite>(&value)?);
+        }
+        favorites.sort_unstable_by(|f1, f2| f2.time_stamp.cmp(&f1.time_stamp));
+        Ok(favorites.into_iter().map(|f| f.song).collect())
+    }
+}
+
+/// Settings remembered for a single track.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TrackSettings {
+    pub speed: Option<f64>, // Playback speed, if changed
+    pub loops: Vec<AbLoop>, // Saved A-B loops, in the order they were saved
+}
+
+/// A named section of a track that is played over and over.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AbLoop {
+    pub name: String, // Name given by the user
+    pub a: f64,       // Start of the loop in seconds
+    pub b: f64,       // End of the loop in seconds
+}
+
+impl Versioned for TrackSettings {
+    const NAME: &'static str = "track settings";
+    // v1: versioned envelope around the original layout
+    // v2: saved A-B loops
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged, track_settings_add_loops];
+}
+
+// Layout of `TrackSettings` before v2
+#[derive(Deserialize)]
+struct TrackSettingsV1 {
+    speed: Option<f64>,
+}
+
+// Adds the list of saved A-B loops, empty for existing tracks
+fn track_settings_add_loops(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: TrackSettingsV1 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    bincode::serialize(&TrackSettings {
+        speed: old.speed,
+        loops: Vec::new(),
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Database handler for per-track settings.
+pub struct TrackSettingsDB {
+    db: Db, // Sled database instance
+}
+
+impl TrackSettingsDB {
+    /// Opens the track settings database and migrates old entries to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/track_settings_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(64 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<TrackSettings>(&db)?;
+        Ok(TrackSettingsDB { db })
+    }
+
+    /// Retrieves the settings of a track, or the defaults if none were saved.
+    pub fn get(&self, song_id: &str) -> Result<TrackSettings, SessionError> {
+        match self.db.get(song_id.as_bytes())? {
+            Some(data) => Ok(schema::decode(&data)?),
+            None => Ok(TrackSettings::default()),
+        }
+    }
+
+    /// Changes the settings of a track.
+    pub fn update(
+        &self,
+        song_id: &str,
+        change: impl FnOnce(&mut TrackSettings),
+    ) -> Result<(), SessionError> {
+        let mut settings = self.get(song_id)?;
+        change(&mut settings);
+        self.db
+            .insert(song_id.as_bytes(), schema::encode(&settings)?)?;
+        Ok(())
+    }
+}
+
+/// Choices made in the app that are kept across restarts.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Preferences {
+    pub audio_device: Option<String>, // Chosen audio output device, `None` for the default
+    pub repeat: Repeat,               // Repeat mode of the player
+    pub shuffle: bool,                // Whether queues play in shuffled order
+}
+
+impl Versioned for Preferences {
+    const NAME: &'static str = "preferences";
+    // v2: repeat and shuffle modes
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged, preferences_add_play_modes];
+}
+
+// Layout of `Preferences` before v2
+#[derive(Deserialize)]
+struct PreferencesV1 {
+    audio_device: Option<String>,
+}
+
+// Adds the repeat and shuffle modes, both off for existing preferences
+fn preferences_add_play_modes(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let old: PreferencesV1 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+    bincode::serialize(&Preferences {
+        audio_device: old.audio_device,
+        ..Preferences::default()
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Database handler for preferences.
+pub struct PreferencesDB {
+    db: Db, // Sled database instance
+}
+
+impl PreferencesDB {
+    const KEY: &'static str = "preferences";
+
+    /// Opens the preferences database and migrates the stored preferences to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/preferences_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(64 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<Preferences>(&db)?;
+        Ok(PreferencesDB { db })
+    }
+
+    /// Loads the preferences, or the defaults if none were saved.
+    pub fn load(&self) -> Result<Preferences, SessionError> {
+        match self.db.get(Self::KEY)? {
+            Some(data) => Ok(schema::decode(&data)?),
+            None => Ok(Preferences::default()),
+        }
+    }
+
+    /// Changes the stored preferences.
+    pub fn update(&self, change: impl FnOnce(&mut Preferences)) -> Result<(), SessionError> {
+        let mut preferences = self.load()?;
+        change(&mut preferences);
+        self.db.insert(Self::KEY, schema::encode(&preferences)?)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+/// A listen still to be submitted to a scrobbling service.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingScrobble {
+    pub service: ScrobbleService, // Where the listen goes
+    pub listen: Listen,           // What was heard
+    pub attempts: u32,            // Failed submissions so far
+    pub next_attempt: u64,        // Unix timestamp before which it is not retried
+}
+
+impl Versioned for PendingScrobble {
+    const NAME: &'static str = "pending scrobble";
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged];
+}
+
+/// Database handler for listens that could not be submitted yet, kept in the order they
+/// were heard.
+pub struct ScrobbleDB {
+    db: Db,        // Sled database instance, also holding session keys
+    pending: Tree, // Queued listens keyed by a big-endian sequence number
+}
+
+impl ScrobbleDB {
+    const SESSION_KEY_PREFIX: &'static str = "session_key:";
+
+    /// Opens the scrobble database and migrates pending listens to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/scrobble_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(64 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        let pending = db.open_tree("pending")?;
+        schema::migrate::<PendingScrobble>(&pending)?;
+        Ok(ScrobbleDB { db, pending })
+    }
+
+    /// Queues a listen and returns its key.
+    pub fn push(&self, scrobble: &PendingScrobble) -> Result<u64, SessionError> {
+        let key = self.db.generate_id()?;
+        self.pending
+            .insert(key.to_be_bytes(), schema::encode(scrobble)?)?;
+        self.db.flush()?;
+        Ok(key)
+    }
+
+    /// Replaces a queued listen, e.g. after a failed attempt.
+    pub fn update(&self, key: u64, scrobble: &PendingScrobble) -> Result<(), SessionError> {
+        self.pending
+            .insert(key.to_be_bytes(), schema::encode(scrobble)?)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Removes a listen once it was submitted or rejected.
+    pub fn remove(&self, key: u64) -> Result<(), SessionError> {
+        self.pending.remove(key.to_be_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Retrieves all queued listens with their keys, oldest first.
+    pub fn pending(&self) -> Result<Vec<(u64, PendingScrobble)>, SessionError> {
+        let mut pending = Vec::new();
+        for item in self.pending.iter() {
+            let (key, value) = item?;
+            let key = key
+                .as_ref()
+                .try_into()
+                .map(u64::from_be_bytes)
+                .unwrap_or_default();
+            pending.push((key, schema::decode(&value)?));
         }
+        Ok(pending)
+    }
+
+    /// Retrieves the session key kept for a service, if any.
+    pub fn session_key(&self, service: ScrobbleService) -> Result<Option<String>, SessionError> {
+        let key = format!("{}{:?}", Self::SESSION_KEY_PREFIX, service);
+        Ok(self
+            .db
+            .get(key)?
+            .map(|value| String::from_utf8_lossy(&value).into_owned()))
+    }
+
+    /// Keeps the session key of a service.
+    pub fn set_session_key(
+        &self,
+        service: ScrobbleService,
+        session_key: &str,
+    ) -> Result<(), SessionError> {
+        let key = format!("{}{:?}", Self::SESSION_KEY_PREFIX, service);
+        self.db.insert(key, session_key.as_bytes())?;
+        self.db.flush()?;
+        Ok(())
     }
+
+    /// Forgets the session key of a service, e.g. once the service no longer accepts it.
+    pub fn clear_session_key(&self, service: ScrobbleService) -> Result<(), SessionError> {
+        let key = format!("{}{:?}", Self::SESSION_KEY_PREFIX, service);
+        self.db.remove(key)?;
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+impl Versioned for Lyrics {
+    const NAME: &'static str = "lyrics";
+    const MIGRATIONS: &'static [Migration] = &[schema::unchanged];
 }
 
-// Unchanged UserPlaylist and PlaylistManager sections...
-// #[derive(Serialize, Deserialize, Debug, Clone)]
-// struct UserPlaylist {
-//     playlist_name: PlaylistName,
-//     songs: Vec<Song>,
-// }
-
-// #[derive(Error, Debug)]
-// pub enum PlaylistManagerError {
-//     #[error("Database error: {0}")]
-//     DbError(#[from] sled::Error),
-//     #[error("Serialization error: {0}")]
-//     SerializationError(#[from] bincode::Error),
-//     #[error("Playlist '{0}' not found")]
-//     PlaylistNotFound(String),
-//     #[error("Song '{0}' not found in playlist '{1}'")]
-//     SongNotFound(String, String),
-//     #[error("Duplicate playlist name: '{0}'")]
-//     DuplicatePlaylist(String),
-//     #[error("Failed to add song '{0}' to playlist '{1}'")]
-//     AddSongError(String, String),
-//     #[error("Failed to remove song '{0}' from playlist '{1}'")]
-//     RemoveSongError(String, String),
-//     #[error("Unknown error: {0}")]
-//     Other(String),
-// }
-
-// #[derive(Serialize, Deserialize, Debug, Clone)]
-// struct Song {
-//     song_name: SongName,
-//     song_id: SongId,
-//     artist: Vec<ArtistName>,
-// }
-
-// struct PlaylistManager {
-//     db: sled::Db,
-// }
-
-// impl PlaylistManager {
-//     pub fn new(path: &str) -> Result<Self, PlaylistManagerError> {
-//         let db = sled::open(path)?;
-//         Ok(Self { db })
-//     }
-//     fn create_playlist(&self, name: &str) -> Result<(), PlaylistManagerError> {
-//         if self.db.get(name)?.is_some() {
-//             return Err(PlaylistManagerError::DuplicatePlaylist(name.to_string()));
-//         }
-//         let playlist = UserPlaylist {
-//             playlist_name: name.to_string(),
-//             songs: Vec::new(),
-//         };
-//         let value = bincode::serialize(&playlist)?;
-//         self.db.insert(name, value)?;
-//         self.db.flush()?;
-//         Ok(())
-//     }
-//     fn add_song_to_playlist(
-//         &self,
-//         playlist_name: &str,
-//         song: Song,
-//     ) -> Result<(), PlaylistManagerError> {
-//         let raw_data = self
-//             .db
-//             .get(playlist_name)?
-//             .ok_or_else(|| PlaylistManagerError::Other("Error: In Opening Playlist".to_string()))?
-//             .to_vec();
-
-//         let mut playlist: UserPlaylist = bincode::deserialize(&raw_data)?;
-
-//         playlist.songs.retain(|s| s.song_id != song.song_id);
-//         playlist.songs.push(song);
-
-//         let serialized_data = bincode::serialize(&playlist)?;
-//         self.db.insert(playlist_name, serialized_data)?;
-//         self.db.flush()?;
-
-//         Ok(())
-//     }
-//     fn remove_song_from_playlist(
-//         &self,
-//         playlist_name: &str,
-//         song_id: &str,
-//     ) -> Result<(), PlaylistManagerError> {
-//         let raw_data = self
-//             .db
-//             .get(playlist_name)?
-//             .ok_or_else(|| PlaylistManagerError::Other("Error: In Opening Playlist".to_string()))?
-//             .to_vec();
-
-//         let mut playlist: UserPlaylist = bincode::deserialize(&raw_data)?;
-
-//         playlist.songs.retain(|s| s.song_id != song_id);
-//         let serialized_data = bincode::serialize(&playlist)?;
-//         self.db.insert(playlist_name, serialized_data)?;
-//         self.db.flush()?;
-
-//         Ok(())
-//     }
-
-//     fn get_playlist(&self, playlist_name: &str) -> Result<UserPlaylist, PlaylistManagerError> {
-//         let data = self
-//             .db
-//             .get(playlist_name)?
-//             .ok_or_else(|| PlaylistManagerError::PlaylistNotFound(playlist_name.to_string()))?
-//             .to_vec();
-//         let playlist: UserPlaylist = bincode::deserialize(&data)?;
-//         Ok(playlist)
-//     }
-//     fn delete_playlist(&self, playlist_name: &str) -> Result<(), PlaylistManagerError> {
-//         self.db
-//             .remove(&playlist_name)?
-//             .ok_or_else(|| PlaylistManagerError::PlaylistNotFound(playlist_name.to_string()));
-//         self.db.flush()?;
-//         Ok(())
-//     }
-// }
-
-// // Tests unchanged...
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use tempfile::tempdir;
-
-//     fn sample_song(name: &str, id: &str) -> Song {
-//         Song {
-//             song_name: name.to_string(),
-//             song_id: id.to_string(),
-//             artist: vec!["Artist One".to_string(), "Artist Two".to_string()],
-//         }
-//     }
-
-//     #[test]
-//     fn test_playlist_manager() {
-//         let temp_dir = tempdir().unwrap();
-//         let db_path = temp_dir.path().to_str().unwrap();
-//         let manager = PlaylistManager::new(db_path).unwrap();
-
-//         let playlist_name = "MyPlaylist";
-
-//         assert!(manager.create_playlist(playlist_name).is_ok());
-
-//         let song1 = sample_song("Song A", "123");
-//         let song2 = sample_song("Song B", "456");
-
-//         assert!(manager
-//             .add_song_to_playlist(playlist_name, song1.clone())
-//             .is_ok());
-//         assert!(manager
-//             .add_song_to_playlist(playlist_name, song2.clone())
-//             .is_ok());
-
-//         let playlist = manager.get_playlist(playlist_name).unwrap();
-//         assert_eq!(playlist.songs.len(), 2);
-//         assert!(playlist.songs.iter().any(|s| s.song_id == "123"));
-//         assert!(playlist.songs.iter().any(|s| s.song_id == "456"));
-
-//         assert!(manager
-//             .remove_song_from_playlist(playlist_name, "123")
-//             .is_ok());
-//         let playlist = manager.get_playlist(playlist_name).unwrap();
-//         assert_eq!(playlist.songs.len(), 1);
-//         assert!(playlist.songs.iter().all(|s| s.song_id != "123"));
-
-//         assert!(manager.delete_playlist(playlist_name).is_ok());
-//         let result = manager.get_playlist(playlist_name);
-//         assert!(matches!(
-//             result,
-//             Err(PlaylistManagerError::PlaylistNotFound(_))
-//         ));
-//     }
-// }
+/// Database handler for lyrics fetched before, so they show without a connection. A song
+/// found to have no lyrics is kept as lyrics without lines.
+pub struct LyricsDB {
+    db: Db, // Sled database instance
+}
+
+impl LyricsDB {
+    /// Opens the lyrics database and migrates cached lyrics to the current version.
+    pub fn new() -> Result<Self, SessionError> {
+        let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/lyrics_db");
+
+        let db = sled::Config::new()
+            .path(path)
+            .cache_capacity(256 * 1024)
+            .use_compression(true)
+            .open()?;
+
+        schema::migrate::<Lyrics>(&db)?;
+        Ok(LyricsDB { db })
+    }
+
+    /// Saves the lyrics of a song, replacing any cached ones.
+    pub fn set(&self, song_id: &str, lyrics: &Lyrics) -> Result<(), SessionError> {
+        self.db
+            .insert(song_id.as_bytes(), schema::encode(lyrics)?)?;
+        Ok(())
+    }
+
+    /// Retrieves the cached lyrics of a song, if any.
+    pub fn get(&self, song_id: &str) -> Result<Option<Lyrics>, SessionError> {
+        match self.db.get(song_id.as_bytes())? {
+            Some(data) => Ok(Some(schema::decode(&data)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn sample_song(name: &str, id: &str) -> SongEntry {
+        SongEntry::new(
+            name.to_string(),
+            id.to_string(),
+            vec!["Artist One".to_string(), "Artist Two".to_string()],
+        )
+    }
+
+    fn ids(songs: &[SongEntry]) -> Vec<&str> {
+        songs.iter().map(|song| song.song_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_playlist_manager() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PlaylistManager::open(temp_dir.path()).unwrap();
+
+        let playlist_name = "MyPlaylist";
+
+        assert!(manager.create_playlist(playlist_name).is_ok());
+        assert!(matches!(
+            manager.create_playlist(playlist_name),
+            Err(PlaylistManagerError::DuplicatePlaylist(_))
+        ));
+
+        let song1 = sample_song("Song A", "123");
+        let song2 = sample_song("Song B", "456");
+
+        assert!(
+            manager
+                .add_song_to_playlist(playlist_name, song1.clone())
+                .is_ok()
+        );
+        assert!(
+            manager
+                .add_song_to_playlist(playlist_name, song2.clone())
+                .is_ok()
+        );
+
+        let playlist = manager.get_playlist(playlist_name).unwrap();
+        assert_eq!(playlist.songs.len(), 2);
+        assert!(playlist.songs.iter().any(|s| s.song_id == "123"));
+        assert!(playlist.songs.iter().any(|s| s.song_id == "456"));
+
+        // Adding a song again moves it to the end
+        assert!(manager.add_song_to_playlist(playlist_name, song1).is_ok());
+        let playlist = manager.get_playlist(playlist_name).unwrap();
+        assert_eq!(ids(&playlist.songs), ["456", "123"]);
+
+        assert!(
+            manager
+                .remove_song_from_playlist(playlist_name, "123")
+                .is_ok()
+        );
+        let playlist = manager.get_playlist(playlist_name).unwrap();
+        assert_eq!(playlist.songs.len(), 1);
+        assert!(playlist.songs.iter().all(|s| s.song_id != "123"));
+
+        assert!(manager.delete_playlist(playlist_name).is_ok());
+        let result = manager.get_playlist(playlist_name);
+        assert!(matches!(
+            result,
+            Err(PlaylistManagerError::PlaylistNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn diffs_against_the_last_sync() {
+        let last_synced = [sample_song("A", "a"), sample_song("B", "b")];
+        let remote = [sample_song("B", "b"), sample_song("C", "c")];
+        let diff = PlaylistDiff::between(&last_synced, &remote);
+        assert_eq!(ids(&diff.added), ["c"]);
+        assert_eq!(ids(&diff.removed), ["a"]);
+        assert!(!diff.is_empty());
+        assert!(PlaylistDiff::between(&remote, &remote).is_empty());
+    }
+
+    #[test]
+    fn syncs_remote_additions_and_removals() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PlaylistManager::open(temp_dir.path()).unwrap();
+        let remote = vec![sample_song("A", "a"), sample_song("B", "b")];
+        manager
+            .save_remote_playlist("Mix", "PL1".to_string(), remote)
+            .unwrap();
+
+        // Added remotely
+        let remote = vec![
+            sample_song("A", "a"),
+            sample_song("B", "b"),
+            sample_song("C", "c"),
+        ];
+        let diff = manager.sync_diff("Mix", &remote).unwrap();
+        assert_eq!(ids(&diff.added), ["c"]);
+        assert!(diff.removed.is_empty());
+        // Looking at the diff saves nothing
+        assert_eq!(ids(&manager.get_playlist("Mix").unwrap().songs), ["a", "b"]);
+        manager.apply_sync("Mix", remote).unwrap();
+        assert_eq!(
+            ids(&manager.get_playlist("Mix").unwrap().songs),
+            ["a", "b", "c"]
+        );
+
+        // Removed remotely
+        let remote = vec![sample_song("A", "a"), sample_song("C", "c")];
+        let diff = manager.apply_sync("Mix", remote.clone()).unwrap();
+        assert!(diff.added.is_empty());
+        assert_eq!(ids(&diff.removed), ["b"]);
+        assert_eq!(ids(&manager.get_playlist("Mix").unwrap().songs), ["a", "c"]);
+
+        // Nothing changed since
+        assert!(manager.sync_diff("Mix", &remote).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_local_edits_on_sync() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PlaylistManager::open(temp_dir.path()).unwrap();
+        let remote = vec![sample_song("A", "a"), sample_song("B", "b")];
+        manager
+            .save_remote_playlist("Mix", "PL1".to_string(), remote.clone())
+            .unwrap();
+        manager
+            .add_song_to_playlist("Mix", sample_song("Local", "local"))
+            .unwrap();
+        manager
+            .add_song_to_playlist("Mix", sample_song("Both", "both"))
+            .unwrap();
+        manager.remove_song_from_playlist("Mix", "a").unwrap();
+
+        // The remote playlist gains a song that was added locally as well
+        let mut remote = remote;
+        remote.push(sample_song("Both", "both"));
+        let diff = manager.apply_sync("Mix", remote).unwrap();
+        assert_eq!(ids(&diff.added), ["both"]);
+        // The song removed locally stays removed, as it did not change remotely
+        assert_eq!(
+            ids(&manager.get_playlist("Mix").unwrap().songs),
+            ["b", "local", "both"]
+        );
+    }
+
+    #[test]
+    fn refuses_to_sync_a_local_playlist() {
+        let temp_dir = tempdir().unwrap();
+        let manager = PlaylistManager::open(temp_dir.path()).unwrap();
+        manager
+            .save_playlist("Local", vec![sample_song("A", "a")])
+            .unwrap();
+        assert!(matches!(
+            manager.sync_diff("Local", &[]),
+            Err(PlaylistManagerError::NoSource(_))
+        ));
+        assert!(matches!(
+            manager.apply_sync("Local", Vec::new()),
+            Err(PlaylistManagerError::NoSource(_))
+        ));
+        assert_eq!(ids(&manager.get_playlist("Local").unwrap().songs), ["a"]);
+        assert!(matches!(
+            manager.apply_sync("Missing", Vec::new()),
+            Err(PlaylistManagerError::PlaylistNotFound(_))
+        ));
+    }
+}
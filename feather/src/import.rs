@@ -0,0 +1,488 @@
+// This file imports track lists from other services by fuzzy matching each entry on YouTube Music
+use crate::database::SongEntry;
+use crate::yt::YoutubeClient;
+use crate::{ArtistName, SongName};
+use std::future::Future;
+use thiserror::Error;
+
+/// Matches scoring below this are listed for manual review.
+pub const LOW_CONFIDENCE: f64 = 0.75;
+
+/// Number of candidates kept per imported track.
+const MAX_CANDIDATES: usize = 5;
+
+/// Duration difference (in seconds) at which the duration score reaches zero.
+const MAX_DURATION_DIFF: f64 = 30.0;
+
+/// A track read from an import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportTrack {
+    pub title: SongName,       // Title of the track
+    pub artist: ArtistName,    // Artist(s) as written in the file
+    pub album: Option<String>, // Album, if the file has one
+    pub duration: Option<u32>, // Duration in seconds, if the file has one
+}
+
+impl ImportTrack {
+    /// Builds the query used to search for this track.
+    pub fn query(&self) -> String {
+        format!("{} {}", self.artist, self.title)
+    }
+}
+
+/// A search result scored against an imported track.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub song: SongEntry,       // The matched song
+    pub duration: Option<u32>, // Duration in seconds, if known
+    pub score: f64,            // Match confidence between 0 and 1
+}
+
+/// The candidates found for a single imported track.
+#[derive(Debug, Clone)]
+pub struct TrackMatch {
+    pub track: ImportTrack,         // The imported track
+    pub candidates: Vec<Candidate>, // Candidates sorted by score, best first
+    pub chosen: Option<usize>,      // Index of the chosen candidate, `None` to skip the track
+}
+
+impl TrackMatch {
+    /// Returns the chosen candidate, if any.
+    pub fn chosen(&self) -> Option<&Candidate> {
+        self.chosen.and_then(|i| self.candidates.get(i))
+    }
+
+    /// Returns true if the match should be confirmed by the user.
+    pub fn is_low_confidence(&self) -> bool {
+        self.candidates
+            .first()
+            .is_none_or(|best| best.score < LOW_CONFIDENCE)
+    }
+}
+
+/// Represents possible errors that can occur while reading an import file.
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("CSV file has no '{0}' column")]
+    MissingColumn(&'static str),
+    #[error("No tracks found in file")]
+    Empty,
+}
+
+/// A source of search results, so matching can run against something other than YouTube Music.
+pub trait SearchSource {
+    /// Searches for tracks, returning each with its duration in seconds, if known.
+    fn search_tracks(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<(SongEntry, Option<u32>)>, String>> + Send;
+}
+
+impl SearchSource for YoutubeClient {
+    fn search_tracks(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<(SongEntry, Option<u32>)>, String>> + Send {
+        YoutubeClient::search_tracks(self, query)
+    }
+}
+
+impl<T: SearchSource + Sync> SearchSource for &T {
+    fn search_tracks(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<(SongEntry, Option<u32>)>, String>> + Send {
+        (**self).search_tracks(query)
+    }
+}
+
+/// Reads tracks from a file, choosing the format by extension.
+/// `.csv` files are read as CSV exports, anything else as "Artist - Title" lines.
+pub fn read_tracks(path: &std::path::Path) -> Result<Vec<ImportTrack>, ImportError> {
+    let content = std::fs::read_to_string(path)?;
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let tracks = if is_csv {
+        parse_csv(&content)?
+    } else {
+        parse_text(&content)
+    };
+    if tracks.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    Ok(tracks)
+}
+
+/// Parses a CSV export with title, artist, album and duration columns.
+/// Column names from Spotify exports ("Track Name", "Artist Name(s)", "Duration (ms)") are accepted too.
+pub fn parse_csv(content: &str) -> Result<Vec<ImportTrack>, ImportError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+
+    let title = column(&["title", "track name", "track", "name"])
+        .ok_or(ImportError::MissingColumn("title"))?;
+    let artist = column(&["artist", "artists", "artist name", "artist name(s)"])
+        .ok_or(ImportError::MissingColumn("artist"))?;
+    let album = column(&["album", "album name"]);
+    let duration = column(&["duration", "duration (ms)", "duration_ms"]);
+    let duration_in_ms = duration.is_some_and(|i| headers[i].contains("ms"));
+
+    let mut tracks = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).map(str::trim).filter(|f| !f.is_empty());
+        let (Some(title), Some(artist)) = (field(title), field(artist)) else {
+            continue;
+        };
+        tracks.push(ImportTrack {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: album.and_then(field).map(str::to_string),
+            duration: duration
+                .and_then(field)
+                .and_then(|d| parse_duration(d, duration_in_ms)),
+        });
+    }
+    Ok(tracks)
+}
+
+/// Parses a text file of "Artist - Title" lines, skipping blank lines and `#` comments.
+pub fn parse_text(content: &str) -> Vec<ImportTrack> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (artist, title) = line.split_once(" - ")?;
+            Some(ImportTrack {
+                title: title.trim().to_string(),
+                artist: artist.trim().to_string(),
+                album: None,
+                duration: None,
+            })
+        })
+        .collect()
+}
+
+// Parses "m:ss", "h:mm:ss", seconds or milliseconds into seconds
+fn parse_duration(value: &str, in_ms: bool) -> Option<u32> {
+    if value.contains(':') {
+        // Checked, as hostile files may hold values such as "99999999:99"
+        return value.split(':').try_fold(0u32, |total, part| {
+            total.checked_mul(60)?.checked_add(part.parse().ok()?)
+        });
+    }
+    let value = value.parse::<u32>().ok()?;
+    Some(if in_ms { value / 1000 } else { value })
+}
+
+/// Matches imported tracks against a search source.
+pub struct Importer<S: SearchSource> {
+    source: S, // Where candidates are searched
+}
+
+impl<S: SearchSource> Importer<S> {
+    /// Creates a new importer using the given search source.
+    pub fn new(source: S) -> Self {
+        Self { source }
+    }
+
+    /// Searches for a single track and scores the results.
+    /// The best candidate is chosen unless nothing was found.
+    pub async fn match_track(&self, track: ImportTrack) -> Result<TrackMatch, String> {
+        let results = self.source.search_tracks(&track.query()).await?;
+        let mut candidates: Vec<Candidate> = results
+            .into_iter()
+            .map(|(song, duration)| Candidate {
+                score: score(&track, &song, duration),
+                song,
+                duration,
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        candidates.truncate(MAX_CANDIDATES);
+        let chosen = (!candidates.is_empty()).then_some(0);
+        Ok(TrackMatch {
+            track,
+            candidates,
+            chosen,
+        })
+    }
+
+    /// Matches every track in order, calling `progress` after each one.
+    /// Tracks whose search fails are kept with no candidates so they show up for review.
+    pub async fn match_tracks(
+        &self,
+        tracks: Vec<ImportTrack>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Vec<TrackMatch> {
+        let total = tracks.len();
+        let mut matches = Vec::with_capacity(total);
+        for (i, track) in tracks.into_iter().enumerate() {
+            let track_match = match self.match_track(track.clone()).await {
+                Ok(track_match) => track_match,
+                Err(_) => TrackMatch {
+                    track,
+                    candidates: Vec::new(),
+                    chosen: None,
+                },
+            };
+            matches.push(track_match);
+            progress(i + 1, total);
+        }
+        matches
+    }
+}
+
+/// Collects the chosen songs of all matches, in order and without duplicates.
+pub fn chosen_songs(matches: &[TrackMatch]) -> Vec<SongEntry> {
+    let mut songs: Vec<SongEntry> = Vec::with_capacity(matches.len());
+    for candidate in matches.iter().filter_map(TrackMatch::chosen) {
+        if !songs.iter().any(|s| s.song_id == candidate.song.song_id) {
+            songs.push(candidate.song.clone());
+        }
+    }
+    songs
+}
+
+/// Scores how well a search result matches an imported track, between 0 and 1.
+/// Title similarity weighs the most, then artist similarity, then duration difference.
+pub fn score(track: &ImportTrack, song: &SongEntry, duration: Option<u32>) -> f64 {
+    let title = similarity(&track.title, &song.song_name);
+    let artist = similarity(&track.artist, &song.artist_name.join(", "));
+    match (track.duration, duration) {
+        (Some(expected), Some(actual)) => {
+            let diff = (expected as f64 - actual as f64).abs();
+            let duration = 1.0 - (diff / MAX_DURATION_DIFF).min(1.0);
+            0.55 * title + 0.3 * artist + 0.15 * duration
+        }
+        _ => 0.65 * title + 0.35 * artist,
+    }
+}
+
+/// Compares two strings ignoring case, punctuation and word order, returning a value between 0 and 1.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = normalize(a);
+    let b = normalize(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    // Token overlap handles reordered artists and extra words like "Remastered"
+    let tokens_a: Vec<&str> = a.split(' ').collect();
+    let tokens_b: Vec<&str> = b.split(' ').collect();
+    let common = tokens_a.iter().filter(|t| tokens_b.contains(t)).count();
+    let overlap = common as f64 / tokens_a.len().max(tokens_b.len()) as f64;
+
+    // Edit distance handles small spelling differences
+    let distance = levenshtein(&a, &b);
+    let edit = 1.0 - distance as f64 / a.chars().count().max(b.chars().count()) as f64;
+
+    overlap.max(edit)
+}
+
+// Lowercases, drops bracketed parts such as "(feat. X)" and keeps only alphanumeric words
+fn normalize(value: &str) -> String {
+    let mut depth = 0usize;
+    let mut cleaned = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => (),
+            c if c.is_alphanumeric() => cleaned.extend(c.to_lowercase()),
+            _ => cleaned.push(' '),
+        }
+    }
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Computes the Levenshtein distance between two strings
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Search source answering every query with the same results
+    struct FakeSource {
+        results: Vec<(SongEntry, Option<u32>)>, // Results of every search
+    }
+
+    impl SearchSource for FakeSource {
+        fn search_tracks(
+            &self,
+            _query: &str,
+        ) -> impl Future<Output = Result<Vec<(SongEntry, Option<u32>)>, String>> + Send {
+            let results = self.results.clone();
+            async move {
+                if results.is_empty() {
+                    Err("no results".to_string())
+                } else {
+                    Ok(results)
+                }
+            }
+        }
+    }
+
+    fn song(id: &str, title: &str, artist: &str) -> SongEntry {
+        SongEntry::new(title.to_string(), id.to_string(), vec![artist.to_string()])
+    }
+
+    fn track(artist: &str, title: &str, duration: Option<u32>) -> ImportTrack {
+        ImportTrack {
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: None,
+            duration,
+        }
+    }
+
+    #[test]
+    fn parses_spotify_csv() {
+        let csv = "Track Name,Artist Name(s),Album Name,Duration (ms)\n\
+                   One More Time,Daft Punk,Discovery,320357\n\
+                   ,Nobody,,1000\n\
+                   Digital Love,Daft Punk,,\n";
+        let tracks = parse_csv(csv).unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].title, "One More Time");
+        assert_eq!(tracks[0].album.as_deref(), Some("Discovery"));
+        assert_eq!(tracks[0].duration, Some(320));
+        assert_eq!(tracks[1].duration, None);
+    }
+
+    #[test]
+    fn rejects_csv_without_title() {
+        let result = parse_csv("artist,album\nDaft Punk,Discovery\n");
+        assert!(matches!(result, Err(ImportError::MissingColumn("title"))));
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("3:25", false), Some(205));
+        assert_eq!(parse_duration("1:02:03", false), Some(3723));
+        assert_eq!(parse_duration("205", false), Some(205));
+        assert_eq!(parse_duration("205000", true), Some(205));
+        assert_eq!(parse_duration("99999999:99", false), None);
+        assert_eq!(parse_duration("x:10", false), None);
+    }
+
+    #[test]
+    fn parses_text_lines() {
+        let tracks = parse_text("# Road trip\n\nDaft Punk - One More Time\nno separator\n");
+        assert_eq!(tracks, vec![track("Daft Punk", "One More Time", None)]);
+    }
+
+    #[test]
+    fn similarity_ignores_case_order_and_brackets() {
+        assert_eq!(
+            similarity("One More Time", "one more time (Remastered)"),
+            1.0
+        );
+        assert_eq!(
+            similarity("Daft Punk, Romanthony", "Romanthony, Daft Punk"),
+            1.0
+        );
+        assert!(similarity("Digital Love", "Digitl Love") > 0.9);
+        assert!(similarity("Digital Love", "Something Else") < 0.5);
+        assert_eq!(similarity("", "x"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn chooses_best_match() {
+        let importer = Importer::new(FakeSource {
+            results: vec![
+                (song("a", "One More Time (Live)", "Tribute Band"), Some(300)),
+                (song("b", "One More Time", "Daft Punk"), Some(321)),
+            ],
+        });
+        let found = importer
+            .match_track(track("Daft Punk", "One More Time", Some(320)))
+            .await
+            .unwrap();
+        assert_eq!(found.chosen().unwrap().song.song_id, "b");
+        assert!(!found.is_low_confidence());
+    }
+
+    #[tokio::test]
+    async fn flags_ambiguous_match() {
+        let importer = Importer::new(FakeSource {
+            results: vec![
+                (song("a", "Time", "Pink Floyd"), None),
+                (song("b", "One More Try", "George Michael"), None),
+            ],
+        });
+        let found = importer
+            .match_track(track("Daft Punk", "One More Time", None))
+            .await
+            .unwrap();
+        assert!(found.chosen.is_some());
+        assert!(found.is_low_confidence());
+    }
+
+    #[tokio::test]
+    async fn keeps_unmatched_tracks_for_review() {
+        let importer = Importer::new(FakeSource {
+            results: Vec::new(),
+        });
+        let mut progress = Vec::new();
+        let matches = importer
+            .match_tracks(vec![track("Nobody", "Nothing", None)], |done, total| {
+                progress.push((done, total))
+            })
+            .await;
+        assert_eq!(progress, vec![(1, 1)]);
+        assert!(matches[0].candidates.is_empty());
+        assert!(matches[0].chosen().is_none());
+        assert!(matches[0].is_low_confidence());
+        assert!(chosen_songs(&matches).is_empty());
+    }
+
+    #[test]
+    fn chosen_songs_skips_duplicates() {
+        let candidate = |id: &str| Candidate {
+            song: song(id, "Title", "Artist"),
+            duration: None,
+            score: 1.0,
+        };
+        let matched = |id: &str| TrackMatch {
+            track: track("Artist", "Title", None),
+            candidates: vec![candidate(id)],
+            chosen: Some(0),
+        };
+        let songs = chosen_songs(&[matched("a"), matched("b"), matched("a")]);
+        let ids: Vec<&str> = songs.iter().map(|s| s.song_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod config;
+pub mod cookies;
 pub mod database;
+pub mod import;
+pub mod lyrics;
 pub mod player;
+pub mod queue;
+pub mod schema;
+pub mod scrobble;
+pub mod sleep;
 pub mod yt;
 
 /// Input/Return Types
@@ -0,0 +1,153 @@
+// This file parses song lyrics and finds the line being sung at a playback position
+use serde::{Deserialize, Serialize};
+
+/// Lyrics of a song, synced if its lines carry timestamps.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Lyrics {
+    pub lines: Vec<LyricLine>, // Lines in the order they are sung
+    pub source: String,        // Where the lyrics come from, e.g. "Source: Musixmatch"
+}
+
+/// A line of lyrics.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LyricLine {
+    pub time: Option<f64>, // Seconds into the song at which the line starts, if synced
+    pub text: String,      // The line itself, empty between verses
+}
+
+impl Lyrics {
+    /// Parses lyrics as served by YouTube Music. When lines start with LRC timestamps such as
+    /// `[01:23.45]` the lyrics are synced: untimed lines and tags such as `[ar:Artist]` are
+    /// dropped, and a line with several timestamps is sung at each of them.
+    pub fn parse(body: &str, source: &str) -> Self {
+        let mut timed = Vec::new();
+        let mut plain = Vec::new();
+        for line in body.lines() {
+            let (times, text) = split_timestamps(line);
+            if times.is_empty() {
+                plain.push(LyricLine {
+                    time: None,
+                    text: line.trim_end().to_string(),
+                });
+            }
+            for time in times {
+                timed.push(LyricLine {
+                    time: Some(time),
+                    text: text.trim().to_string(),
+                });
+            }
+        }
+
+        let lines = if timed.is_empty() {
+            plain
+        } else {
+            timed.sort_by(|a, b| a.time.unwrap_or(0.0).total_cmp(&b.time.unwrap_or(0.0)));
+            timed
+        };
+        Self {
+            lines,
+            source: source.trim().to_string(),
+        }
+    }
+
+    /// Returns whether the lines carry timestamps.
+    pub fn is_synced(&self) -> bool {
+        self.lines.iter().any(|line| line.time.is_some())
+    }
+
+    /// Returns the index of the line being sung at `position` seconds, if the lyrics are
+    /// synced and the first line has started.
+    pub fn current_line(&self, position: f64) -> Option<usize> {
+        if !self.is_synced() {
+            return None;
+        }
+        let started = self
+            .lines
+            .partition_point(|line| line.time.is_some_and(|time| time <= position));
+        started.checked_sub(1)
+    }
+}
+
+// Splits the leading timestamps off a line, e.g. "[00:12.30][01:02.00]Text"
+fn split_timestamps(line: &str) -> (Vec<f64>, &str) {
+    let mut times = Vec::new();
+    let mut rest = line.trim_start();
+    while let Some(tag) = rest.strip_prefix('[') {
+        let Some((stamp, after)) = tag.split_once(']') else {
+            break;
+        };
+        let Some(time) = parse_timestamp(stamp) else {
+            break;
+        };
+        times.push(time);
+        rest = after;
+    }
+    (times, rest)
+}
+
+// Parses "mm:ss", "mm:ss.xx" or "mm:ss:xx" into seconds
+fn parse_timestamp(stamp: &str) -> Option<f64> {
+    let (minutes, seconds) = stamp.split_once(':')?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    // Some files separate the hundredths with a colon
+    let seconds: f64 = seconds.trim().replacen(':', ".", 1).parse().ok()?;
+    (0.0..60.0)
+        .contains(&seconds)
+        .then_some(minutes as f64 * 60.0 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lyrics: &Lyrics) -> Vec<&str> {
+        lyrics.lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_plain_lyrics() {
+        let lyrics = Lyrics::parse("First line  \n\nSecond line\n", "  Source: Musixmatch\n");
+        assert!(!lyrics.is_synced());
+        assert_eq!(texts(&lyrics), ["First line", "", "Second line"]);
+        assert!(lyrics.lines.iter().all(|line| line.time.is_none()));
+        assert_eq!(lyrics.source, "Source: Musixmatch");
+    }
+
+    #[test]
+    fn parses_synced_lyrics() {
+        let body = "[ar:Artist]\n\
+                    [00:12.30] First\n\
+                    [00:20.00][01:02.00]Chorus\n\
+                    Untimed\n\
+                    [00:30:50]Colon\n\
+                    [01:70.00]Bad seconds";
+        let lyrics = Lyrics::parse(body, "");
+        assert!(lyrics.is_synced());
+        assert_eq!(texts(&lyrics), ["First", "Chorus", "Colon", "Chorus"]);
+        let times: Vec<_> = lyrics.lines.iter().map(|line| line.time).collect();
+        assert_eq!(times, [Some(12.3), Some(20.0), Some(30.5), Some(62.0)]);
+    }
+
+    #[test]
+    fn finds_the_current_line() {
+        let lyrics = Lyrics::parse(
+            "[00:10.00]One\n[00:20.00]Two\n[00:20.00]Too\n[01:00]Three",
+            "",
+        );
+        assert_eq!(lyrics.current_line(0.0), None);
+        assert_eq!(lyrics.current_line(9.99), None);
+        assert_eq!(lyrics.current_line(10.0), Some(0));
+        assert_eq!(lyrics.current_line(19.0), Some(0));
+        // Of lines sung at the same time, the last one is current
+        assert_eq!(lyrics.current_line(20.0), Some(2));
+        assert_eq!(lyrics.current_line(600.0), Some(3));
+    }
+
+    #[test]
+    fn has_no_current_line_without_timestamps() {
+        let lyrics = Lyrics::parse("One\nTwo", "");
+        assert_eq!(lyrics.current_line(0.0), None);
+        assert_eq!(lyrics.current_line(100.0), None);
+        assert_eq!(Lyrics::default().current_line(10.0), None);
+    }
+}
@@ -1,12 +1,82 @@
 use libmpv2::Mpv; // We are not using libmpv library because it was requiring user to install an old version which was not available in many distros so we decided to opt for libmpv2 which is a fork of it
-use std::sync::Arc;
+use serde::Deserialize;
+use std::f64::consts::FRAC_PI_2;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// The `Player` struct represents a media player using the MPV library.
 /// It provides functionalities to control playback, retrieve metadata,
 /// and manage audio optimizations.
+///
+/// It owns two MPV instances ("decks") so the next track can be preloaded on the idle deck
+/// and mixed in while the current one fades out. All playback methods act on the active deck.
 pub struct Player {
-    /// An instance of the MPV player wrapped in an `Arc` for thread safety.
-    pub player: Arc<Mpv>,
+    decks: [Arc<Mpv>; 2],    // MPV instances, one playing and one idle or preloaded
+    active: AtomicUsize,     // Index of the deck playing the current track
+    volume: Arc<Mutex<f64>>, // Volume chosen by the user (0-100), scaled per deck while fading
+    fade: Arc<AtomicU64>,    // Generation of the crossfade in progress, bumped to cancel it
+}
+
+/// Longest supported crossfade in seconds.
+pub const MAX_CROSSFADE_SECS: f64 = 12.0;
+
+/// Interval between volume changes while crossfading.
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// Slowest supported playback speed.
+pub const MIN_SPEED: f64 = 0.25;
+/// Fastest supported playback speed.
+pub const MAX_SPEED: f64 = 4.0;
+
+/// Loudness YouTube normalizes streams to; `loudness_db` values are relative to it.
+pub const YOUTUBE_REFERENCE_LUFS: f64 = -14.0;
+
+/// An audio output device reported by mpv.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioDevice {
+    pub name: String, // Value for the `audio-device` property, e.g. "pulse/alsa_output..."
+    pub description: String, // Human readable name
+}
+
+/// A seek target for [`Player::seek`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seek {
+    /// Move by a number of seconds, backwards if negative.
+    Relative(f64),
+    /// Jump to a timestamp in seconds.
+    Absolute(f64),
+    /// Jump to a percentage (0-100) of the track.
+    Percent(f64),
+    /// Jump to the next chapter.
+    ChapterNext,
+    /// Jump to the previous chapter.
+    ChapterPrevious,
+}
+
+impl Seek {
+    /// Parses a "go to" target: "90", "1:30", "1:02:03" or "50%".
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(percent) = input.strip_suffix('%') {
+            let percent: f64 = percent.trim().parse().ok()?;
+            return (0.0..=100.0)
+                .contains(&percent)
+                .then_some(Seek::Percent(percent));
+        }
+        let mut seconds = 0.0;
+        for part in input.split(':') {
+            let value: f64 = part.trim().parse().ok()?;
+            // "inf" and "nan" parse as floats but are no place in a track
+            if !value.is_finite() || value < 0.0 {
+                return None;
+            }
+            seconds = seconds * 60.0 + value;
+        }
+        seconds.is_finite().then_some(Seek::Absolute(seconds))
+    }
 }
 
 /// Enum representing possible errors when interacting with the MPV player.
@@ -28,11 +98,27 @@ pub enum MpvError {
 
 impl Player {
     /// Creates a new `Player` instance and configures MPV settings for optimized audio playback.
-    pub fn new(cookies: Option<String>) -> Result<Self, MpvError> {
+    /// Requests send the cookies in `cookies_file`, a Netscape cookies.txt, if given.
+    pub fn new(cookies_file: Option<&Path>) -> Result<Self, MpvError> {
+        let decks = [
+            Arc::new(Self::new_deck(cookies_file)?),
+            Arc::new(Self::new_deck(cookies_file)?),
+        ];
+        Ok(Self {
+            decks,
+            active: AtomicUsize::new(0),
+            volume: Arc::new(Mutex::new(100.0)),
+            fade: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    // Creates one MPV instance configured for audio playback
+    fn new_deck(cookies_file: Option<&Path>) -> Result<Mpv, MpvError> {
         let mpv = Mpv::new()?;
-        if cookies.is_some() {
-            // setting cookies  if given by user
-            mpv.set_property("cookies-file", cookies.unwrap())?;
+        if let Some(cookies_file) = cookies_file {
+            // mpv ignores the cookies file unless cookies are turned on
+            mpv.set_property("cookies", "yes")?;
+            mpv.set_property("cookies-file", &*cookies_file.to_string_lossy())?;
         }
 
         // Disable video to save memory
@@ -45,7 +131,7 @@ impl Player {
 
         // Configure network request headers for YouTube playback
         mpv.set_property("ytdl-raw-options", "no-check-certificate=")?;
-        mpv.set_property("loop", "inf")?; // Looping enabled (to be removed with autoplay)
+        mpv.set_property("keep-open", "yes")?; // Stay on the last frame so the end can be detected
         mpv.set_property(
             "http-header-fields",
             "User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64)",
@@ -54,35 +140,122 @@ impl Player {
         // Audio optimization
         mpv.set_property("audio-buffer", 0.1)?; // 100ms audio buffer
         mpv.set_property("audio-channels", "stereo")?; // Force stereo audio
+        mpv.set_property("audio-pitch-correction", true)?; // Keep pitch when changing speed (scaletempo)
+
+        Ok(mpv)
+    }
 
-        let mpv = Arc::new(mpv);
-        Ok(Self { player: mpv })
+    // Returns the deck playing the current track
+    fn mpv(&self) -> &Mpv {
+        &self.decks[self.active.load(Ordering::SeqCst)]
+    }
+
+    // Returns the deck that is idle or holds the preloaded track
+    fn idle(&self) -> &Mpv {
+        &self.decks[1 - self.active.load(Ordering::SeqCst)]
     }
 
     /// Loads and plays a media file from a given URL.
     pub fn play(&self, url: &str) -> Result<(), MpvError> {
-         if let Ok(true) = self.player.get_property("pause") {
+        self.play_at(url, None, false)
+    }
+
+    /// Loads a media file, optionally starting at `start` seconds and/or paused.
+    pub fn play_at(&self, url: &str, start: Option<f64>, paused: bool) -> Result<(), MpvError> {
+        self.cancel_crossfade()?;
+        // `start` and `pause` carry over to later files, so both are set on every load
+        let start = start.map_or_else(|| "none".to_string(), |s| format!("{:.3}", s));
+        self.mpv().set_property("start", start)?;
+        // A-B points also carry over and would not fit the new file
+        Self::set_deck_ab_loop(self.mpv(), None, None)?;
+        if paused {
+            self.pause()?;
+        } else if let Ok(true) = self.mpv().get_property("pause") {
             self.unpause()?;
-        } // Quick fix will improve 
-        self.player.command("loadfile", &[url])?; // Replace the current playback
+        }
+        self.mpv().command("loadfile", &[url])?; // Replace the current playback
+        Ok(())
+    }
+
+    /// Loads the next track paused on the idle deck so a crossfade can start without delay.
+    pub fn preload(&self, url: &str) -> Result<(), MpvError> {
+        let idle = self.idle();
+        idle.set_property("start", "none")?;
+        idle.set_property("pause", true)?;
+        idle.set_property("volume", 0.0)?;
+        Self::set_deck_ab_loop(idle, None, None)?;
+        idle.command("loadfile", &[url])?;
+        Ok(())
+    }
+
+    /// Starts the preloaded track at `speed` with a gain of `gain_db` and fades it in over
+    /// `secs` seconds while the current one fades out at its own speed and gain.
+    /// The preloaded track becomes the current one right away.
+    pub fn start_crossfade(&self, secs: f64, speed: f64, gain_db: f64) -> Result<(), MpvError> {
+        let generation = self.fade.fetch_add(1, Ordering::SeqCst) + 1;
+        let outgoing = self.active.load(Ordering::SeqCst);
+        let incoming = 1 - outgoing;
+        // Set up before it is heard, so the track does not jump in speed or loudness
+        let deck = &self.decks[incoming];
+        deck.set_property("speed", speed.clamp(MIN_SPEED, MAX_SPEED))?;
+        deck.set_property("volume-gain", gain_db.clamp(-96.0, 12.0))?;
+        deck.set_property("pause", false)?;
+        self.active.store(incoming, Ordering::SeqCst);
+
+        let decks = self.decks.clone();
+        let volume = Arc::clone(&self.volume);
+        let fade = Arc::clone(&self.fade);
+        let secs = secs.clamp(0.0, MAX_CROSSFADE_SECS);
+        thread::spawn(move || {
+            let started = Instant::now();
+            loop {
+                if fade.load(Ordering::SeqCst) != generation {
+                    return; // Cancelled, e.g. another track was started
+                }
+                let progress = if secs > 0.0 {
+                    (started.elapsed().as_secs_f64() / secs).min(1.0)
+                } else {
+                    1.0
+                };
+                // Equal-power curves keep the overall loudness steady through the fade
+                let volume = volume.lock().map(|v| *v).unwrap_or(100.0);
+                let angle = progress * FRAC_PI_2;
+                let _ = decks[outgoing].set_property("volume", volume * angle.cos());
+                let _ = decks[incoming].set_property("volume", volume * angle.sin());
+                if progress >= 1.0 {
+                    break;
+                }
+                thread::sleep(FADE_STEP);
+            }
+            let _ = decks[outgoing].command("stop", &[]);
+        });
+        Ok(())
+    }
+
+    // Stops a crossfade in progress and anything loaded on the idle deck,
+    // leaving the active deck at the user's volume
+    fn cancel_crossfade(&self) -> Result<(), MpvError> {
+        self.fade.fetch_add(1, Ordering::SeqCst);
+        self.idle().command("stop", &[])?;
+        self.mpv().set_property("volume", self.volume())?;
         Ok(())
     }
 
     /// Pauses playback.
     pub fn pause(&self) -> Result<(), MpvError> {
-        self.player.command("set", &["pause", "yes"])?;
+        self.mpv().command("set", &["pause", "yes"])?;
         Ok(())
     }
 
     /// Resumes playback.
     pub fn unpause(&self) -> Result<(), MpvError> {
-        self.player.command("set", &["pause", "no"])?;
+        self.mpv().command("set", &["pause", "no"])?;
         Ok(())
     }
 
     /// Toggles between play and pause states.
     pub fn play_pause(&self) -> Result<(), MpvError> {
-        match self.player.get_property::<bool>("pause") {
+        match self.mpv().get_property::<bool>("pause") {
             Ok(true) => self.unpause()?,
             Ok(false) => self.pause()?,
             Err(_) => todo!(),
@@ -90,21 +263,41 @@ impl Player {
         Ok(())
     }
 
+    /// Seeks within the current track.
+    pub fn seek(&self, seek: Seek) -> Result<(), MpvError> {
+        match seek {
+            Seek::Relative(seconds) => {
+                self.mpv()
+                    .command("seek", &[&seconds.to_string(), "relative"])?;
+            }
+            Seek::Absolute(seconds) => {
+                self.mpv()
+                    .command("seek", &[&seconds.to_string(), "absolute"])?;
+            }
+            Seek::Percent(percent) => {
+                let percent = percent.clamp(0.0, 100.0).to_string();
+                self.mpv()
+                    .command("seek", &[&percent, "absolute-percent"])?;
+            }
+            Seek::ChapterNext => self.mpv().command("add", &["chapter", "1"])?,
+            Seek::ChapterPrevious => self.mpv().command("add", &["chapter", "-1"])?,
+        }
+        Ok(())
+    }
+
     /// Seeks forward by 5 seconds in the current track.
     pub fn seek_forward(&self) -> Result<(), MpvError> {
-        self.player.command("seek", &["5", "relative"])?;
-        Ok(())
+        self.seek(Seek::Relative(5.0))
     }
 
     /// Seeks backward by 5 seconds in the current track.
     pub fn seek_backword(&self) -> Result<(), MpvError> {
-        self.player.command("seek", &["-5", "relative"])?;
-        Ok(())
+        self.seek(Seek::Relative(-5.0))
     }
 
     /// Retrieves the current playback time as a string.
     pub fn get_current_time(&self) -> String {
-        self.player
+        self.mpv()
             .get_property("time-pos")
             .unwrap_or(0.0)
             .to_string()
@@ -112,7 +305,7 @@ impl Player {
 
     /// Retrieves the duration of the currently playing media.
     pub fn duration(&self) -> String {
-        self.player
+        self.mpv()
             .get_property("duration")
             .unwrap_or(0.0)
             .to_string()
@@ -120,7 +313,186 @@ impl Player {
 
     /// Returns whether a media file is currently playing.
     pub fn is_playing(&self) -> Result<bool, MpvError> {
-        let pause: bool = self.player.get_property("pause")?;
+        let pause: bool = self.mpv().get_property("pause")?;
         Ok(!pause)
     }
+
+    /// Returns whether a media file is loaded, playing or paused.
+    pub fn is_loaded(&self) -> bool {
+        !self
+            .mpv()
+            .get_property::<bool>("idle-active")
+            .unwrap_or(true)
+            && self.mpv().get_property::<f64>("duration").is_ok()
+    }
+
+    /// Returns whether the current track played to its end.
+    pub fn is_finished(&self) -> bool {
+        self.mpv().get_property("eof-reached").unwrap_or(false)
+    }
+
+    /// Repeats the current track forever when `repeat` is set, otherwise plays it once.
+    pub fn set_repeat_one(&self, repeat: bool) -> Result<(), MpvError> {
+        let value = if repeat { "inf" } else { "no" };
+        for deck in &self.decks {
+            deck.set_property("loop-file", value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the A and B points of the A-B loop in seconds, each `None` if unset.
+    pub fn ab_loop(&self) -> (Option<f64>, Option<f64>) {
+        (
+            self.mpv().get_property("ab-loop-a").ok(),
+            self.mpv().get_property("ab-loop-b").ok(),
+        )
+    }
+
+    /// Returns true if both points of the A-B loop are set, so playback repeats between them.
+    pub fn is_ab_looping(&self) -> bool {
+        matches!(self.ab_loop(), (Some(_), Some(_)))
+    }
+
+    /// Sets the A and B points of the A-B loop in seconds. `None` clears a point.
+    pub fn set_ab_loop(&self, a: Option<f64>, b: Option<f64>) -> Result<(), MpvError> {
+        Self::set_deck_ab_loop(self.mpv(), a, b)
+    }
+
+    /// Clears both points of the A-B loop.
+    pub fn clear_ab_loop(&self) -> Result<(), MpvError> {
+        self.set_ab_loop(None, None)
+    }
+
+    // Sets the A-B points of one deck, where mpv takes "no" for an unset point
+    fn set_deck_ab_loop(deck: &Mpv, a: Option<f64>, b: Option<f64>) -> Result<(), MpvError> {
+        for (property, point) in [("ab-loop-a", a), ("ab-loop-b", b)] {
+            match point {
+                Some(secs) => deck.set_property(property, secs)?,
+                None => deck.set_property(property, "no")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieves the current playback position in seconds.
+    pub fn position(&self) -> f64 {
+        self.mpv().get_property("time-pos").unwrap_or(0.0)
+    }
+
+    /// Retrieves the duration of the current media in seconds, or 0 if unknown.
+    pub fn duration_seconds(&self) -> f64 {
+        self.mpv().get_property("duration").unwrap_or(0.0)
+    }
+
+    /// Retrieves the current playback speed.
+    pub fn speed(&self) -> f64 {
+        self.mpv().get_property("speed").unwrap_or(1.0)
+    }
+
+    /// Sets the playback speed, clamped to 0.25x-4x. Returns the speed that was applied.
+    pub fn set_speed(&self, speed: f64) -> Result<f64, MpvError> {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        for deck in &self.decks {
+            deck.set_property("speed", speed)?;
+        }
+        Ok(speed)
+    }
+
+    /// Replaces the audio filter chain (mpv's `af` property). An empty chain removes all filters.
+    pub fn set_audio_filters(&self, chain: &str) -> Result<(), MpvError> {
+        for deck in &self.decks {
+            deck.set_property("af", chain)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a gain so a stream with the given YouTube loudness plays at `target_lufs`.
+    /// Returns the gain in dB that was applied.
+    pub fn normalize_loudness(&self, loudness_db: f32, target_lufs: f64) -> Result<f64, MpvError> {
+        self.set_gain(Self::loudness_gain(loudness_db, target_lufs))
+    }
+
+    /// Returns the gain in dB that brings a stream with the given YouTube loudness to
+    /// `target_lufs`.
+    pub fn loudness_gain(loudness_db: f32, target_lufs: f64) -> f64 {
+        target_lufs - (YOUTUBE_REFERENCE_LUFS + f64::from(loudness_db))
+    }
+
+    /// Sets a gain in dB on top of the volume, clamped to mpv's -96..12 dB range.
+    /// Returns the gain that was applied.
+    pub fn set_gain(&self, gain_db: f64) -> Result<f64, MpvError> {
+        let gain_db = gain_db.clamp(-96.0, 12.0);
+        self.mpv().set_property("volume-gain", gain_db)?;
+        Ok(gain_db)
+    }
+
+    /// Returns the `af` filter that normalizes a stream to `target_lufs` while it plays.
+    /// Used when no loudness data is known, e.g. for local files.
+    pub fn loudnorm_filter(target_lufs: f64) -> String {
+        format!("lavfi=[loudnorm=I={}:TP=-1.5:LRA=11]", target_lufs)
+    }
+
+    /// Lists the available audio output devices. The first one is mpv's automatic choice.
+    pub fn audio_devices(&self) -> Result<Vec<AudioDevice>, MpvError> {
+        // Node properties are returned as JSON when read as a string
+        let list: String = self.mpv().get_property("audio-device-list")?;
+        serde_json::from_str(&list).map_err(|e| MpvError::PropertyError(e.to_string()))
+    }
+
+    /// Returns the name of the selected audio output device ("auto" for the default).
+    pub fn audio_device(&self) -> String {
+        self.mpv()
+            .get_property("audio-device")
+            .unwrap_or_else(|_| "auto".to_string())
+    }
+
+    /// Switches playback to another audio output device.
+    pub fn set_audio_device(&self, name: &str) -> Result<(), MpvError> {
+        for deck in &self.decks {
+            deck.set_property("audio-device", name)?;
+        }
+        Ok(())
+    }
+
+    /// Retrieves the current volume (0-100).
+    pub fn volume(&self) -> f64 {
+        self.volume.lock().map(|v| *v).unwrap_or(100.0)
+    }
+
+    /// Sets the volume, clamped to 0-100. A crossfade in progress picks it up on its next step.
+    pub fn set_volume(&self, volume: f64) -> Result<(), MpvError> {
+        let volume = volume.clamp(0.0, 100.0);
+        if let Ok(mut current) = self.volume.lock() {
+            *current = volume;
+        }
+        self.mpv().set_property("volume", volume)?;
+        Ok(())
+    }
+
+    /// Changes the volume by `delta`.
+    pub fn change_volume(&self, delta: f64) -> Result<(), MpvError> {
+        self.set_volume(self.volume() + delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_seek_targets() {
+        assert_eq!(Seek::parse("90"), Some(Seek::Absolute(90.0)));
+        assert_eq!(Seek::parse("1:30"), Some(Seek::Absolute(90.0)));
+        assert_eq!(Seek::parse("1:02:03"), Some(Seek::Absolute(3723.0)));
+        assert_eq!(Seek::parse("50%"), Some(Seek::Percent(50.0)));
+        assert_eq!(Seek::parse("150%"), None);
+        assert_eq!(Seek::parse("-5"), None);
+    }
+
+    #[test]
+    fn rejects_non_finite_seek_targets() {
+        for input in ["inf", "nan", "1:inf", "NaN%", "inf%", "1e308:1e308"] {
+            assert_eq!(Seek::parse(input), None, "{}", input);
+        }
+    }
 }
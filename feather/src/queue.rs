@@ -0,0 +1,352 @@
+// This file contains the play queue shared by the player and the session store
+use crate::database::SongEntry;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a song or the queue ends.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Repeat {
+    /// Stop at the end of the queue.
+    #[default]
+    Off,
+    /// Play the current song again.
+    One,
+    /// Start the queue over after the last song.
+    All,
+}
+
+impl Repeat {
+    /// Returns the mode that follows this one when cycling through them.
+    pub fn cycle(self) -> Self {
+        match self {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
+        }
+    }
+}
+
+/// An ordered list of songs with a cursor pointing at the one being played.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Queue {
+    songs: Vec<SongEntry>, // Songs in play order
+    cursor: Option<usize>, // Index of the current song, if any
+    gapless: bool,         // Songs flow into each other and must not be crossfaded
+    order: Vec<usize>,     // Shuffled play order as indices into `songs`, empty when not shuffled
+}
+
+impl Queue {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds an unshuffled queue from its stored parts.
+    pub(crate) fn from_parts(songs: Vec<SongEntry>, cursor: Option<usize>, gapless: bool) -> Self {
+        Self {
+            songs,
+            cursor,
+            gapless,
+            order: Vec::new(),
+        }
+    }
+
+    /// Replaces the queue with the given songs, starting at `cursor`.
+    /// The new queue is neither gapless nor shuffled.
+    pub fn set(&mut self, songs: Vec<SongEntry>, cursor: usize) {
+        self.cursor = (cursor < songs.len()).then_some(cursor);
+        self.songs = songs;
+        self.gapless = false;
+        self.order.clear();
+    }
+
+    /// Shuffles the play order. The current song stays current and every other song
+    /// plays once before any of them repeats.
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut order: Vec<usize> = (0..self.songs.len())
+            .filter(|&i| Some(i) != self.cursor)
+            .collect();
+        order.shuffle(rng);
+        if let Some(cursor) = self.cursor {
+            order.insert(0, cursor);
+        }
+        self.order = order;
+    }
+
+    /// Goes back to the original order, keeping the current song.
+    pub fn unshuffle(&mut self) {
+        self.order.clear();
+    }
+
+    /// Returns true if the queue plays in shuffled order.
+    pub fn is_shuffled(&self) -> bool {
+        !self.order.is_empty()
+    }
+
+    /// Moves the cursor to the first song in play order and returns it.
+    /// A shuffled queue is shuffled again, so each round plays in a new order.
+    pub fn restart<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Option<&SongEntry> {
+        if self.is_shuffled() {
+            self.cursor = None;
+            self.shuffle(rng);
+        }
+        let first = self.order.first().copied().unwrap_or(0);
+        self.jump(first)
+    }
+
+    // Returns the index into `songs` of the song at `position` in play order
+    fn at(&self, position: usize) -> Option<usize> {
+        if self.is_shuffled() {
+            self.order.get(position).copied()
+        } else {
+            (position < self.songs.len()).then_some(position)
+        }
+    }
+
+    // Returns the position of the current song in play order
+    fn position(&self) -> Option<usize> {
+        let cursor = self.cursor?;
+        if self.is_shuffled() {
+            self.order.iter().position(|&i| i == cursor)
+        } else {
+            Some(cursor)
+        }
+    }
+
+    /// Marks the queue as gapless, e.g. when it holds an album that must not be crossfaded.
+    pub fn set_gapless(&mut self, gapless: bool) {
+        self.gapless = gapless;
+    }
+
+    /// Returns true if the queue must be played without crossfading.
+    pub fn is_gapless(&self) -> bool {
+        self.gapless
+    }
+
+    /// Appends a song to the end of the queue.
+    pub fn push(&mut self, song: SongEntry) {
+        self.songs.push(song);
+        if self.is_shuffled() {
+            // Shuffled queues play added songs last
+            self.order.push(self.songs.len() - 1);
+        }
+    }
+
+    /// Removes the song at `index`, keeping the cursor on the same song when possible.
+    pub fn remove(&mut self, index: usize) -> Option<SongEntry> {
+        if index >= self.songs.len() {
+            return None;
+        }
+        let song = self.songs.remove(index);
+        self.order.retain(|&i| i != index);
+        for i in self.order.iter_mut() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        self.cursor = match self.cursor {
+            Some(c) if c > index => Some(c - 1),
+            Some(c) if c == index => None,
+            cursor => cursor,
+        };
+        Some(song)
+    }
+
+    /// Removes every song from the queue.
+    pub fn clear(&mut self) {
+        self.songs.clear();
+        self.cursor = None;
+        self.gapless = false;
+        self.order.clear();
+    }
+
+    /// Returns the songs in the queue.
+    pub fn songs(&self) -> &[SongEntry] {
+        &self.songs
+    }
+
+    /// Returns the index of the current song, if any.
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    /// Returns the current song, if any.
+    pub fn current(&self) -> Option<&SongEntry> {
+        self.cursor.and_then(|c| self.songs.get(c))
+    }
+
+    /// Moves the cursor to `index` and returns the song there.
+    pub fn jump(&mut self, index: usize) -> Option<&SongEntry> {
+        if index < self.songs.len() {
+            self.cursor = Some(index);
+        }
+        self.songs.get(index)
+    }
+
+    /// Returns the song after the current one without moving the cursor.
+    pub fn peek_next(&self) -> Option<&SongEntry> {
+        let next = self.position().map_or(0, |p| p + 1);
+        self.songs.get(self.at(next)?)
+    }
+
+    /// Moves the cursor to the next song and returns it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<&SongEntry> {
+        let next = self.position().map_or(0, |p| p + 1);
+        let index = self.at(next)?;
+        self.jump(index)
+    }
+
+    /// Moves the cursor to the previous song and returns it.
+    pub fn previous(&mut self) -> Option<&SongEntry> {
+        let previous = self.position()?.checked_sub(1)?;
+        let index = self.at(previous)?;
+        self.jump(index)
+    }
+
+    /// Returns the number of songs in the queue.
+    pub fn len(&self) -> usize {
+        self.songs.len()
+    }
+
+    /// Returns true if the queue has no songs.
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // Builds a queue of `count` songs with the ids "0", "1", ... starting at `cursor`
+    fn queue(count: usize, cursor: usize) -> Queue {
+        let songs = (0..count)
+            .map(|i| SongEntry::new(format!("Song {}", i), i.to_string(), Vec::new()))
+            .collect();
+        let mut queue = Queue::new();
+        queue.set(songs, cursor);
+        queue
+    }
+
+    // Returns the id of the current song
+    fn current(queue: &Queue) -> Option<&str> {
+        queue.current().map(|song| song.song_id.as_str())
+    }
+
+    // Moves to the end of the queue, returning the ids of the songs on the way
+    fn play_out(queue: &mut Queue) -> Vec<String> {
+        let mut played = Vec::new();
+        while let Some(song) = queue.next() {
+            played.push(song.song_id.clone());
+        }
+        played
+    }
+
+    #[test]
+    fn plays_in_order() {
+        let mut queue = queue(3, 0);
+        assert_eq!(
+            queue.peek_next().map(|song| song.song_id.as_str()),
+            Some("1")
+        );
+        assert_eq!(play_out(&mut queue), ["1", "2"]);
+        assert_eq!(current(&queue), Some("2"));
+        assert_eq!(
+            queue.previous().map(|song| song.song_id.as_str()),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn shuffle_keeps_current_song_and_plays_each_once() {
+        let mut queue = queue(10, 4);
+        queue.shuffle(&mut StdRng::seed_from_u64(1));
+        assert!(queue.is_shuffled());
+        assert_eq!(current(&queue), Some("4"));
+        assert_eq!(queue.previous().map(|song| song.song_id.clone()), None);
+
+        let mut played = play_out(&mut queue);
+        assert_ne!(played, ["0", "1", "2", "3", "5", "6", "7", "8", "9"]);
+        played.sort();
+        assert_eq!(played, ["0", "1", "2", "3", "5", "6", "7", "8", "9"]);
+    }
+
+    #[test]
+    fn unshuffle_restores_order_keeping_current_song() {
+        let mut queue = queue(10, 0);
+        queue.shuffle(&mut StdRng::seed_from_u64(2));
+        queue.next();
+        queue.next();
+        let playing = current(&queue).unwrap().to_string();
+
+        queue.unshuffle();
+        assert!(!queue.is_shuffled());
+        assert_eq!(current(&queue), Some(playing.as_str()));
+        let index: usize = playing.parse().unwrap();
+        let expected: Vec<String> = (index + 1..10).map(|i| i.to_string()).collect();
+        assert_eq!(play_out(&mut queue), expected);
+    }
+
+    #[test]
+    fn shuffled_queue_plays_added_songs_last() {
+        let mut queue = queue(3, 0);
+        queue.shuffle(&mut StdRng::seed_from_u64(3));
+        queue.push(SongEntry::new("Song 3".into(), "3".into(), Vec::new()));
+        assert_eq!(play_out(&mut queue).last().map(String::as_str), Some("3"));
+    }
+
+    #[test]
+    fn remove_keeps_current_song() {
+        let mut queue = queue(4, 2);
+        queue.shuffle(&mut StdRng::seed_from_u64(4));
+        queue.remove(0);
+        assert_eq!(current(&queue), Some("2"));
+        let mut played = play_out(&mut queue);
+        played.sort();
+        assert_eq!(played, ["1", "3"]);
+
+        queue.remove(queue.cursor().unwrap());
+        assert_eq!(queue.current(), None);
+    }
+
+    #[test]
+    fn restart_starts_over() {
+        let mut queue = queue(3, 2);
+        assert_eq!(
+            queue
+                .restart(&mut StdRng::seed_from_u64(5))
+                .map(|song| song.song_id.as_str()),
+            Some("0")
+        );
+        assert_eq!(play_out(&mut queue), ["1", "2"]);
+    }
+
+    #[test]
+    fn restart_reshuffles_each_round() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut queue = queue(8, 0);
+        queue.shuffle(&mut rng);
+        let mut rounds = Vec::new();
+        for _ in 0..3 {
+            let first = queue.restart(&mut rng).unwrap().song_id.clone();
+            let mut round = vec![first];
+            round.extend(play_out(&mut queue));
+            rounds.push(round.clone());
+            round.sort();
+            assert_eq!(round, ["0", "1", "2", "3", "4", "5", "6", "7"]);
+        }
+        assert!(rounds[0] != rounds[1] || rounds[1] != rounds[2]);
+    }
+
+    #[test]
+    fn repeat_cycles_through_modes() {
+        assert_eq!(Repeat::Off.cycle(), Repeat::All);
+        assert_eq!(Repeat::All.cycle(), Repeat::One);
+        assert_eq!(Repeat::One.cycle(), Repeat::Off);
+    }
+}
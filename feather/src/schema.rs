@@ -0,0 +1,313 @@
+// This file wraps every record stored in sled in a versioned envelope and upgrades old records on open.
+//
+// To change a stored struct, bump it by appending a migration to its `MIGRATIONS` list that turns the
+// previous payload into the new one (usually: deserialize the old struct, fill the new field, serialize).
+use serde::{Serialize, de::DeserializeOwned};
+use sled::Tree;
+use thiserror::Error;
+
+/// Marks the start of an enveloped record. Records without it predate versioning (version 0).
+const MAGIC: &[u8; 4] = b"FTHR";
+
+/// Length of the envelope header: magic followed by a little-endian `u32` version.
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// Upgrades a payload from one version to the next.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, String>;
+
+/// Migration for a version bump that did not change the payload layout.
+pub fn unchanged(payload: &[u8]) -> Result<Vec<u8>, String> {
+    Ok(payload.to_vec())
+}
+
+/// A record that is stored with a schema version.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Name of the record type, used in error messages.
+    const NAME: &'static str;
+
+    /// Migrations in order, where `MIGRATIONS[n]` upgrades version `n` to `n + 1`.
+    /// The current version is the number of migrations.
+    const MIGRATIONS: &'static [Migration];
+
+    /// Returns the current schema version.
+    fn version() -> u32 {
+        Self::MIGRATIONS.len() as u32
+    }
+}
+
+/// Represents possible errors when reading, writing or migrating versioned records.
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Database error: {0}")]
+    DbError(#[from] sled::Error),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] bincode::Error),
+    #[error("{name} record has version {found}, but this build only supports up to {supported}")]
+    UnsupportedVersion {
+        name: &'static str,
+        found: u32,
+        supported: u32,
+    },
+    #[error("{name} record needs migration from version {found} to {current}")]
+    NotMigrated {
+        name: &'static str,
+        found: u32,
+        current: u32,
+    },
+    #[error("Failed to migrate {} {name} record(s), nothing was changed: {}", failures.len(), failures.join("; "))]
+    MigrationFailed {
+        name: &'static str,
+        failures: Vec<String>,
+    },
+}
+
+/// Serializes a record inside an envelope carrying its current version.
+pub fn encode<T: Versioned>(record: &T) -> Result<Vec<u8>, SchemaError> {
+    let payload = bincode::serialize(record)?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&T::version().to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Deserializes a record, failing if it was not migrated to the current version.
+pub fn decode<T: Versioned>(bytes: &[u8]) -> Result<T, SchemaError> {
+    let (version, payload) = split(bytes);
+    if version != T::version() {
+        return Err(version_error::<T>(version));
+    }
+    Ok(bincode::deserialize(payload)?)
+}
+
+// Splits a stored value into its version and payload
+fn split(bytes: &[u8]) -> (u32, &[u8]) {
+    match bytes.strip_prefix(MAGIC.as_slice()) {
+        Some(rest) if rest.len() >= 4 => {
+            let (version, payload) = rest.split_at(4);
+            let version = u32::from_le_bytes([version[0], version[1], version[2], version[3]]);
+            (version, payload)
+        }
+        _ => (0, bytes),
+    }
+}
+
+// Builds the error for a record whose version does not match the current one
+fn version_error<T: Versioned>(found: u32) -> SchemaError {
+    if found > T::version() {
+        SchemaError::UnsupportedVersion {
+            name: T::NAME,
+            found,
+            supported: T::version(),
+        }
+    } else {
+        SchemaError::NotMigrated {
+            name: T::NAME,
+            found,
+            current: T::version(),
+        }
+    }
+}
+
+/// Upgrades every record in the tree to the current version of `T`.
+/// All records are migrated and checked before anything is written, so a single failure
+/// leaves the tree untouched and is reported instead of dropping data.
+/// Returns the number of records that were upgraded.
+pub fn migrate<T: Versioned>(tree: &Tree) -> Result<usize, SchemaError> {
+    let mut upgraded = Vec::new();
+    let mut failures = Vec::new();
+
+    for item in tree.iter() {
+        let (key, value) = item?;
+        let (version, payload) = split(&value);
+        if version == T::version() {
+            continue;
+        }
+        if version > T::version() {
+            return Err(version_error::<T>(version));
+        }
+
+        let mut payload = payload.to_vec();
+        let mut result = Ok(());
+        for (from, migration) in T::MIGRATIONS.iter().enumerate().skip(version as usize) {
+            match migration(&payload) {
+                Ok(next) => payload = next,
+                Err(e) => {
+                    result = Err(format!("version {} -> {}: {}", from, from + 1, e));
+                    break;
+                }
+            }
+        }
+        // Make sure the final payload actually reads as the current struct
+        let result = result.and_then(|()| {
+            bincode::deserialize::<T>(&payload)
+                .map_err(|e| format!("version {}: {}", T::version(), e))
+        });
+
+        match result {
+            Ok(record) => upgraded.push((key, encode(&record)?)),
+            Err(e) => failures.push(format!("key {:?}: {}", String::from_utf8_lossy(&key), e)),
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(SchemaError::MigrationFailed {
+            name: T::NAME,
+            failures,
+        });
+    }
+
+    let count = upgraded.len();
+    if count > 0 {
+        let mut batch = sled::Batch::default();
+        for (key, value) in upgraded {
+            batch.insert(key, value);
+        }
+        tree.apply_batch(batch)?;
+        tree.flush()?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    // A record before it gained `note`
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct RecordV0 {
+        count: u32,
+    }
+
+    // The current record: v1 added `note`
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        count: u32,
+        note: String,
+    }
+
+    impl Versioned for Record {
+        const NAME: &'static str = "test";
+        const MIGRATIONS: &'static [Migration] = &[add_note];
+    }
+
+    // v1: adds an empty note, failing for counts the test marks as broken
+    fn add_note(payload: &[u8]) -> Result<Vec<u8>, String> {
+        let old: RecordV0 = bincode::deserialize(payload).map_err(|e| e.to_string())?;
+        if old.count == u32::MAX {
+            return Err("broken record".to_string());
+        }
+        bincode::serialize(&Record {
+            count: old.count,
+            note: String::new(),
+        })
+        .map_err(|e| e.to_string())
+    }
+
+    fn tree() -> Tree {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        db.open_tree("records").unwrap()
+    }
+
+    // Stores a record the way it looked before versioning
+    fn insert_v0(tree: &Tree, key: &str, count: u32) {
+        let payload = bincode::serialize(&RecordV0 { count }).unwrap();
+        tree.insert(key, payload).unwrap();
+    }
+
+    // Stores a record claiming to be of the given version
+    fn insert_version(tree: &Tree, key: &str, version: u32) {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        tree.insert(key, bytes).unwrap();
+    }
+
+    #[test]
+    fn round_trips_current_version() {
+        let record = Record {
+            count: 3,
+            note: "hi".to_string(),
+        };
+        let bytes = encode(&record).unwrap();
+        assert_eq!(split(&bytes).0, 1);
+        assert_eq!(decode::<Record>(&bytes).unwrap(), record);
+    }
+
+    #[test]
+    fn migrates_old_records() {
+        let tree = tree();
+        insert_v0(&tree, "a", 1);
+        insert_v0(&tree, "b", 2);
+        let current = encode(&Record {
+            count: 3,
+            note: "kept".to_string(),
+        })
+        .unwrap();
+        tree.insert("c", current.clone()).unwrap();
+
+        let a = tree.get("a").unwrap().unwrap();
+        assert!(matches!(
+            decode::<Record>(&a),
+            Err(SchemaError::NotMigrated { found: 0, .. })
+        ));
+        assert_eq!(migrate::<Record>(&tree).unwrap(), 2);
+        let a = tree.get("a").unwrap().unwrap();
+        assert_eq!(
+            decode::<Record>(&a).unwrap(),
+            Record {
+                count: 1,
+                note: String::new()
+            }
+        );
+        assert_eq!(tree.get("c").unwrap().unwrap(), current);
+        // Migrated records are left alone the next time
+        assert_eq!(migrate::<Record>(&tree).unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_records_from_newer_versions() {
+        let tree = tree();
+        insert_v0(&tree, "a", 1);
+        insert_version(&tree, "b", 7);
+        assert!(matches!(
+            migrate::<Record>(&tree),
+            Err(SchemaError::UnsupportedVersion {
+                found: 7,
+                supported: 1,
+                ..
+            })
+        ));
+        let b = tree.get("b").unwrap().unwrap();
+        assert!(matches!(
+            decode::<Record>(&b),
+            Err(SchemaError::UnsupportedVersion { found: 7, .. })
+        ));
+        // Nothing was upgraded
+        assert_eq!(split(&tree.get("a").unwrap().unwrap()).0, 0);
+    }
+
+    #[test]
+    fn failed_migration_changes_nothing() {
+        let tree = tree();
+        insert_v0(&tree, "good", 1);
+        insert_v0(&tree, "bad", u32::MAX);
+        let before: Vec<_> = tree.iter().map(Result::unwrap).collect();
+
+        match migrate::<Record>(&tree) {
+            Err(SchemaError::MigrationFailed { name, failures }) => {
+                assert_eq!(name, "test");
+                assert_eq!(failures.len(), 1);
+                assert!(failures[0].contains("bad") && failures[0].contains("broken record"));
+            }
+            other => panic!("expected a failed migration, got {:?}", other),
+        }
+        let after: Vec<_> = tree.iter().map(Result::unwrap).collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn unchanged_keeps_payload() {
+        assert_eq!(unchanged(b"abc").unwrap(), b"abc".to_vec());
+    }
+}
@@ -0,0 +1,431 @@
+// This file submits listens to ListenBrainz and Last.fm and retries the ones that could not be submitted
+use crate::config::{LastfmConfig, ListenBrainzConfig, ScrobbleConfig};
+use crate::database::{HistoryDB, PendingScrobble, ScrobbleDB, SessionError};
+use crate::{ArtistName, SongId, SongName};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// How often queued listens are looked at for a retry.
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Delay before the first retry of a listen, doubled after every failed one.
+const RETRY_BASE_SECS: u64 = 60;
+
+/// Longest delay between two retries of a listen.
+const RETRY_MAX_SECS: u64 = 6 * 60 * 60;
+
+/// Time after which a request to a service is given up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Last.fm error codes that mean "try again later" rather than "this listen is wrong":
+/// operation failed, service offline, temporarily unavailable and rate limit exceeded.
+const LASTFM_TEMPORARY_ERRORS: &[u64] = &[8, 11, 16, 29];
+
+/// Last.fm error code for a session key that was revoked or has expired.
+const LASTFM_INVALID_SESSION: u64 = 9;
+
+/// A service listens are submitted to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ScrobbleService {
+    ListenBrainz,
+    Lastfm,
+}
+
+/// A song that was heard, or is being heard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub song_id: SongId,            // Unique identifier for the song
+    pub track: SongName,            // Name of the song
+    pub artists: Vec<ArtistName>,   // Artists of the song
+    pub duration_secs: Option<u64>, // Length of the song, if known
+    pub listened_at: u64,           // Unix timestamp when the song started playing
+}
+
+impl Listen {
+    /// Creates a listen of a song that starts now.
+    pub fn new(
+        song_id: SongId,
+        track: SongName,
+        artists: Vec<ArtistName>,
+        duration_secs: Option<u64>,
+    ) -> Self {
+        Self {
+            song_id,
+            track,
+            artists,
+            duration_secs,
+            listened_at: now(),
+        }
+    }
+}
+
+/// Represents possible errors when submitting listens.
+#[derive(Error, Debug)]
+pub enum ScrobbleError {
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error), // The service could not be reached
+    #[error("{0:?} is unavailable: {1}")]
+    Unavailable(ScrobbleService, String), // The service failed, the listen is retried later
+    #[error("{0:?} rejected the listen: {1}")]
+    Rejected(ScrobbleService, String), // The service refused the listen, it is dropped
+    #[error("{0:?} is not set up: {1}")]
+    Config(ScrobbleService, String), // Missing account details, the listen is kept
+    #[error("Database error: {0}")]
+    Database(String), // Error reading or writing the queue
+}
+
+/// Submits listens to the configured services. Listens are queued in sled first, so the
+/// ones that fail are retried with a growing delay, even after a restart.
+pub struct Scrobbler {
+    config: ScrobbleConfig,           // Services and listen threshold
+    db: ScrobbleDB,                   // Listens still to be submitted
+    history: Arc<HistoryDB>,          // History whose entries are marked as scrobbled
+    client: reqwest::Client,          // HTTP client shared by all services
+    flushing: tokio::sync::Mutex<()>, // Held while submitting, so no listen is sent twice
+}
+
+impl Scrobbler {
+    /// Creates a scrobbler and opens its queue.
+    pub fn new(config: ScrobbleConfig, history: Arc<HistoryDB>) -> Result<Self, ScrobbleError> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            config,
+            db: ScrobbleDB::new().map_err(|e| ScrobbleError::Database(e.to_string()))?,
+            history,
+            client,
+            flushing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Returns the configured services.
+    pub fn services(&self) -> Vec<ScrobbleService> {
+        let mut services = Vec::new();
+        if self.config.listenbrainz.is_some() {
+            services.push(ScrobbleService::ListenBrainz);
+        }
+        if self.config.lastfm.is_some() {
+            services.push(ScrobbleService::Lastfm);
+        }
+        services
+    }
+
+    /// Announces a song that just started on every service. Nothing is retried, as the
+    /// announcement is stale by then.
+    pub async fn now_playing(&self, listen: &Listen) -> Result<(), ScrobbleError> {
+        if !self.config.now_playing {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for service in self.services() {
+            if let Err(e) = self.send(service, listen, true).await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Queues a listen for every service and tries to submit it right away.
+    pub async fn submit(&self, listen: Listen) -> Result<(), ScrobbleError> {
+        for service in self.services() {
+            let scrobble = PendingScrobble {
+                service,
+                listen: listen.clone(),
+                attempts: 0,
+                next_attempt: 0,
+            };
+            self.db
+                .push(&scrobble)
+                .map_err(|e| ScrobbleError::Database(e.to_string()))?;
+        }
+        self.flush().await.map(|_| ())
+    }
+
+    /// Submits the queued listens that are due and returns how many are still queued.
+    /// Listens that fail are retried later, those a service refuses are dropped.
+    pub async fn flush(&self) -> Result<usize, ScrobbleError> {
+        let _flushing = self.flushing.lock().await;
+        let database = |e: SessionError| ScrobbleError::Database(e.to_string());
+        let now = now();
+        let mut submitted = Vec::new();
+        let mut rejected = Vec::new();
+        let mut down = Vec::new(); // Services that just failed are not tried again this time
+
+        for (key, mut scrobble) in self.db.pending().map_err(database)? {
+            if scrobble.next_attempt > now || down.contains(&scrobble.service) {
+                continue;
+            }
+            match self.send(scrobble.service, &scrobble.listen, false).await {
+                Ok(()) => {
+                    self.db.remove(key).map_err(database)?;
+                    submitted.push(scrobble.listen);
+                }
+                Err(ScrobbleError::Rejected(..)) => {
+                    self.db.remove(key).map_err(database)?;
+                    rejected.push(scrobble.listen);
+                }
+                Err(_) => {
+                    down.push(scrobble.service);
+                    scrobble.next_attempt = now + backoff_secs(scrobble.attempts);
+                    scrobble.attempts += 1;
+                    self.db.update(key, &scrobble).map_err(database)?;
+                }
+            }
+        }
+
+        // A play counts as scrobbled once every service has taken it
+        let pending = self.db.pending().map_err(database)?;
+        for listen in submitted {
+            let done = !rejected.contains(&listen)
+                && !pending
+                    .iter()
+                    .any(|(_, scrobble)| scrobble.listen == listen);
+            if done {
+                let _ = self
+                    .history
+                    .mark_scrobbled(&listen.song_id, listen.listened_at);
+            }
+        }
+        Ok(pending.len())
+    }
+
+    /// Retries queued listens for as long as Feather runs.
+    pub async fn run(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(RETRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            let _ = self.flush().await;
+        }
+    }
+
+    // Sends a listen, or the song playing now, to one service
+    async fn send(
+        &self,
+        service: ScrobbleService,
+        listen: &Listen,
+        playing_now: bool,
+    ) -> Result<(), ScrobbleError> {
+        let not_configured = || ScrobbleError::Rejected(service, "not configured".to_string());
+        match service {
+            ScrobbleService::ListenBrainz => {
+                let config = self
+                    .config
+                    .listenbrainz
+                    .as_ref()
+                    .ok_or_else(not_configured)?;
+                self.send_listenbrainz(config, listen, playing_now).await
+            }
+            ScrobbleService::Lastfm => {
+                let config = self.config.lastfm.as_ref().ok_or_else(not_configured)?;
+                let method = if playing_now {
+                    "track.updateNowPlaying"
+                } else {
+                    "track.scrobble"
+                };
+                let mut params = vec![
+                    (
+                        "artist",
+                        listen.artists.first().cloned().unwrap_or_default(),
+                    ),
+                    ("track", listen.track.clone()),
+                ];
+                if let Some(duration) = listen.duration_secs {
+                    params.push(("duration", duration.to_string()));
+                }
+                if !playing_now {
+                    params.push(("timestamp", listen.listened_at.to_string()));
+                }
+                params.push(("sk", self.lastfm_session_key(config).await?));
+                let response = self.call_lastfm(config, method, params).await?;
+
+                // Last.fm answers filtered scrobbles with success, but counts them as ignored
+                let ignored = &response["scrobbles"]["@attr"]["ignored"];
+                let ignored = ignored
+                    .as_u64()
+                    .or_else(|| ignored.as_str().and_then(|n| n.parse().ok()))
+                    .unwrap_or(0);
+                if ignored > 0 {
+                    return Err(ScrobbleError::Rejected(service, "ignored".to_string()));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Submits a listen to ListenBrainz, see https://listenbrainz.readthedocs.io/en/latest/users/api/core.html
+    async fn send_listenbrainz(
+        &self,
+        config: &ListenBrainzConfig,
+        listen: &Listen,
+        playing_now: bool,
+    ) -> Result<(), ScrobbleError> {
+        let service = ScrobbleService::ListenBrainz;
+        let mut additional_info = json!({
+            "artist_names": listen.artists,
+            "origin_url": format!("https://music.youtube.com/watch?v={}", listen.song_id),
+            "music_service": "music.youtube.com",
+            "submission_client": "Feather",
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(duration) = listen.duration_secs {
+            additional_info["duration_ms"] = json!(duration * 1000);
+        }
+        let mut payload = json!({
+            "track_metadata": {
+                "artist_name": listen.artists.join(", "),
+                "track_name": listen.track,
+                "additional_info": additional_info,
+            }
+        });
+        if !playing_now {
+            payload["listened_at"] = json!(listen.listened_at);
+        }
+        let body = json!({
+            "listen_type": if playing_now { "playing_now" } else { "single" },
+            "payload": [payload],
+        });
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/1/submit-listens",
+                config.url.trim_end_matches('/')
+            ))
+            .header(AUTHORIZATION, format!("Token {}", config.token))
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&text)
+            .ok()
+            .and_then(|error| error["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| status.to_string());
+        // Bad requests stay bad, but a wrong token or an overloaded server may get fixed
+        if status == reqwest::StatusCode::BAD_REQUEST {
+            Err(ScrobbleError::Rejected(service, message))
+        } else {
+            Err(ScrobbleError::Unavailable(service, message))
+        }
+    }
+
+    // Returns the Last.fm session key from the config, the one kept from an earlier login,
+    // or a new one from logging in with the username and password
+    async fn lastfm_session_key(&self, config: &LastfmConfig) -> Result<String, ScrobbleError> {
+        let service = ScrobbleService::Lastfm;
+        if let Some(session_key) = &config.session_key {
+            return Ok(session_key.clone());
+        }
+        let database = |e: SessionError| ScrobbleError::Database(e.to_string());
+        if let Some(session_key) = self.db.session_key(service).map_err(database)? {
+            return Ok(session_key);
+        }
+        let (Some(username), Some(password)) = (&config.username, &config.password) else {
+            return Err(ScrobbleError::Config(
+                service,
+                "set session_key, or username and password".to_string(),
+            ));
+        };
+
+        let params = vec![
+            ("username", username.clone()),
+            ("password", password.clone()),
+        ];
+        let response = self
+            .call_lastfm(config, "auth.getMobileSession", params)
+            .await?;
+        let session_key = response["session"]["key"]
+            .as_str()
+            .ok_or_else(|| ScrobbleError::Unavailable(service, "no session key".to_string()))?;
+        self.db
+            .set_session_key(service, session_key)
+            .map_err(database)?;
+        Ok(session_key.to_string())
+    }
+
+    // Calls a signed Last.fm API method, see https://www.last.fm/api/scrobbling
+    async fn call_lastfm(
+        &self,
+        config: &LastfmConfig,
+        method: &str,
+        mut params: Vec<(&str, String)>,
+    ) -> Result<Value, ScrobbleError> {
+        let service = ScrobbleService::Lastfm;
+        params.push(("method", method.to_string()));
+        params.push(("api_key", config.api_key.clone()));
+        params.push(("api_sig", lastfm_signature(&params, &config.api_secret)));
+        params.push(("format", "json".to_string()));
+
+        let response = self.client.post(&config.url).form(&params).send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let Ok(body) = serde_json::from_str::<Value>(&text) else {
+            return Err(ScrobbleError::Unavailable(service, status.to_string()));
+        };
+        match body["error"].as_u64() {
+            None if status.is_success() => Ok(body),
+            None => Err(ScrobbleError::Unavailable(service, status.to_string())),
+            Some(code) => {
+                let message = body["message"].as_str().unwrap_or_default().to_string();
+                if code == LASTFM_INVALID_SESSION {
+                    return Err(self.forget_lastfm_session(config, message));
+                }
+                if LASTFM_TEMPORARY_ERRORS.contains(&code) {
+                    Err(ScrobbleError::Unavailable(service, message))
+                } else {
+                    Err(ScrobbleError::Rejected(service, message))
+                }
+            }
+        }
+    }
+
+    // Drops the Last.fm session key that was refused, so the listen is retried after
+    // logging in again. A key from the config cannot be replaced, so it needs fixing.
+    fn forget_lastfm_session(&self, config: &LastfmConfig, message: String) -> ScrobbleError {
+        let service = ScrobbleService::Lastfm;
+        if config.session_key.is_some() {
+            return ScrobbleError::Config(service, format!("session_key refused: {}", message));
+        }
+        match self.db.clear_session_key(service) {
+            Ok(()) => ScrobbleError::Unavailable(service, message),
+            Err(e) => ScrobbleError::Database(e.to_string()),
+        }
+    }
+}
+
+// Signs Last.fm parameters: the MD5 of all names and values sorted by name, followed
+// by the shared secret
+fn lastfm_signature(params: &[(&str, String)], secret: &str) -> String {
+    let mut sorted: Vec<_> = params.iter().collect();
+    sorted.sort_by_key(|(name, _)| *name);
+    let mut text: String = sorted
+        .into_iter()
+        .map(|(name, value)| format!("{}{}", name, value))
+        .collect();
+    text.push_str(secret);
+    format!("{:x}", md5::compute(text.as_bytes()))
+}
+
+// Delay before the next attempt of a listen that failed `attempts` times before
+fn backoff_secs(attempts: u32) -> u64 {
+    RETRY_BASE_SECS
+        .saturating_mul(1 << attempts.min(16))
+        .min(RETRY_MAX_SECS)
+}
+
+// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,218 @@
+// This file contains the sleep timer that fades playback out and pauses it
+use std::time::{Duration, Instant};
+
+/// Seconds over which the volume is faded out before pausing.
+pub const FADE_SECS: f64 = 30.0;
+
+/// Seconds before the end at which playback is paused, so that the end of a track
+/// is not missed between two ticks.
+const STOP_MARGIN_SECS: f64 = 1.0;
+
+/// Longest time a sleep timer can be set or extended to.
+pub const MAX_SLEEP: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// When the sleep timer goes off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepMode {
+    /// At a fixed point in time.
+    At(Instant),
+    /// At the end of the current track.
+    EndOfTrack,
+    /// At the end of the last track in the queue.
+    EndOfQueue,
+}
+
+impl SleepMode {
+    /// Parses a timer setting: a number of minutes up to [`MAX_SLEEP`], "track" or "queue".
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "t" | "track" => Some(SleepMode::EndOfTrack),
+            "q" | "queue" => Some(SleepMode::EndOfQueue),
+            minutes => {
+                let minutes: f64 = minutes.parse().ok()?;
+                // Negative, "nan", "inf" and "1e20" do not fit in a `Duration` at all
+                let duration = Duration::try_from_secs_f64(minutes * 60.0)
+                    .ok()
+                    .filter(|duration| !duration.is_zero() && *duration <= MAX_SLEEP)?;
+                Instant::now().checked_add(duration).map(SleepMode::At)
+            }
+        }
+    }
+}
+
+/// What the player should do after a tick of the sleep timer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepAction {
+    /// Nothing to do yet.
+    Wait,
+    /// Lower the volume to the given value.
+    Fade(f64),
+    /// Pause and set the volume back to the given value.
+    Stop(f64),
+}
+
+/// A running sleep timer.
+#[derive(Debug, Clone)]
+pub struct SleepTimer {
+    mode: SleepMode,        // When the timer goes off
+    fade_from: Option<f64>, // Volume before the fade started, once fading
+}
+
+impl SleepTimer {
+    /// Starts a sleep timer.
+    pub fn new(mode: SleepMode) -> Self {
+        Self {
+            mode,
+            fade_from: None,
+        }
+    }
+
+    /// Returns when the timer goes off.
+    pub fn mode(&self) -> SleepMode {
+        self.mode
+    }
+
+    /// Returns the volume to restore if the timer is stopped while fading.
+    pub fn fade_from(&self) -> Option<f64> {
+        self.fade_from
+    }
+
+    /// Returns the seconds left until the timer goes off, if known.
+    ///
+    /// # Arguments
+    /// * `track_left` - Seconds left in the current track at the current speed.
+    /// * `last_in_queue` - Whether the current track is the last one in the queue.
+    pub fn remaining(&self, track_left: Option<f64>, last_in_queue: bool) -> Option<f64> {
+        match self.mode {
+            SleepMode::At(deadline) => Some(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64(),
+            ),
+            SleepMode::EndOfTrack => track_left,
+            SleepMode::EndOfQueue if last_in_queue => track_left,
+            SleepMode::EndOfQueue => None,
+        }
+    }
+
+    /// Pushes the timer back by `by`, but no further than [`MAX_SLEEP`] from now,
+    /// cancelling a fade in progress. Timers waiting for the end of a track become fixed
+    /// time timers. Returns the volume to restore if the timer was fading.
+    pub fn extend(&mut self, by: Duration, remaining: Option<f64>) -> Option<f64> {
+        let now = Instant::now();
+        let from = match self.mode {
+            SleepMode::At(deadline) => Some(deadline.max(now)),
+            _ => remaining
+                .and_then(|remaining| Duration::try_from_secs_f64(remaining).ok())
+                .and_then(|remaining| now.checked_add(remaining)),
+        };
+        if let Some(deadline) = from.and_then(|from| from.checked_add(by)) {
+            let latest = now.checked_add(MAX_SLEEP).unwrap_or(deadline);
+            self.mode = SleepMode::At(deadline.min(latest));
+        }
+        self.fade_from.take()
+    }
+
+    /// Advances the timer given the seconds left and the current volume.
+    pub fn tick(&mut self, remaining: Option<f64>, volume: f64) -> SleepAction {
+        let Some(remaining) = remaining else {
+            return SleepAction::Wait;
+        };
+        if remaining > FADE_SECS {
+            return SleepAction::Wait;
+        }
+        let from = *self.fade_from.get_or_insert(volume);
+        if remaining <= STOP_MARGIN_SECS {
+            SleepAction::Stop(from)
+        } else {
+            SleepAction::Fade(
+                from * (remaining - STOP_MARGIN_SECS) / (FADE_SECS - STOP_MARGIN_SECS),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Seconds from now until a fixed time timer goes off
+    fn secs_left(mode: SleepMode) -> f64 {
+        match mode {
+            SleepMode::At(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .as_secs_f64(),
+            mode => panic!("expected a fixed time timer, got {:?}", mode),
+        }
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert_eq!(SleepMode::parse(" Track "), Some(SleepMode::EndOfTrack));
+        assert_eq!(SleepMode::parse("q"), Some(SleepMode::EndOfQueue));
+        let left = secs_left(SleepMode::parse("1.5").unwrap());
+        assert!((89.0..=90.0).contains(&left));
+        assert!(SleepMode::parse("1440").is_some());
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        for input in ["0", "-5", "abc", "nan", "inf", "1e20", "1441"] {
+            assert_eq!(SleepMode::parse(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn extends_timers() {
+        let mut timer = SleepTimer::new(SleepMode::parse("1").unwrap());
+        timer.extend(Duration::from_secs(60), None);
+        assert!((119.0..=120.0).contains(&secs_left(timer.mode())));
+
+        // A timer for the end of the track starts counting from it
+        let mut timer = SleepTimer::new(SleepMode::EndOfTrack);
+        timer.extend(Duration::from_secs(60), Some(30.0));
+        assert!((89.0..=90.0).contains(&secs_left(timer.mode())));
+
+        // Without knowing when the track ends there is nothing to extend from
+        let mut timer = SleepTimer::new(SleepMode::EndOfQueue);
+        timer.extend(Duration::from_secs(60), None);
+        assert_eq!(timer.mode(), SleepMode::EndOfQueue);
+    }
+
+    #[test]
+    fn caps_extended_timers() {
+        let mut timer = SleepTimer::new(SleepMode::parse("1440").unwrap());
+        timer.extend(Duration::from_secs(3600), None);
+        assert!(secs_left(timer.mode()) <= MAX_SLEEP.as_secs_f64());
+
+        let mut timer = SleepTimer::new(SleepMode::EndOfTrack);
+        timer.extend(Duration::from_secs(60), Some(f64::INFINITY));
+        assert_eq!(timer.mode(), SleepMode::EndOfTrack);
+    }
+
+    #[test]
+    fn fades_out_then_stops() {
+        let mut timer = SleepTimer::new(SleepMode::EndOfTrack);
+        assert_eq!(timer.tick(None, 80.0), SleepAction::Wait);
+        assert_eq!(timer.tick(Some(FADE_SECS + 1.0), 80.0), SleepAction::Wait);
+        assert_eq!(timer.fade_from(), None);
+
+        let halfway = STOP_MARGIN_SECS + (FADE_SECS - STOP_MARGIN_SECS) / 2.0;
+        assert_eq!(timer.tick(Some(halfway), 80.0), SleepAction::Fade(40.0));
+        // The fade keeps going from the volume it started at
+        assert_eq!(timer.tick(Some(halfway), 40.0), SleepAction::Fade(40.0));
+        assert_eq!(timer.tick(Some(0.5), 10.0), SleepAction::Stop(80.0));
+    }
+
+    #[test]
+    fn extending_cancels_fade() {
+        let mut timer = SleepTimer::new(SleepMode::EndOfTrack);
+        timer.tick(Some(10.0), 60.0);
+        assert_eq!(
+            timer.extend(Duration::from_secs(300), Some(10.0)),
+            Some(60.0)
+        );
+        assert_eq!(timer.fade_from(), None);
+        assert_eq!(timer.tick(Some(310.0), 60.0), SleepAction::Wait);
+    }
+}
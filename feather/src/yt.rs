@@ -1,15 +1,61 @@
+use crate::config::{Codec, Quality, StreamConfig};
+use crate::cookies::Cookies;
+use crate::database::SongEntry;
+use crate::lyrics::Lyrics;
 use crate::{ArtistName, ChannelName, PlaylistId, PlaylistName, SongId, SongName, SongUrl};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use reqwest::header::{CONTENT_RANGE, RANGE};
 use rustypipe::{
     client::{RustyPipe, RustyPipeQuery},
-    model::MusicItem,
-    param::StreamFilter,
+    model::{AudioCodec, AudioStream, MusicItem},
 };
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::AsyncWriteExt;
+
+/// Upper bound on the number of songs loaded from a single playlist.
+const MAX_PLAYLIST_SONGS: usize = 5000;
+
+/// Size of the byte ranges streams are downloaded in, as YouTube throttles larger requests.
+const DOWNLOAD_CHUNK_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The audio stream selected for a song.
+#[derive(Debug, Clone)]
+pub struct AudioStreamInfo {
+    pub url: SongUrl,             // Direct URL of the stream
+    pub loudness_db: Option<f32>, // Loudness relative to YouTube's reference level, if reported
+    pub codec: String,            // Codec name, e.g. "opus"
+    pub bitrate: u32,             // Bitrate in bits per second
+    pub format: String,           // Container, e.g. "webm", also used as file extension
+}
+
+impl AudioStreamInfo {
+    // Keeps the details of a stream returned by RustyPipe
+    fn from_stream(stream: &AudioStream) -> Self {
+        Self {
+            url: stream.url.clone(),
+            loudness_db: stream.loudness_db,
+            codec: format!("{:?}", stream.codec).to_lowercase(),
+            bitrate: stream.bitrate,
+            format: format!("{:?}", stream.format).to_lowercase(),
+        }
+    }
+}
+
+/// Details of a track beyond its stream: its name and artists, album and cover.
+#[derive(Debug, Clone, Default)]
+pub struct TrackInfo {
+    pub name: SongName,            // Name of the track
+    pub artists: Vec<ArtistName>,  // Artists performing the track
+    pub album: Option<String>,     // Name of the album the track is on, if any
+    pub cover_url: Option<String>, // URL of the largest cover image, if any
+}
 
 /// A client for interacting with YouTube music using RustyPipe.
 pub struct YoutubeClient {
-    client: RustyPipeQuery,
+    rp: RustyPipe,          // Client holding the login, if any
+    client: RustyPipeQuery, // Query builder for all requests
+    signed_in: AtomicBool,  // Whether streams are requested with the login
 }
 
 impl YoutubeClient {
@@ -19,7 +65,28 @@ impl YoutubeClient {
         path.push("Feather");
         let rp = RustyPipe::builder().storage_dir(path).build().unwrap();
         let client = rp.query();
-        YoutubeClient { client }
+        YoutubeClient {
+            rp,
+            client,
+            signed_in: AtomicBool::new(false),
+        }
+    }
+
+    /// Signs in to YouTube with cookies from a browser, so songs restricted to signed-in
+    /// users can be streamed. Fails if YouTube does not accept them.
+    pub async fn set_cookies(&self, cookies: &Cookies) -> Result<(), String> {
+        self.rp
+            .user_auth_set_cookie(&cookies.header())
+            .await
+            .map_err(|e| {
+                format!(
+                    "YouTube did not accept the cookies in {}: {}",
+                    cookies.path().display(),
+                    e
+                )
+            })?;
+        self.signed_in.store(true, Ordering::Relaxed);
+        Ok(())
     }
 
     /// Searches for music based on the given query.
@@ -48,27 +115,196 @@ impl YoutubeClient {
         }
     }
 
-    /// Fetches the audio stream URL for a given song ID.
-    pub async fn fetch_song_url(&self, id: &SongId) -> Result<SongUrl, String> {
-        match self.client.player(&id).await {
-            Ok(player) => match player.select_audio_stream(&StreamFilter::default()) {
-                Some(stream) => return Ok(stream.url.clone()),
+    /// Searches for tracks and keeps the details needed to compare them,
+    /// returning each track together with its duration in seconds, if known.
+    pub async fn search_tracks(
+        &self,
+        query: &str,
+    ) -> Result<Vec<(SongEntry, Option<u32>)>, String> {
+        match self.client.music_search_tracks(query).await {
+            Ok(results) => Ok(results
+                .items
+                .items
+                .into_iter()
+                .map(|track| {
+                    let artist_names = track.artists.into_iter().map(|a| a.name).collect();
+                    (
+                        SongEntry::new(track.name, track.id, artist_names),
+                        track.duration,
+                    )
+                })
+                .collect()),
+            Err(e) => Err(format!("Error in Search Result: {}", e)),
+        }
+    }
+
+    /// Searches for albums.
+    /// Returns each album's name and ID, along with the names of its artists.
+    pub async fn search_albums(
+        &self,
+        query: &str,
+    ) -> Result<Vec<((String, String), Vec<ArtistName>)>, String> {
+        match self.client.music_search_albums(query).await {
+            Ok(results) => Ok(results
+                .items
+                .items
+                .into_iter()
+                .map(|album| {
+                    let artist_names = album.artists.into_iter().map(|a| a.name).collect();
+                    ((album.name, album.id), artist_names)
+                })
+                .collect()),
+            Err(e) => Err(format!("Error in Search Result: {}", e)),
+        }
+    }
+
+    /// Searches for artists.
+    /// Returns each artist's name and channel ID.
+    pub async fn search_artists(&self, query: &str) -> Result<Vec<(ArtistName, String)>, String> {
+        match self.client.music_search_artists(query).await {
+            Ok(results) => Ok(results
+                .items
+                .items
+                .into_iter()
+                .map(|artist| (artist.name, artist.id))
+                .collect()),
+            Err(e) => Err(format!("Error in Search Result: {}", e)),
+        }
+    }
+
+    /// Fetches the audio stream for a given song ID, chosen according to `config`,
+    /// together with its format, bitrate and loudness.
+    pub async fn fetch_song_url(
+        &self,
+        id: &SongId,
+        config: &StreamConfig,
+    ) -> Result<AudioStreamInfo, String> {
+        // Only stream requests need the login; the rest works the same without it
+        let query = if self.signed_in.load(Ordering::Relaxed) {
+            self.client.clone().authenticated()
+        } else {
+            self.client.clone()
+        };
+        match query.player(&id).await {
+            Ok(player) => match select_stream(&player.audio_streams, config) {
+                Some(stream) => return Ok(AudioStreamInfo::from_stream(stream)),
                 None => return Err("Audio Stream not Found".to_string()),
             },
             Err(_) => return Err("Link cannot be Found".to_string()),
         }
     }
 
+    /// Fetches the name, artists, album and cover of a track.
+    pub async fn fetch_track_info(&self, id: &SongId) -> Result<TrackInfo, String> {
+        match self.client.music_details(id).await {
+            Ok(details) => Ok(TrackInfo {
+                name: details.track.name,
+                artists: details
+                    .track
+                    .artists
+                    .into_iter()
+                    .map(|artist| artist.name)
+                    .collect(),
+                album: details.track.album.map(|album| album.name),
+                cover_url: details
+                    .track
+                    .cover
+                    .into_iter()
+                    .max_by_key(|thumbnail| thumbnail.width)
+                    .map(|thumbnail| thumbnail.url),
+            }),
+            Err(e) => Err(format!("Error in fetching track details: {}", e)),
+        }
+    }
+
+    /// Fetches the lyrics of a track, or `None` if YouTube Music has none for it.
+    pub async fn fetch_lyrics(&self, id: &SongId) -> Result<Option<Lyrics>, String> {
+        let details = self
+            .client
+            .music_details(id)
+            .await
+            .map_err(|e| format!("Error in fetching track details: {}", e))?;
+        let Some(lyrics_id) = details.lyrics_id else {
+            return Ok(None);
+        };
+        match self.client.music_lyrics(&lyrics_id).await {
+            Ok(lyrics) => Ok(Some(Lyrics::parse(&lyrics.body, &lyrics.footer))),
+            Err(e) => Err(format!("Error in fetching lyrics: {}", e)),
+        }
+    }
+
+    /// Downloads the audio of a song, chosen according to `config`, into `dir`
+    /// as "Artists - Title [ID].ext" and returns the path of the file.
+    /// The file only appears once it is complete.
+    pub async fn download(
+        &self,
+        id: &SongId,
+        config: &StreamConfig,
+        dir: &Path,
+    ) -> Result<PathBuf, String> {
+        let info = self.fetch_track_info(id).await?;
+        let stream = self.fetch_song_url(id, config).await?;
+        let name = format!(
+            "{} - {} [{}].{}",
+            info.artists.join(", "),
+            info.name,
+            id,
+            stream.format
+        );
+        // Keep the name valid on every file system
+        let name: String = name
+            .chars()
+            .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+            .collect();
+        let path = dir.join(name);
+        let partial = path.with_extension(format!("{}.part", stream.format));
+
+        let result = download_stream(&stream.url, &partial).await;
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(format!("Error downloading {}: {}", id, e));
+        }
+        tokio::fs::rename(&partial, &path)
+            .await
+            .map_err(|e| format!("Error saving {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Saves the cover of a song from `url` in the cache directory, unless it is already
+    /// there, and returns the path of the file.
+    pub async fn cache_cover(&self, id: &SongId, url: &str) -> Result<PathBuf, String> {
+        let mut path = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        path.push("Feather/covers");
+        tokio::fs::create_dir_all(&path)
+            .await
+            .map_err(|e| format!("Error creating {}: {}", path.display(), e))?;
+        path.push(format!("{}.jpg", id));
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(path);
+        }
+
+        let partial = path.with_extension("jpg.part");
+        if let Err(e) = download_stream(url, &partial).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(format!("Error downloading the cover of {}: {}", id, e));
+        }
+        tokio::fs::rename(&partial, &path)
+            .await
+            .map_err(|e| format!("Error saving {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
     /// Searches for playlists based on a given query.
-    /// Returns a hashmap where the key is the playlist name and the value is a tuple
+    /// Returns the playlists in result order, each as its name paired with a tuple
     /// containing the playlist ID and a list of associated channel names.
+    /// Playlists may share a name, so they are told apart by their IDs.
     pub async fn fetch_playlist(
         &self,
         search_query: &str,
-    ) -> Result<HashMap<PlaylistName, (PlaylistId, Vec<ChannelName>)>, String> {
+    ) -> Result<Vec<(PlaylistName, (PlaylistId, Vec<ChannelName>))>, String> {
         match self.client.music_search_playlists(search_query, true).await {
             Ok(playlists) => {
-                let mut result = HashMap::new();
+                let mut result = Vec::new();
 
                 for playlist in playlists.items.items {
                     let playlist_id = playlist.id;
@@ -78,7 +314,7 @@ impl YoutubeClient {
                         .map(|channel| channel.name)
                         .collect();
 
-                    result.insert(playlist.name, (playlist_id, channel_names));
+                    result.push((playlist.name, (playlist_id, channel_names)));
                 }
 
                 Ok(result)
@@ -88,28 +324,36 @@ impl YoutubeClient {
     }
 
     /// Fetches songs from a given playlist ID.
-    /// Returns a hashmap where each key is a tuple of (song name, song ID), and
-    /// the value is a list of associated artist names.
+    /// Returns the songs in playlist order, each as a tuple of (song name, song ID)
+    /// paired with a list of associated artist names.
     pub async fn fetch_playlist_songs(
         &self,
         playlist_id: PlaylistId,
-    ) -> Result<HashMap<(SongName, SongId), Vec<ArtistName>>, String> {
+    ) -> Result<Vec<((SongName, SongId), Vec<ArtistName>)>, String> {
         match self.client.playlist(playlist_id).await {
-            Ok(playlist_data) => {
-                let mut song_map = HashMap::new();
+            Ok(mut playlist_data) => {
+                // Load the remaining pages so long playlists are copied completely
+                playlist_data
+                    .videos
+                    .extend_limit(&self.client, MAX_PLAYLIST_SONGS)
+                    .await
+                    .map_err(|e| format!("Error fetching playlist songs: {}", e))?;
 
-                for video in playlist_data.videos.items {
-                    let song_key = (video.name, video.id);
-                    let artist_names: Vec<String> = video
-                        .channel
-                        .into_iter()
-                        .map(|channel| channel.name)
-                        .collect();
-
-                    song_map.insert(song_key, artist_names);
-                }
+                let songs = playlist_data
+                    .videos
+                    .items
+                    .into_iter()
+                    .map(|video| {
+                        let artist_names: Vec<String> = video
+                            .channel
+                            .into_iter()
+                            .map(|channel| channel.name)
+                            .collect();
+                        ((video.name, video.id), artist_names)
+                    })
+                    .collect();
 
-                Ok(song_map)
+                Ok(songs)
             }
             Err(e) => Err(format!("Error fetching playlist songs: {}", e)),
         }
@@ -141,6 +385,80 @@ impl YoutubeClient {
         }
     }
 }
+
+// Writes a stream to `path` in ranges of `DOWNLOAD_CHUNK_BYTES`
+async fn download_stream(url: &str, path: &Path) -> Result<(), String> {
+    let client = reqwest::Client::new();
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut offset = 0;
+    loop {
+        let range = format!("bytes={}-{}", offset, offset + DOWNLOAD_CHUNK_BYTES - 1);
+        let mut response = client
+            .get(url)
+            .header(RANGE, range)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?;
+        // "bytes 0-1023/4096": without it the server sent the whole stream at once
+        let total = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse::<u64>().ok());
+
+        let mut received = 0;
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+            received += chunk.len() as u64;
+        }
+        offset += received;
+        match total {
+            Some(total) if offset < total && received > 0 => continue,
+            _ => break,
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())
+}
+
+/// Picks a stream following the fallback chain:
+/// preferred codec within the bitrate limit, any codec within the limit,
+/// then the smallest stream of the preferred codec, then the smallest stream overall.
+fn select_stream<'a>(streams: &'a [AudioStream], config: &StreamConfig) -> Option<&'a AudioStream> {
+    let codec_ok = |s: &AudioStream| match config.codec {
+        Codec::Opus => matches!(s.codec, AudioCodec::Opus),
+        Codec::M4a => matches!(s.codec, AudioCodec::Mp4a),
+        Codec::Any => true,
+    };
+    let bitrate_ok = |s: &AudioStream| {
+        config
+            .max_bitrate_kbps
+            .is_none_or(|max| s.bitrate <= max.saturating_mul(1000))
+    };
+    let pick = |matching: Vec<&'a AudioStream>| match config.quality {
+        Quality::Best => matching.into_iter().max_by_key(|s| s.bitrate),
+        Quality::DataSaver => matching.into_iter().min_by_key(|s| s.bitrate),
+    };
+
+    pick(
+        streams
+            .iter()
+            .filter(|s| codec_ok(s) && bitrate_ok(s))
+            .collect(),
+    )
+    .or_else(|| pick(streams.iter().filter(|s| bitrate_ok(s)).collect()))
+    .or_else(|| {
+        streams
+            .iter()
+            .filter(|s| codec_ok(s))
+            .min_by_key(|s| s.bitrate)
+    })
+    .or_else(|| streams.iter().min_by_key(|s| s.bitrate))
+}
+
 // #[tokio::test]
 // async fn test_search() {
 //     let client = YoutubeClient::new();
@@ -0,0 +1,446 @@
+// Submits listens to a local HTTP server standing in for ListenBrainz and Last.fm, set
+// through the `url` of each service in the config
+use feather::config::{LastfmConfig, ListenBrainzConfig, ScrobbleConfig};
+use feather::database::{HistoryDB, HistoryEntry, ScrobbleDB};
+use feather::scrobble::{Listen, ScrobbleService, Scrobbler};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Held by every test, as they point the databases at their own directory through the
+/// environment and sled allows a database to be opened only once.
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const SONG_ID: &str = "abc123";
+
+// A request the mock server received
+struct Request {
+    head: String, // Request line and headers
+    body: String, // Body as text
+}
+
+impl Request {
+    // Returns the value of a header, matching its name in any case
+    fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    // Returns a parameter of a form body
+    fn param(&self, name: &str) -> Option<String> {
+        form(&self.body)
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    // Parses a JSON body
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+// An HTTP server answering with scripted replies, and with 200 once they run out
+struct Mock {
+    url: String,                                  // Address of the server
+    requests: Arc<Mutex<Vec<Request>>>,           // Requests in the order received
+    replies: Arc<Mutex<VecDeque<(u16, String)>>>, // Status and body of the next replies
+}
+
+impl Mock {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mock = Self {
+            url: format!("http://{}", listener.local_addr().unwrap()),
+            requests: Default::default(),
+            replies: Default::default(),
+        };
+        let requests = Arc::clone(&mock.requests);
+        let replies = Arc::clone(&mock.replies);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = Arc::clone(&requests);
+                let replies = Arc::clone(&replies);
+                tokio::spawn(serve(stream, requests, replies));
+            }
+        });
+        mock
+    }
+
+    fn reply(&self, status: u16, body: &str) {
+        self.replies
+            .lock()
+            .unwrap()
+            .push_back((status, body.to_string()));
+    }
+
+    fn count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    // Runs `check` on the request received `index`th
+    fn request<T>(&self, index: usize, check: impl FnOnce(&Request) -> T) -> T {
+        check(&self.requests.lock().unwrap()[index])
+    }
+}
+
+// Answers the requests of one connection
+async fn serve(
+    mut stream: TcpStream,
+    requests: Arc<Mutex<Vec<Request>>>,
+    replies: Arc<Mutex<VecDeque<(u16, String)>>>,
+) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let head_end = loop {
+            if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            }
+        };
+        let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+        let length: usize = head
+            .lines()
+            .find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse().unwrap())
+            })
+            .unwrap_or(0);
+        while buffer.len() < head_end + length {
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            }
+        }
+        let body = String::from_utf8_lossy(&buffer[head_end..head_end + length]).to_string();
+        buffer.drain(..head_end + length);
+        requests.lock().unwrap().push(Request { head, body });
+
+        let (status, reply) = replies
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or((200, r#"{"status":"ok"}"#.to_string()));
+        let response = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            reply.len(),
+            reply
+        );
+        if stream.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Decodes an application/x-www-form-urlencoded body
+fn form(body: &str) -> Vec<(String, String)> {
+    let decode = |text: &str| {
+        let text = text.replace('+', " ");
+        let bytes = text.as_bytes();
+        let mut decoded = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                decoded.push(u8::from_str_radix(&text[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).unwrap()
+    };
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (decode(key), decode(value)))
+        .collect()
+}
+
+// Points the databases at a new directory, removed when the returned value is dropped
+fn data_dir() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    // SAFETY: every test holds `SERIAL` while it reads or writes the environment.
+    unsafe { std::env::set_var("XDG_DATA_HOME", dir.path()) };
+    dir
+}
+
+// Opens the history with one entry for the song that is listened to
+fn history() -> Arc<HistoryDB> {
+    let history = Arc::new(HistoryDB::new().unwrap());
+    let entry = HistoryEntry::new(
+        "Song".to_string(),
+        SONG_ID.to_string(),
+        vec!["First".to_string(), "Second".to_string()],
+    )
+    .unwrap();
+    history.add_entry(&entry).unwrap();
+    history
+}
+
+fn listen() -> Listen {
+    Listen::new(
+        SONG_ID.to_string(),
+        "Song".to_string(),
+        vec!["First".to_string(), "Second".to_string()],
+        Some(200),
+    )
+}
+
+fn scrobbled(history: &HistoryDB) -> bool {
+    history.get_history().unwrap()[0].scrobbled
+}
+
+fn listenbrainz(mock: &Mock) -> ScrobbleConfig {
+    ScrobbleConfig {
+        listenbrainz: Some(ListenBrainzConfig {
+            token: "token".to_string(),
+            // A trailing slash is left out of the requests
+            url: format!("{}/", mock.url),
+        }),
+        ..Default::default()
+    }
+}
+
+fn lastfm(mock: &Mock) -> ScrobbleConfig {
+    ScrobbleConfig {
+        lastfm: Some(LastfmConfig {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+            username: Some("user".to_string()),
+            password: Some("pass word".to_string()),
+            url: format!("{}/2.0/", mock.url),
+            ..Default::default()
+        }),
+        now_playing: false,
+        ..Default::default()
+    }
+}
+
+const SESSION: &str = r#"{"session":{"name":"user","key":"SK","subscriber":0}}"#;
+const ACCEPTED: &str = r#"{"scrobbles":{"@attr":{"accepted":1,"ignored":0}}}"#;
+
+#[tokio::test]
+async fn submits_to_listenbrainz() {
+    let _serial = SERIAL.lock().await;
+    let _data = data_dir();
+    let mock = Mock::start().await;
+    let history = history();
+    let scrobbler = Scrobbler::new(listenbrainz(&mock), Arc::clone(&history)).unwrap();
+    let listen = listen();
+
+    scrobbler.now_playing(&listen).await.unwrap();
+    mock.request(0, |request| {
+        assert!(request.head.starts_with("POST /1/submit-listens "));
+        assert_eq!(request.header("authorization"), Some("Token token"));
+        let body = request.json();
+        assert_eq!(body["listen_type"], "playing_now");
+        assert!(body["payload"][0].get("listened_at").is_none());
+        let metadata = &body["payload"][0]["track_metadata"];
+        assert_eq!(metadata["artist_name"], "First, Second");
+        assert_eq!(metadata["track_name"], "Song");
+        assert_eq!(metadata["additional_info"]["duration_ms"], 200_000);
+    });
+    assert!(!scrobbled(&history));
+
+    scrobbler.submit(listen.clone()).await.unwrap();
+    mock.request(1, |request| {
+        let body = request.json();
+        assert_eq!(body["listen_type"], "single");
+        assert_eq!(body["payload"][0]["listened_at"], listen.listened_at);
+    });
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert!(scrobbled(&history));
+}
+
+#[tokio::test]
+async fn keeps_failed_listens_across_restarts() {
+    let _serial = SERIAL.lock().await;
+    let _data = data_dir();
+    let mock = Mock::start().await;
+    let history = history();
+    let scrobbler = Scrobbler::new(listenbrainz(&mock), Arc::clone(&history)).unwrap();
+    let listen = listen();
+
+    mock.reply(503, r#"{"code":503,"error":"down"}"#);
+    scrobbler.submit(listen.clone()).await.unwrap();
+    assert_eq!(mock.count(), 1);
+    // Not retried before it is due
+    assert_eq!(scrobbler.flush().await.unwrap(), 1);
+    assert_eq!(mock.count(), 1);
+    assert!(!scrobbled(&history));
+    drop(scrobbler);
+
+    // The queue outlives the scrobbler; make the listen due as if time had passed
+    {
+        let db = ScrobbleDB::new().unwrap();
+        let pending = db.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        let (key, mut scrobble) = pending[0].clone();
+        assert_eq!(scrobble.service, ScrobbleService::ListenBrainz);
+        assert_eq!(scrobble.listen, listen);
+        assert_eq!(scrobble.attempts, 1);
+        assert!(scrobble.next_attempt >= listen.listened_at + 60);
+        scrobble.next_attempt = 0;
+        db.update(key, &scrobble).unwrap();
+    }
+
+    let scrobbler = Scrobbler::new(listenbrainz(&mock), Arc::clone(&history)).unwrap();
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert_eq!(mock.count(), 2);
+    mock.request(1, |request| {
+        assert_eq!(
+            request.json()["payload"][0]["listened_at"],
+            listen.listened_at
+        );
+    });
+    assert!(scrobbled(&history));
+}
+
+#[tokio::test]
+async fn drops_rejected_listens_and_keeps_the_rest() {
+    let _serial = SERIAL.lock().await;
+    let _data = data_dir();
+    let mock = Mock::start().await;
+    let history = history();
+
+    let scrobbler = Scrobbler::new(listenbrainz(&mock), Arc::clone(&history)).unwrap();
+    mock.reply(400, r#"{"code":400,"error":"bad listen"}"#);
+    scrobbler.submit(listen()).await.unwrap();
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert!(!scrobbled(&history));
+    drop(scrobbler);
+
+    let scrobbler = Scrobbler::new(lastfm(&mock), Arc::clone(&history)).unwrap();
+    mock.reply(200, SESSION);
+    // Invalid parameters
+    mock.reply(400, r#"{"error":6,"message":"bad track"}"#);
+    scrobbler.submit(listen()).await.unwrap();
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    // Filtered by Last.fm, which still answers with success
+    mock.reply(
+        200,
+        r#"{"scrobbles":{"@attr":{"accepted":0,"ignored":"1"}}}"#,
+    );
+    scrobbler.submit(listen()).await.unwrap();
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    // Service offline, temporarily unavailable and rate limit exceeded
+    for code in [11, 16, 29] {
+        mock.reply(503, &format!(r#"{{"error":{},"message":"later"}}"#, code));
+        scrobbler.submit(listen()).await.unwrap();
+    }
+    assert_eq!(scrobbler.flush().await.unwrap(), 3);
+    assert!(!scrobbled(&history));
+}
+
+#[tokio::test]
+async fn logs_in_to_lastfm_once() {
+    let _serial = SERIAL.lock().await;
+    let _data = data_dir();
+    let mock = Mock::start().await;
+    let history = history();
+    let scrobbler = Scrobbler::new(lastfm(&mock), Arc::clone(&history)).unwrap();
+    let listen = listen();
+
+    // Announcing the song is turned off
+    scrobbler.now_playing(&listen).await.unwrap();
+    assert_eq!(mock.count(), 0);
+
+    mock.reply(200, SESSION);
+    mock.reply(200, ACCEPTED);
+    scrobbler.submit(listen.clone()).await.unwrap();
+    assert!(scrobbled(&history));
+    mock.reply(200, ACCEPTED);
+    scrobbler.submit(listen.clone()).await.unwrap();
+    assert_eq!(mock.count(), 3);
+
+    for index in 0..3 {
+        mock.request(index, |request| {
+            assert!(request.head.starts_with("POST /2.0/ "));
+            assert_eq!(request.param("format").as_deref(), Some("json"));
+            assert_eq!(request.param("api_key").as_deref(), Some("key"));
+            // Signed with every parameter but the format, sorted by name
+            let mut signed: Vec<_> = form(&request.body)
+                .into_iter()
+                .filter(|(key, _)| key != "format" && key != "api_sig")
+                .collect();
+            signed.sort();
+            let mut text: String = signed
+                .iter()
+                .map(|(key, value)| key.clone() + value)
+                .collect();
+            text.push_str("secret");
+            let signature = format!("{:x}", md5::compute(text));
+            assert_eq!(request.param("api_sig"), Some(signature));
+        });
+    }
+    mock.request(0, |request| {
+        assert_eq!(
+            request.param("method").as_deref(),
+            Some("auth.getMobileSession")
+        );
+        assert_eq!(request.param("password").as_deref(), Some("pass word"));
+    });
+    mock.request(1, |request| {
+        assert_eq!(request.param("method").as_deref(), Some("track.scrobble"));
+        assert_eq!(request.param("sk").as_deref(), Some("SK"));
+        assert_eq!(request.param("artist").as_deref(), Some("First"));
+        assert_eq!(request.param("track").as_deref(), Some("Song"));
+        assert_eq!(request.param("duration").as_deref(), Some("200"));
+        assert_eq!(
+            request.param("timestamp"),
+            Some(listen.listened_at.to_string())
+        );
+    });
+}
+
+#[tokio::test]
+async fn logs_in_to_lastfm_again_after_an_invalid_session() {
+    let _serial = SERIAL.lock().await;
+    let _data = data_dir();
+    let mock = Mock::start().await;
+    let history = history();
+    let scrobbler = Scrobbler::new(lastfm(&mock), Arc::clone(&history)).unwrap();
+
+    mock.reply(200, SESSION);
+    mock.reply(403, r#"{"error":9,"message":"Invalid session key"}"#);
+    scrobbler.submit(listen()).await.unwrap();
+    assert_eq!(scrobbler.flush().await.unwrap(), 1);
+    drop(scrobbler);
+
+    let db = ScrobbleDB::new().unwrap();
+    assert_eq!(db.session_key(ScrobbleService::Lastfm).unwrap(), None);
+    let (key, mut scrobble) = db.pending().unwrap()[0].clone();
+    scrobble.next_attempt = 0;
+    db.update(key, &scrobble).unwrap();
+    drop(db);
+
+    let scrobbler = Scrobbler::new(lastfm(&mock), Arc::clone(&history)).unwrap();
+    mock.reply(
+        200,
+        r#"{"session":{"name":"user","key":"NEW","subscriber":0}}"#,
+    );
+    mock.reply(200, ACCEPTED);
+    assert_eq!(scrobbler.flush().await.unwrap(), 0);
+    assert_eq!(mock.count(), 4);
+    mock.request(2, |request| {
+        assert_eq!(
+            request.param("method").as_deref(),
+            Some("auth.getMobileSession")
+        );
+    });
+    mock.request(3, |request| {
+        assert_eq!(request.param("sk").as_deref(), Some("NEW"));
+    });
+    assert!(scrobbled(&history));
+}
@@ -0,0 +1,573 @@
+use crate::backend::{Backend, BackendError};
+use crate::{
+    devices::DevicePicker, favorites::Favorites, history::History, ipc, lyrics::LyricsView, mpris,
+    notify, player::SongPlayer, playlist::PlaylistView, queue::QueueView, search::Search, status,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use feather::config::Config;
+use feather::database::HistoryDB;
+use ratatui::{
+    Frame, Terminal, TerminalOptions, Viewport,
+    backend::CrosstermBackend,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
+use std::{io, sync::Arc, thread};
+use tokio::{
+    runtime::Handle,
+    sync::mpsc::{self, error::TrySendError},
+    time::{Duration, interval},
+};
+
+/// Opens the databases and the player, and starts everything that runs for as long as
+/// this Feather does: playback, MPRIS, the control socket, the status files and restoring
+/// the last session.
+/// Must be called from within the Tokio runtime.
+pub fn start() -> Result<Arc<Backend>, BackendError> {
+    let history =
+        Arc::new(HistoryDB::new().map_err(|e| BackendError::HistoryError(e.to_string()))?);
+    let config = Config::load().map_err(|e| BackendError::ConfigError(e.to_string()))?;
+    // Cookies let YouTube serve songs it only plays for signed-in users
+    let cookies = config
+        .youtube
+        .load_cookies()
+        .map_err(|e| BackendError::CookieError(e.to_string()))?;
+    let cookies_file = cookies.as_ref().map(|cookies| cookies.path());
+    let backend = Arc::new(Backend::new(history, config, cookies_file)?);
+
+    tokio::spawn(Arc::clone(&backend).drive());
+    // Retry listens that could not be scrobbled, including those left from earlier runs
+    if let Some(scrobbler) = &backend.scrobbler {
+        tokio::spawn(scrobbler.clone().run());
+    }
+    // Let media keys and desktop widgets control playback; without a session bus this just stops
+    tokio::spawn(mpris::run(backend.clone()));
+    // Tell the desktop about new songs and failures, if enabled
+    tokio::spawn(notify::run(backend.clone()));
+    // Publish what is playing for status bars and shell prompts, if enabled
+    tokio::spawn(status::run(backend.clone()));
+    // Accept commands, `feather ctl` and attaching TUIs on the control socket
+    tokio::spawn(ipc::serve(backend.clone()));
+
+    let restoring = backend.clone();
+    tokio::spawn(async move {
+        // Sign in first, so the restored song is already streamed with the login
+        if let Some(cookies) = &cookies {
+            restoring.sign_in(cookies).await;
+        }
+        let _ = restoring.restore_last_session().await;
+    });
+    Ok(backend)
+}
+
+/// Runs a TUI for a client attached over the control socket. It draws into a screen of
+/// the client's size, sends what it draws to `out` and takes its input from `events`.
+/// It ends when `events` is closed, the user quits it or `out` is full because the client
+/// does not keep up, all of which detach the client.
+pub fn attach(
+    backend: Arc<Backend>,
+    width: u16,
+    height: u16,
+    events: mpsc::Receiver<Event>,
+    out: mpsc::Sender<Vec<u8>>,
+) -> io::Result<()> {
+    let screen = RemoteScreen {
+        buffer: Vec::new(),
+        out,
+    };
+    let options = TerminalOptions {
+        viewport: Viewport::Fixed(Rect::new(0, 0, width, height)),
+    };
+    let mut terminal = Terminal::with_options(CrosstermBackend::new(screen), options)?;
+
+    // The TUI is driven on a thread of its own, like the local one is on the main thread;
+    // the tasks it spawns still run on the shared runtime
+    let runtime = Handle::current();
+    thread::Builder::new()
+        .name("feather-attach".to_string())
+        .spawn(move || runtime.block_on(App::new(backend).run(&mut terminal, events)))?;
+    Ok(())
+}
+
+/// What an attached TUI writes to its terminal, sent to the client once per frame.
+struct RemoteScreen {
+    buffer: Vec<u8>,            // Output of the frame being drawn
+    out: mpsc::Sender<Vec<u8>>, // Connection to the client
+}
+
+impl io::Write for RemoteScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        // Waiting for the client would hold up this TUI, and skipping a frame would leave
+        // its screen wrong, so a client too far behind is let go
+        self.out
+            .try_send(std::mem::take(&mut self.buffer))
+            .map_err(|e| match e {
+                TrySendError::Full(_) => io::Error::other("the client fell too far behind"),
+                TrySendError::Closed(_) => io::ErrorKind::BrokenPipe.into(),
+            })
+    }
+}
+
+/// Whether the terminal of a TUI has focus, counted by the backend from the moment the TUI
+/// starts until it ends, even by panicking.
+struct Focus {
+    backend: Arc<Backend>, // Backend counting the focused TUIs
+    focused: bool,         // Whether this TUI is counted
+}
+
+impl Focus {
+    /// Counts a TUI that just started as focused.
+    fn new(backend: Arc<Backend>) -> Self {
+        backend.set_tui_focus(true);
+        Self {
+            backend,
+            focused: true,
+        }
+    }
+
+    /// Records a focus change reported by the terminal.
+    fn set(&mut self, focused: bool) {
+        if self.focused != focused {
+            self.focused = focused;
+            self.backend.set_tui_focus(focused);
+        }
+    }
+}
+
+impl Drop for Focus {
+    fn drop(&mut self) {
+        self.set(false);
+    }
+}
+
+/// Enum representing different states of the application.
+#[derive(Debug)]
+enum State {
+    HelpMode,
+    Global,
+    Search,
+    History,
+    Playlist,
+    Queue,
+    Favorites,
+    Lyrics,
+    Devices,
+    // UserPlaylist,
+    // CurrentPlayingPlaylist,
+    SongPlayer,
+}
+
+impl State {
+    /// Restores a pane from the name saved in the session.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Search" => Some(State::Search),
+            "History" => Some(State::History),
+            "Playlist" => Some(State::Playlist),
+            "Queue" => Some(State::Queue),
+            "Favorites" => Some(State::Favorites),
+            "Lyrics" => Some(State::Lyrics),
+            "SongPlayer" => Some(State::SongPlayer),
+            _ => None,
+        }
+    }
+}
+
+/// The TUI: the panes and which of them has focus. Any number of them can show the
+/// same backend, each in its own terminal.
+pub struct App<'a> {
+    state: State,
+    search: Search<'a>,
+    history: History,
+    playlist: PlaylistView<'a>,
+    queue: QueueView,
+    favorites: Favorites,
+    lyrics: LyricsView,
+    devices: DevicePicker,
+    // user_playlist: UserPlaylist,
+    // current_playling_playlist: CurrentPlayingPlaylist,
+    top_bar: TopBar,
+    player: SongPlayer,
+    backend: Arc<Backend>,
+    help_mode: bool,
+    exit: bool,
+}
+
+impl App<'_> {
+    /// Creates a TUI for the backend, opening the pane that was active in the last session.
+    pub fn new(backend: Arc<Backend>) -> Self {
+        let (tx, rx) = mpsc::channel(32);
+        let state = backend
+            .sessions
+            .load()
+            .ok()
+            .flatten()
+            .and_then(|s| State::from_name(&s.pane))
+            .unwrap_or(State::Global);
+
+        App {
+            state,
+            search: Search::new(backend.clone(), tx.clone()),
+            history: History::new(backend.history.clone(), backend.clone(), tx.clone()),
+            playlist: PlaylistView::new(backend.playlists.clone(), backend.clone(), tx.clone()),
+            queue: QueueView::new(backend.clone(), tx.clone()),
+            favorites: Favorites::new(backend.clone(), tx.clone()),
+            lyrics: LyricsView::new(backend.clone()),
+            devices: DevicePicker::new(backend.clone()),
+            // user_playlist: UserPlaylist {},
+            // current_playling_playlist: CurrentPlayingPlaylist {},
+            top_bar: TopBar::new(),
+            player: SongPlayer::new(backend.clone(), tx, rx),
+            backend,
+            help_mode: false,
+            exit: false,
+        }
+    }
+
+    /// Handles global keystrokes and state transitions.
+    fn handle_global_keystrokes(&mut self, key: KeyEvent) {
+        match self.state {
+            State::Global => match key.code {
+                KeyCode::Char('s') => self.state = State::Search,
+                KeyCode::Char('h') => self.state = State::History,
+                KeyCode::Char('l') => self.state = State::Playlist,
+                KeyCode::Char('q') => self.state = State::Queue,
+                KeyCode::Char('f') => self.state = State::Favorites,
+                KeyCode::Char('y') => self.state = State::Lyrics,
+                KeyCode::Char('o') => {
+                    self.devices.refresh();
+                    self.state = State::Devices;
+                }
+                KeyCode::Char('p') => self.state = State::SongPlayer,
+                KeyCode::Char('?') => {
+                    self.help_mode = true;
+                    self.state = State::HelpMode;
+                }
+                KeyCode::Esc => {
+                    self.exit = true;
+                }
+                _ => (),
+            },
+            State::Search => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.search.handle_keystrokes(key),
+            },
+            State::HelpMode => match key.code {
+                KeyCode::Esc => {
+                    self.state = State::Global;
+                    self.help_mode = false;
+                }
+                _ => (),
+            },
+            State::History => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.history.handle_keystrokes(key),
+            },
+            State::Playlist => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.playlist.handle_keystrokes(key),
+            },
+            State::Queue => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.queue.handle_keystrokes(key),
+            },
+            State::Favorites => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.favorites.handle_keystrokes(key),
+            },
+            State::Lyrics => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.lyrics.handle_keystrokes(key),
+            },
+            State::Devices => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.devices.handle_keystrokes(key),
+            },
+            State::SongPlayer => match key.code {
+                KeyCode::Esc => self.state = State::Global,
+                _ => self.player.handle_keystrokes(key),
+            },
+        }
+    }
+
+    /// Main loop: draws the TUI and handles terminal events until the user quits
+    /// or `events` is closed.
+    ///
+    /// # Arguments
+    /// * `terminal` - Terminal to draw on.
+    /// * `events` - Key presses and resizes of that terminal.
+    pub async fn run<B: ratatui::backend::Backend>(
+        mut self,
+        terminal: &mut Terminal<B>,
+        mut events: mpsc::Receiver<Event>,
+    ) {
+        let mut redraw_interval = interval(Duration::from_millis(250)); // Redraw every 250ms
+        // A TUI that just started or attached is what the user is looking at
+        let mut focus = Focus::new(Arc::clone(&self.backend));
+
+        while !self.exit {
+            // Stop if the terminal is gone, e.g. an attached client disconnected
+            if terminal.draw(|frame| self.draw(frame)).is_err() {
+                break;
+            }
+
+            tokio::select! {
+                _ = redraw_interval.tick() => {}
+                event = events.recv() => match event {
+                    Some(Event::Key(key)) => self.handle_global_keystrokes(key),
+                    Some(Event::Resize(width, height)) => {
+                        let _ = terminal.resize(Rect::new(0, 0, width, height));
+                    }
+                    Some(Event::FocusGained) => focus.set(true),
+                    Some(Event::FocusLost) => focus.set(false),
+                    Some(_) => (),
+                    None => self.exit = true,
+                },
+            }
+        }
+        let _ = self.backend.save_session(Some(format!("{:?}", self.state)));
+    }
+
+    // Draws the panes, or the help table in help mode
+    fn draw(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        let layout = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(10),
+                Constraint::Percentage(75),
+                Constraint::Percentage(15),
+            ])
+            .split(area);
+
+        let middle_layout = Layout::default()
+            .direction(ratatui::layout::Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(layout[1]);
+
+        if !self.help_mode {
+            self.top_bar.render(
+                layout[0],
+                frame.buffer_mut(),
+                &self.state,
+                self.backend.notice(),
+            );
+            if let State::Playlist = self.state {
+                self.playlist.render(middle_layout[0], frame.buffer_mut());
+            } else {
+                self.search.render(middle_layout[0], frame.buffer_mut());
+            }
+            match self.state {
+                State::Queue => self.queue.render(middle_layout[1], frame.buffer_mut()),
+                State::Favorites => self.favorites.render(middle_layout[1], frame.buffer_mut()),
+                State::Lyrics => self.lyrics.render(middle_layout[1], frame.buffer_mut()),
+                State::Devices => self.devices.render(middle_layout[1], frame.buffer_mut()),
+                _ => self.history.render(middle_layout[1], frame.buffer_mut()),
+            }
+            self.player.render(layout[2], frame.buffer_mut());
+        } else {
+            let rows = vec![
+                Row::new(vec![Cell::from("s"), Cell::from("Search")]),
+                Row::new(vec![Cell::from("h"), Cell::from("History")]),
+                Row::new(vec![Cell::from("p"), Cell::from("Player")]),
+                Row::new(vec![Cell::from("l"), Cell::from("Playlists")]),
+                Row::new(vec![Cell::from("q"), Cell::from("Queue")]),
+                Row::new(vec![Cell::from("f"), Cell::from("Favorites")]),
+                Row::new(vec![Cell::from("y"), Cell::from("Lyrics")]),
+                Row::new(vec![Cell::from("o"), Cell::from("Audio output device")]),
+                Row::new(vec![Cell::from("?"), Cell::from("Toggle Help Mode")]),
+                Row::new(vec![
+                    Cell::from("TAB (Search)"),
+                    Cell::from("Toggle between search input and results"),
+                ]),
+                Row::new(vec![
+                    Cell::from("TAB (Playlists)"),
+                    Cell::from("Cycle search, remote results and local playlists"),
+                ]),
+                Row::new(vec![
+                    Cell::from("g (Local playlists)"),
+                    Cell::from("Toggle gapless (no crossfade)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("S (Playlist tracks)"),
+                    Cell::from("Save remote playlist as local playlist"),
+                ]),
+                Row::new(vec![
+                    Cell::from("r (Local playlists)"),
+                    Cell::from("Re-sync from source and review changes"),
+                ]),
+                Row::new(vec![
+                    Cell::from("i (Local playlists)"),
+                    Cell::from("Import a CSV or 'Artist - Title' track list"),
+                ]),
+                Row::new(vec![
+                    Cell::from("Esc (Global)"),
+                    Cell::from("Quit application (detach if attached to a daemon)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("Esc (Non-Global)"),
+                    Cell::from("Switch to Global Mode"),
+                ]),
+                Row::new(vec![
+                    Cell::from("↑ / k(History/Search)"),
+                    Cell::from("Navigate up in list"),
+                ]),
+                Row::new(vec![
+                    Cell::from("↓ / j(History/Search)"),
+                    Cell::from("Navigate down in list"),
+                ]),
+                Row::new(vec![
+                    Cell::from("Space / ; (Player)"),
+                    Cell::from("Pause current song"),
+                ]),
+                Row::new(vec![
+                    Cell::from("→ / l (Player)"),
+                    Cell::from("Skip forward a small step (default 5 seconds)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("← / j (Player)"),
+                    Cell::from("Rewind a small step (default 5 seconds)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("Shift+→ / L, Shift+← / J (Player)"),
+                    Cell::from("Skip forward / rewind a large step (default 60 seconds)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("0-9 (Player)"),
+                    Cell::from("Jump to 0-90% of the song"),
+                ]),
+                Row::new(vec![
+                    Cell::from("[ / ] (Player)"),
+                    Cell::from("Previous / next chapter"),
+                ]),
+                Row::new(vec![
+                    Cell::from("g (Player)"),
+                    Cell::from("Go to time (1:23, 1:02:03 or 50%)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("n / b (Player)"),
+                    Cell::from("Next / previous song in queue"),
+                ]),
+                Row::new(vec![
+                    Cell::from("+ / - (Player)"),
+                    Cell::from("Volume up / down"),
+                ]),
+                Row::new(vec![
+                    Cell::from("> / < (Player)"),
+                    Cell::from("Speed up / slow down"),
+                ]),
+                Row::new(vec![
+                    Cell::from("Backspace (Player)"),
+                    Cell::from("Reset speed to 1x"),
+                ]),
+                Row::new(vec![
+                    Cell::from("e (Player)"),
+                    Cell::from("Next equalizer / filter preset"),
+                ]),
+                Row::new(vec![
+                    Cell::from("i (Player)"),
+                    Cell::from("Toggle track info (format, bitrate)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("t (Player)"),
+                    Cell::from("Sleep timer (minutes, track, queue or off)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("T / x (Player)"),
+                    Cell::from("Extend sleep timer by 5 minutes / cancel it"),
+                ]),
+                Row::new(vec![
+                    Cell::from("r (Player)"),
+                    Cell::from("Cycle repeat off / all / one"),
+                ]),
+                Row::new(vec![
+                    Cell::from("s (Player)"),
+                    Cell::from("Toggle shuffle (off restores queue order)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("a / A (Player)"),
+                    Cell::from("Set loop point A, then B, then clear / clear loop"),
+                ]),
+                Row::new(vec![
+                    Cell::from("w (Player)"),
+                    Cell::from("Save A-B loop under a name"),
+                ]),
+                Row::new(vec![
+                    Cell::from("m / M (Player)"),
+                    Cell::from("Next saved loop / delete saved loop"),
+                ]),
+                Row::new(vec![
+                    Cell::from("a (Search/History/Playlist)"),
+                    Cell::from("Add song to queue"),
+                ]),
+                Row::new(vec![
+                    Cell::from("TAB (History)"),
+                    Cell::from("Toggle between history and continue listening"),
+                ]),
+                Row::new(vec![
+                    Cell::from("f (Non-Global)"),
+                    Cell::from("Toggle song as favorite"),
+                ]),
+                Row::new(vec![
+                    Cell::from("r (Favorites)"),
+                    Cell::from("Shuffle play favorites"),
+                ]),
+                Row::new(vec![
+                    Cell::from("d (Queue)"),
+                    Cell::from("Remove song from queue"),
+                ]),
+                Row::new(vec![
+                    Cell::from("↑ / k, ↓ / j (Lyrics)"),
+                    Cell::from("Scroll lyrics (stops following the song)"),
+                ]),
+                Row::new(vec![
+                    Cell::from("c (Lyrics)"),
+                    Cell::from("Follow the line being sung again"),
+                ]),
+            ];
+
+            let help_table = Table::new(
+                rows,
+                [Constraint::Percentage(20), Constraint::Percentage(80)],
+            )
+            .block(Block::default().borders(Borders::ALL).title("Help"))
+            .header(Row::new(vec![Cell::from("Key"), Cell::from("Action")]));
+
+            help_table.render(area, frame.buffer_mut());
+        }
+    }
+}
+
+/// Represents the top bar UI component.
+struct TopBar;
+
+impl TopBar {
+    fn new() -> Self {
+        Self
+    }
+    fn render(&mut self, area: Rect, buf: &mut Buffer, state: &State, notice: Option<String>) {
+        let mut s = format!("Feather | Current Mode : {:?}", state);
+        if let Some(notice) = notice {
+            s.push_str(&format!(" | {}", notice));
+        }
+        Paragraph::new(s)
+            .block(Block::default().borders(Borders::ALL))
+            .render(area, buf);
+    }
+}
+
+#[allow(unused)]
+/// Placeholder struct for user playlists.
+struct UserPlaylist {}
+#[allow(unused)]
+/// Placeholder struct for currently playing playlist.
+struct CurrentPlayingPlaylist {}
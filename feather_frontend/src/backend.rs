@@ -1,30 +1,97 @@
 use feather::{
     ArtistName, SongId, SongName,
-    database::{HistoryDB, HistoryEntry},
-    player::{MpvError, Player},
-    yt::YoutubeClient,
+    config::Config,
+    cookies::Cookies,
+    database::{
+        AbLoop, FavoritesDB, HistoryDB, HistoryEntry, LyricsDB, PlaylistManager, PreferencesDB,
+        ResumeDB, ResumePosition, SessionDB, SessionState, SongEntry, TrackSettingsDB,
+    },
+    lyrics::Lyrics,
+    player::{AudioDevice, MAX_CROSSFADE_SECS, MpvError, Player, Seek},
+    queue::{Queue, Repeat},
+    scrobble::{Listen, Scrobbler},
+    sleep::{SleepAction, SleepMode, SleepTimer},
+    yt::{AudioStreamInfo, YoutubeClient},
 };
+use std::path::Path;
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
 use std::time::Duration;
+use tokio::sync::broadcast;
 
 use thiserror::Error;
 
+const TICK_INTERVAL: Duration = Duration::from_millis(500); // How often playback is driven
+const RESUME_SAVE_TICKS: u32 = 10; // Ticks between saving the resume position
+const SESSION_SAVE_TICKS: u32 = 20; // Ticks between saving the session
+const RESTART_SECS: f64 = 5.0; // Jumping back this close to the start plays a song anew
+const EVENT_CAPACITY: usize = 16; // Player events kept for subscribers that fall behind
+
 /// The `Backend` struct manages the YouTube client, music player, and history database.
-/// It also tracks the currently playing song.
+/// It also tracks the currently playing song and the play queue.
 pub struct Backend {
-    pub yt: YoutubeClient,         // YouTube client for fetching song URLs
-    pub player: Player,            // Music player instance
-    pub history: Arc<HistoryDB>,   // Shared history database
-    pub song: Mutex<Option<Song>>, // Mutex-protected optional current song
+    pub yt: YoutubeClient,                  // YouTube client for fetching song URLs
+    pub player: Player,                     // Music player instance
+    pub history: Arc<HistoryDB>,            // Shared history database
+    pub playlists: Arc<PlaylistManager>,    // Local playlists
+    pub sessions: SessionDB,                // Playback state kept across restarts
+    pub song: Mutex<Option<Song>>,          // Mutex-protected optional current song
+    pub queue: Mutex<Queue>,                // Mutex-protected play queue
+    pub resume: ResumeDB,                   // Per-track resume positions
+    pub favorites: FavoritesDB,             // Liked songs
+    pub track_settings: TrackSettingsDB,    // Per-track settings such as speed
+    pub preferences: PreferencesDB,         // Choices kept across restarts
+    lyrics: LyricsDB,                       // Lyrics fetched before, for offline use
+    filters: Mutex<FilterState>,            // Active audio filters
+    stream: Mutex<Option<AudioStreamInfo>>, // Stream of the current song
+    notice: Mutex<Option<String>>,          // Message to show to the user, if any
+    sleep: Mutex<Option<SleepTimer>>,       // Running sleep timer, if any
+    preloaded: Mutex<Option<(SongId, AudioStreamInfo)>>, // Next song loaded for a crossfade
+    modes: Mutex<PlayModes>,                // Repeat and shuffle modes
+    pub scrobbler: Option<Arc<Scrobbler>>,  // Submits listens, if a service is configured
+    listen: Mutex<Option<ListenState>>,     // How much of the current song was heard
+    events: broadcast::Sender<PlayerEvent>, // Track changes and failures, for whoever listens
+    focused_tuis: AtomicUsize,              // TUIs whose terminal has focus
+    pub config: Config,                     // User configuration
+}
+
+/// Audio filters currently applied to the player.
+#[derive(Clone, Copy)]
+struct FilterState {
+    preset: usize,  // Index of the active filter preset in the config
+    loudnorm: bool, // Whether the loudnorm fallback is active
+}
+
+/// Something that happened to playback, sent to `Backend::subscribe` receivers.
+#[derive(Clone)]
+pub enum PlayerEvent {
+    TrackChanged(Song),                           // A song started playing
+    PlaybackFailed { song: Song, error: String }, // A song could not be played
+}
+
+/// How much of the current song was heard, for scrobbling.
+struct ListenState {
+    listen: Listen,   // The song and when it started
+    played_secs: f64, // Time it was actually playing
+    position: f64,    // Position at the last tick, to notice the song starting over
+    announced: bool,  // Whether it was announced as playing now
+    submitted: bool,  // Whether it was submitted as a listen
+}
+
+/// How the player moves on when a song ends.
+#[derive(Clone, Copy)]
+struct PlayModes {
+    repeat: Repeat, // What happens at the end of a song or the queue
+    shuffle: bool,  // Whether queues play in shuffled order
 }
 
 /// Represents a song with its name, ID, and artist(s).
 #[derive(Clone)]
 pub struct Song {
-    pub song_name: SongName,      // Name of the song
-    pub song_id: SongId,          // Unique identifier for the song
-    artist_name: Vec<ArtistName>, // List of artists performing the song
+    pub song_name: SongName,          // Name of the song
+    pub song_id: SongId,              // Unique identifier for the song
+    pub artist_name: Vec<ArtistName>, // List of artists performing the song
 }
 
 /// Implements conversion from `Song` to `HistoryEntry`, ensuring valid history records.
@@ -35,6 +102,20 @@ impl From<Song> for HistoryEntry {
     }
 }
 
+/// Converts a stored playlist song into a playable `Song`.
+impl From<SongEntry> for Song {
+    fn from(value: SongEntry) -> Self {
+        Song::new(value.song_name, value.song_id, value.artist_name)
+    }
+}
+
+/// Converts a `Song` into an entry that can be stored in a local playlist.
+impl From<Song> for SongEntry {
+    fn from(value: Song) -> Self {
+        SongEntry::new(value.song_name, value.song_id, value.artist_name)
+    }
+}
+
 impl Song {
     /// Creates a new `Song` instance.
     pub fn new(song_name: SongName, song_id: SongId, artist_name: Vec<ArtistName>) -> Self {
@@ -61,6 +142,15 @@ pub enum BackendError {
     #[error("History database error: {0}")]
     HistoryError(String), // Error related to history database operations
 
+    #[error("Database error: {0}")]
+    DatabaseError(String), // Error related to other database operations
+
+    #[error("Config error: {0}")]
+    ConfigError(String), // Error loading the user configuration
+
+    #[error("Cookie error: {0}")]
+    CookieError(String), // Error reading the YouTube cookies file
+
     #[error("Playback error: {0}")]
     PlaybackError(String), // Error related to playback issues
 }
@@ -70,17 +160,76 @@ impl Backend {
     ///
     /// # Arguments
     /// * `history` - Shared reference to the history database.
-    /// * `cookies` - Optional cookie string for authentication.
+    /// * `config` - User configuration.
+    /// * `cookies_file` - Optional Netscape cookies.txt that mpv sends with its requests.
     ///
     /// # Returns
     /// * `Result<Self, BackendError>` - Returns `Backend` on success or an error on failure.
-    pub fn new(history: Arc<HistoryDB>, cookies: Option<String>) -> Result<Self, BackendError> {
-        Ok(Self {
+    pub fn new(
+        history: Arc<HistoryDB>,
+        config: Config,
+        cookies_file: Option<&Path>,
+    ) -> Result<Self, BackendError> {
+        let filters = FilterState {
+            preset: config
+                .filters
+                .position(&config.filters.default)
+                .unwrap_or(0),
+            loudnorm: false,
+        };
+        let preferences =
+            PreferencesDB::new().map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        let saved = preferences
+            .load()
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        let modes = PlayModes {
+            repeat: saved.repeat,
+            shuffle: saved.shuffle,
+        };
+        let scrobbler = if config.scrobble.is_enabled() {
+            let scrobbler = Scrobbler::new(config.scrobble.clone(), history.clone())
+                .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+            Some(Arc::new(scrobbler))
+        } else {
+            None
+        };
+
+        let backend = Self {
             yt: YoutubeClient::new(),
-            player: Player::new(cookies).map_err(BackendError::Mpv)?,
+            player: Player::new(cookies_file).map_err(BackendError::Mpv)?,
             history,
+            playlists: Arc::new(
+                PlaylistManager::new().map_err(|e| BackendError::DatabaseError(e.to_string()))?,
+            ),
+            sessions: SessionDB::new().map_err(|e| BackendError::DatabaseError(e.to_string()))?,
             song: Mutex::new(None),
-        })
+            queue: Mutex::new(Queue::new()),
+            resume: ResumeDB::new().map_err(|e| BackendError::DatabaseError(e.to_string()))?,
+            favorites: FavoritesDB::new()
+                .map_err(|e| BackendError::DatabaseError(e.to_string()))?,
+            track_settings: TrackSettingsDB::new()
+                .map_err(|e| BackendError::DatabaseError(e.to_string()))?,
+            preferences,
+            lyrics: LyricsDB::new().map_err(|e| BackendError::DatabaseError(e.to_string()))?,
+            filters: Mutex::new(filters),
+            stream: Mutex::new(None),
+            notice: Mutex::new(None),
+            sleep: Mutex::new(None),
+            preloaded: Mutex::new(None),
+            modes: Mutex::new(modes),
+            scrobbler,
+            listen: Mutex::new(None),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            focused_tuis: AtomicUsize::new(0),
+            config,
+        };
+        backend.apply_filters(filters)?;
+        backend
+            .player
+            .set_repeat_one(modes.repeat == Repeat::One)
+            .map_err(BackendError::Mpv)?;
+        backend.restore_audio_device();
+        Ok(backend)
     }
 
     /// Plays a song by fetching its URL from YouTube and passing it to the player.
@@ -91,47 +240,1126 @@ impl Backend {
     /// # Returns
     /// * `Result<(), BackendError>` - Returns `Ok(())` on success or an error on failure.
     pub async fn play_music(&self, song: Song) -> Result<(), BackendError> {
+        self.start_music(song, true).await
+    }
+
+    // Plays a song, adding it to history if `record` is set
+    async fn start_music(&self, song: Song, record: bool) -> Result<(), BackendError> {
+        if let Err(e) = self.load_music(&song).await {
+            self.emit(PlayerEvent::PlaybackFailed {
+                song,
+                error: e.to_string(),
+            });
+            return Err(e);
+        }
+        self.emit(PlayerEvent::TrackChanged(song.clone()));
+        if !record {
+            return Ok(());
+        }
+
+        // Add the song to history
+        self.history
+            .add_entry(&HistoryEntry::from(song))
+            .map_err(|e| BackendError::HistoryError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // Fetches the stream of a song and starts playing it
+    async fn load_music(&self, song: &Song) -> Result<(), BackendError> {
+        let stream = self.fetch_url(&song.song_id).await?;
+        // Loading a track cancels any crossfade and drops the preloaded one
+        self.lock_preloaded()?.take();
+
+        // Update the currently playing song in a mutex-protected section
+        self.set_current_song(Some(song.clone()), Some(stream.clone()))?;
+
+        // Play the song, continuing long tracks where they were left off
+        self.apply_track_speed(&song.song_id)?;
+        self.apply_loudness(stream.loudness_db)?;
+        let start = self.resume_position(&song.song_id);
+        self.player
+            .play_at(&stream.url, start, false)
+            .map_err(BackendError::Mpv)
+    }
+
+    /// Loads a song paused at `position` seconds without adding it to history.
+    ///
+    /// # Arguments
+    /// * `song` - The song to be loaded.
+    /// * `position` - Playback position to resume from.
+    pub async fn resume_music(&self, song: Song, position: f64) -> Result<(), BackendError> {
+        let stream = self.fetch_url(&song.song_id).await?;
+        self.lock_preloaded()?.take();
+        self.apply_track_speed(&song.song_id)?;
+        self.apply_loudness(stream.loudness_db)?;
+        self.set_current_song(Some(song), Some(stream.clone()))?;
+        self.player
+            .play_at(&stream.url, Some(position), true)
+            .map_err(BackendError::Mpv)?;
+        Ok(())
+    }
+
+    // Returns the saved position of a song if resuming is enabled
+    fn resume_position(&self, song_id: &SongId) -> Option<f64> {
+        if !self.config.resume.enabled {
+            return None;
+        }
+        self.resume
+            .get(song_id)
+            .ok()
+            .flatten()
+            .map(|saved| saved.position)
+    }
+
+    /// Saves how far the current song has played if it is long enough to be resumed later,
+    /// and forgets the position once the song is (nearly) finished.
+    pub fn save_resume_position(&self) -> Result<(), BackendError> {
+        const FINISHED_MARGIN: f64 = 10.0; // Seconds before the end that count as finished
+
+        if !self.config.resume.enabled || !self.player.is_loaded() {
+            return Ok(());
+        }
+        let song = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        let Some(song) = song else {
+            return Ok(());
+        };
+        let duration = self.player.duration_seconds();
+        if duration < self.config.resume.min_length_secs as f64 {
+            return Ok(());
+        }
+
+        let position = self.player.position();
+        let result = if duration - position <= FINISHED_MARGIN {
+            self.resume.clear(&song.song_id)
+        } else if position > 0.0 {
+            self.resume.set(&ResumePosition::new(
+                SongEntry::from(song),
+                position,
+                duration,
+            ))
+        } else {
+            Ok(())
+        };
+        result.map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    // Applies the speed saved for a song if speeds are remembered per track
+    fn apply_track_speed(&self, song_id: &SongId) -> Result<(), BackendError> {
+        if !self.config.playback.remember_speed {
+            return Ok(());
+        }
+        self.player
+            .set_speed(self.track_speed(song_id)?)
+            .map_err(BackendError::Mpv)?;
+        Ok(())
+    }
+
+    // Returns the speed a song plays at: its saved one if speeds are remembered per track,
+    // otherwise the current one
+    fn track_speed(&self, song_id: &SongId) -> Result<f64, BackendError> {
+        if !self.config.playback.remember_speed {
+            return Ok(self.player.speed());
+        }
+        let settings = self
+            .track_settings
+            .get(song_id)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        Ok(settings.speed.unwrap_or(1.0))
+    }
+
+    /// Changes the playback speed by `delta`, remembering it for the current song if enabled.
+    pub fn change_speed(&self, delta: f64) -> Result<f64, BackendError> {
+        self.set_speed(self.player.speed() + delta)
+    }
+
+    /// Sets the playback speed, remembering it for the current song if enabled.
+    /// Returns the speed that was applied.
+    pub fn set_speed(&self, speed: f64) -> Result<f64, BackendError> {
+        // Round to avoid drifting values like 1.2000000000000002 from repeated steps
+        let speed = self
+            .player
+            .set_speed((speed * 100.0).round() / 100.0)
+            .map_err(BackendError::Mpv)?;
+        if !self.config.playback.remember_speed {
+            return Ok(speed);
+        }
+        let song = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        if let Some(song) = song {
+            self.track_settings
+                .update(&song.song_id, |settings| {
+                    settings.speed = (speed != 1.0).then_some(speed);
+                })
+                .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        }
+        Ok(speed)
+    }
+
+    /// Steps through setting the A-B loop at the current position: the first call sets A,
+    /// the second sets B and the third clears the loop.
+    pub fn cycle_ab_loop(&self) -> Result<(), BackendError> {
+        let position = self.player.position();
+        let (a, b) = match self.player.ab_loop() {
+            (None, _) => (Some(position), None),
+            // B before A would never loop, so the points are swapped
+            (Some(a), None) if position < a => (Some(position), Some(a)),
+            (Some(a), None) => (Some(a), Some(position)),
+            (Some(_), Some(_)) => (None, None),
+        };
+        self.player.set_ab_loop(a, b).map_err(BackendError::Mpv)
+    }
+
+    /// Clears the A-B loop.
+    pub fn clear_ab_loop(&self) -> Result<(), BackendError> {
+        self.player.clear_ab_loop().map_err(BackendError::Mpv)
+    }
+
+    // Returns the song being played, or an error if there is none
+    fn current_song(&self) -> Result<Song, BackendError> {
+        self.song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone()
+            .ok_or_else(|| BackendError::PlaybackError("No song is playing".to_string()))
+    }
+
+    /// Returns the loops saved for the current song.
+    pub fn saved_loops(&self) -> Result<Vec<AbLoop>, BackendError> {
+        let song = self.current_song()?;
+        self.track_settings
+            .get(&song.song_id)
+            .map(|settings| settings.loops)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Returns the saved loop that is playing, if any.
+    pub fn active_saved_loop(&self) -> Option<AbLoop> {
+        let (Some(a), Some(b)) = self.player.ab_loop() else {
+            return None;
+        };
+        self.saved_loops()
+            .ok()?
+            .into_iter()
+            .find(|saved| saved.a == a && saved.b == b)
+    }
+
+    /// Saves the A-B loop under `name` for the current song, replacing a loop of the same name.
+    pub fn save_ab_loop(&self, name: &str) -> Result<(), BackendError> {
+        let (Some(a), Some(b)) = self.player.ab_loop() else {
+            return Err(BackendError::PlaybackError(
+                "Set both loop points first".to_string(),
+            ));
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(BackendError::PlaybackError(
+                "Loop name is empty".to_string(),
+            ));
+        }
+        let song = self.current_song()?;
+        let ab_loop = AbLoop {
+            name: name.to_string(),
+            a,
+            b,
+        };
+        self.track_settings
+            .update(&song.song_id, |settings| {
+                match settings.loops.iter_mut().find(|saved| saved.name == name) {
+                    Some(saved) => *saved = ab_loop,
+                    None => settings.loops.push(ab_loop),
+                }
+            })
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Switches to the next loop saved for the current song and returns its name,
+    /// or `None` if no loops are saved.
+    pub fn next_saved_loop(&self) -> Result<Option<String>, BackendError> {
+        let loops = self.saved_loops()?;
+        let next = match self.active_saved_loop() {
+            Some(active) => loops
+                .iter()
+                .position(|saved| *saved == active)
+                .map_or(0, |i| (i + 1) % loops.len()),
+            None => 0,
+        };
+        let Some(saved) = loops.get(next) else {
+            return Ok(None);
+        };
+        self.player
+            .set_ab_loop(Some(saved.a), Some(saved.b))
+            .map_err(BackendError::Mpv)?;
+        self.player
+            .seek(Seek::Absolute(saved.a))
+            .map_err(BackendError::Mpv)?;
+        Ok(Some(saved.name.clone()))
+    }
+
+    /// Forgets the saved loop that is playing and clears the A-B loop.
+    pub fn delete_saved_loop(&self) -> Result<(), BackendError> {
+        let Some(active) = self.active_saved_loop() else {
+            return Err(BackendError::PlaybackError(
+                "No saved loop is playing".to_string(),
+            ));
+        };
+        let song = self.current_song()?;
+        self.track_settings
+            .update(&song.song_id, |settings| {
+                settings.loops.retain(|saved| *saved != active)
+            })
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        self.clear_ab_loop()
+    }
+
+    /// Switches to the next filter preset from the config and returns its name.
+    pub fn cycle_filter_preset(&self) -> Result<String, BackendError> {
+        let presets = &self.config.filters.presets;
+        if presets.is_empty() {
+            return Err(BackendError::PlaybackError(
+                "No filter presets configured".to_string(),
+            ));
+        }
+        let mut current = self
+            .filters
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?;
+        let next = FilterState {
+            preset: (current.preset + 1) % presets.len(),
+            ..*current
+        };
+        self.apply_filters(next)?;
+        *current = next;
+        Ok(presets[next.preset].name.clone())
+    }
+
+    /// Returns the name of the active filter preset.
+    pub fn filter_preset_name(&self) -> String {
+        self.filters
+            .lock()
+            .ok()
+            .and_then(|current| self.config.filters.presets.get(current.preset))
+            .map(|preset| preset.name.clone())
+            .unwrap_or_else(|| "Off".to_string())
+    }
+
+    // Brings a stream to the configured loudness: with a gain when YouTube reported its loudness,
+    // or with the loudnorm filter when it did not
+    fn apply_loudness(&self, loudness_db: Option<f32>) -> Result<(), BackendError> {
+        let (gain_db, loudnorm) = self.loudness_gain(loudness_db);
+        self.player.set_gain(gain_db).map_err(BackendError::Mpv)?;
+        self.set_loudnorm(loudnorm)
+    }
+
+    // Returns the gain for a stream and whether it needs the loudnorm fallback
+    fn loudness_gain(&self, loudness_db: Option<f32>) -> (f64, bool) {
+        let loudness = &self.config.loudness;
+        match loudness_db {
+            Some(db) if loudness.enabled => {
+                (Player::loudness_gain(db, loudness.target_lufs), false)
+            }
+            _ => (0.0, loudness.enabled),
+        }
+    }
+
+    // Turns the loudnorm fallback filter on or off
+    fn set_loudnorm(&self, loudnorm: bool) -> Result<(), BackendError> {
+        let mut current = self
+            .filters
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?;
+        if current.loudnorm != loudnorm {
+            let next = FilterState {
+                loudnorm,
+                ..*current
+            };
+            self.apply_filters(next)?;
+            *current = next;
+        }
+        Ok(())
+    }
+
+    // Sets the player's filter chain: the preset followed by the loudnorm fallback if active
+    fn apply_filters(&self, filters: FilterState) -> Result<(), BackendError> {
+        let mut chain: Vec<String> = self
+            .config
+            .filters
+            .presets
+            .get(filters.preset)
+            .map(|preset| preset.af())
+            .into_iter()
+            .filter(|af| !af.is_empty())
+            .collect();
+        if filters.loudnorm {
+            chain.push(Player::loudnorm_filter(self.config.loudness.target_lufs));
+        }
+        self.player
+            .set_audio_filters(&chain.join(","))
+            .map_err(BackendError::Mpv)
+    }
+
+    // Switches to the saved audio device, falling back to the default with a notice if it is gone
+    fn restore_audio_device(&self) {
+        let Some(device) = self
+            .preferences
+            .load()
+            .ok()
+            .and_then(|preferences| preferences.audio_device)
+        else {
+            return;
+        };
+        let available = self
+            .player
+            .audio_devices()
+            .is_ok_and(|devices| devices.iter().any(|d| d.name == device));
+        if !available || self.player.set_audio_device(&device).is_err() {
+            self.set_notice(Some(format!(
+                "Audio device \"{}\" is unavailable, using the default device",
+                device
+            )));
+        }
+    }
+
+    /// Lists the audio output devices that can be switched to.
+    pub fn audio_devices(&self) -> Result<Vec<AudioDevice>, BackendError> {
+        self.player.audio_devices().map_err(BackendError::Mpv)
+    }
+
+    /// Switches to an audio output device and remembers it for the next start.
+    pub fn set_audio_device(&self, name: &str) -> Result<(), BackendError> {
+        self.player
+            .set_audio_device(name)
+            .map_err(BackendError::Mpv)?;
+        let device = (name != "auto").then(|| name.to_string());
+        self.preferences
+            .update(|preferences| preferences.audio_device = device)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        self.set_notice(None);
+        Ok(())
+    }
+
+    // Locks the repeat and shuffle modes
+    fn lock_modes(&self) -> Result<MutexGuard<'_, PlayModes>, BackendError> {
+        self.modes
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))
+    }
+
+    /// Returns the repeat mode.
+    pub fn repeat(&self) -> Repeat {
+        self.modes.lock().map_or(Repeat::Off, |modes| modes.repeat)
+    }
+
+    /// Returns true if queues play in shuffled order.
+    pub fn is_shuffled(&self) -> bool {
+        self.modes.lock().is_ok_and(|modes| modes.shuffle)
+    }
+
+    /// Switches to the next repeat mode and remembers it for the next start.
+    pub fn cycle_repeat(&self) -> Result<Repeat, BackendError> {
+        let repeat = self.repeat().cycle();
+        self.set_repeat(repeat)?;
+        Ok(repeat)
+    }
+
+    /// Sets the repeat mode and remembers it for the next start.
+    pub fn set_repeat(&self, repeat: Repeat) -> Result<(), BackendError> {
+        let mut modes = self.lock_modes()?;
+        self.player
+            .set_repeat_one(repeat == Repeat::One)
+            .map_err(BackendError::Mpv)?;
+        modes.repeat = repeat;
+        self.preferences
+            .update(|preferences| preferences.repeat = repeat)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Turns shuffle on or off and remembers it for the next start.
+    /// Returns true if shuffle is now on.
+    pub fn toggle_shuffle(&self) -> Result<bool, BackendError> {
+        let shuffle = !self.is_shuffled();
+        self.set_shuffle(shuffle)?;
+        Ok(shuffle)
+    }
+
+    /// Turns shuffle on or off and remembers it for the next start. Turning it off
+    /// puts the queue back in its original order.
+    pub fn set_shuffle(&self, shuffle: bool) -> Result<(), BackendError> {
+        let mut modes = self.lock_modes()?;
+        if modes.shuffle == shuffle {
+            return Ok(());
+        }
+        {
+            let mut queue = self.lock_queue()?;
+            if shuffle {
+                queue.shuffle(&mut rand::rng());
+            } else {
+                queue.unshuffle();
+            }
+        }
+        // The preloaded song may no longer be the next one
+        self.lock_preloaded()?.take();
+        modes.shuffle = shuffle;
+        self.preferences
+            .update(|preferences| preferences.shuffle = shuffle)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Moves on once the current song has ended: to the next song of the queue, or back to
+    /// its start when repeating all. Songs played from outside the queue are repeated on
+    /// their own. Returns true when a new song has started.
+    pub async fn tick_autoplay(&self) -> Result<bool, BackendError> {
+        let repeat = self.repeat();
+        if repeat == Repeat::One || !self.player.is_finished() {
+            return Ok(false);
+        }
+        let current = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        let Some(current) = current else {
+            return Ok(false);
+        };
+        // The song played to its end, so it starts from the beginning next time. The
+        // periodic save may have missed its last seconds, e.g. at a high speed.
+        self.resume
+            .clear(&current.song_id)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+
+        let next = {
+            let mut queue = self.lock_queue()?;
+            let in_queue = queue
+                .current()
+                .is_some_and(|song| song.song_id == current.song_id);
+            match (in_queue, repeat) {
+                (true, Repeat::All) => match queue.next().cloned() {
+                    Some(song) => Some(Song::from(song)),
+                    None => queue.restart(&mut rand::rng()).cloned().map(Song::from),
+                },
+                (true, _) => queue.next().cloned().map(Song::from),
+                (false, Repeat::All) => Some(current),
+                (false, _) => None,
+            }
+        };
+        match next {
+            Some(song) => {
+                // A song repeating on its own is in history already
+                let record = song.song_id != current.song_id;
+                self.start_music(song, record).await.map(|_| true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Signs in to YouTube with the cookies, telling the user if YouTube rejects them.
+    pub async fn sign_in(&self, cookies: &Cookies) {
+        if let Err(e) = self.yt.set_cookies(cookies).await {
+            self.set_notice(Some(e));
+        }
+    }
+
+    /// Returns the message to show to the user, if any.
+    pub fn notice(&self) -> Option<String> {
+        self.notice.lock().ok().and_then(|notice| notice.clone())
+    }
+
+    /// Shows the user a failure of work done in the background, e.g. publishing the status.
+    pub fn report(&self, message: String) {
+        self.set_notice(Some(message));
+    }
+
+    // Replaces the message shown to the user
+    fn set_notice(&self, message: Option<String>) {
+        if let Ok(mut notice) = self.notice.lock() {
+            *notice = message;
+        }
+    }
+
+    /// Returns a receiver of track changes and playback failures from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<PlayerEvent> {
+        self.events.subscribe()
+    }
+
+    // Tells subscribers about a player event; without any it goes nowhere
+    fn emit(&self, event: PlayerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Records that the terminal of a TUI gained or lost focus.
+    pub fn set_tui_focus(&self, focused: bool) {
+        if focused {
+            self.focused_tuis.fetch_add(1, Ordering::Relaxed);
+        } else {
+            let _ = self
+                .focused_tuis
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
+
+    /// Returns whether any TUI has focus, i.e. the user is looking at Feather.
+    pub fn is_tui_focused(&self) -> bool {
+        self.focused_tuis.load(Ordering::Relaxed) > 0
+    }
+
+    /// Fetches the stream of a song, retrying a few times on failure.
+    async fn fetch_url(&self, id: &SongId) -> Result<AudioStreamInfo, BackendError> {
         const MAX_RETRIES: i32 = 8;
-        let id = song.song_id.to_string();
-
-        // Fetch song URL with retry mechanism
-        let url = {
-            let mut attempts = 0;
-            loop {
-                match self.yt.fetch_song_url(&id).await {
-                    Ok(url) => break url,
-                    Err(_) if attempts < MAX_RETRIES => {
-                        attempts += 1;
-                        tokio::time::sleep(Duration::from_millis(100)).await;
-                        continue;
-                    }
-                    Err(e) => {
-                        return Err(BackendError::YoutubeFetch(format!(
-                            "Failed to fetch URL after {} attempts: {:?}",
-                            MAX_RETRIES, e
-                        )));
-                    }
+        let mut attempts = 0;
+        loop {
+            match self.yt.fetch_song_url(id, &self.config.stream).await {
+                Ok(stream) => return Ok(stream),
+                Err(_) if attempts < MAX_RETRIES => {
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                Err(e) => {
+                    return Err(BackendError::YoutubeFetch(format!(
+                        "Failed to fetch URL after {} attempts: {:?}",
+                        MAX_RETRIES, e
+                    )));
                 }
             }
+        }
+    }
+
+    /// Returns the lyrics of a song, from the cache if they were looked up before.
+    /// `None` means YouTube Music has no lyrics for it.
+    pub async fn load_lyrics(&self, song_id: &SongId) -> Result<Option<Lyrics>, BackendError> {
+        let cached = self
+            .lyrics
+            .get(song_id)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        if let Some(cached) = cached {
+            // Lyrics without lines stand for a song that has none
+            return Ok(Some(cached).filter(|lyrics| !lyrics.lines.is_empty()));
+        }
+        let lyrics = self
+            .yt
+            .fetch_lyrics(song_id)
+            .await
+            .map_err(BackendError::YoutubeFetch)?;
+        // Songs without lyrics are cached too, so they show as such without a connection
+        self.lyrics
+            .set(song_id, lyrics.as_ref().unwrap_or(&Lyrics::default()))
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+        Ok(lyrics)
+    }
+
+    /// Returns the details of the stream being played, if any.
+    pub fn stream_info(&self) -> Option<AudioStreamInfo> {
+        self.stream.lock().ok().and_then(|stream| stream.clone())
+    }
+
+    // Replaces the currently playing song and the details of its stream
+    fn set_current_song(
+        &self,
+        song: Option<Song>,
+        stream: Option<AudioStreamInfo>,
+    ) -> Result<(), BackendError> {
+        *self
+            .stream
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))? = stream;
+        let mut current_song = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?;
+        *current_song = song;
+        Ok(())
+    }
+
+    /// Adds a song to favorites, or removes it if it already is one.
+    /// Returns true if the song is now a favorite.
+    pub fn toggle_favorite(&self, song: Song) -> Result<bool, BackendError> {
+        self.favorites
+            .toggle(SongEntry::from(song))
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Toggles the favorite state of the currently playing song.
+    pub fn toggle_current_favorite(&self) -> Result<bool, BackendError> {
+        let song = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        match song {
+            Some(song) => self.toggle_favorite(song),
+            None => Err(BackendError::PlaybackError(
+                "No song is playing".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the marker shown in front of favorite songs in lists.
+    pub fn favorite_marker(&self, song_id: &str) -> &'static str {
+        if self.favorites.is_favorite(song_id) {
+            "♥ "
+        } else {
+            "  "
+        }
+    }
+
+    // Locks the preloaded next song
+    fn lock_preloaded(
+        &self,
+    ) -> Result<MutexGuard<'_, Option<(SongId, AudioStreamInfo)>>, BackendError> {
+        self.preloaded
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))
+    }
+
+    /// Crossfades into the next song of the queue near the end of the current one, going
+    /// back to the first song with `Repeat::All`. The next song is preloaded a little
+    /// earlier so the fade starts without a gap. Returns true when the next song has started.
+    pub async fn tick_crossfade(&self) -> Result<bool, BackendError> {
+        const PRELOAD_LEAD_SECS: f64 = 15.0; // Seconds before the fade at which the next song is loaded
+
+        let secs = self
+            .config
+            .playback
+            .crossfade_secs
+            .clamp(0.0, MAX_CROSSFADE_SECS);
+        let repeat = self.repeat();
+        if secs <= 0.0
+            || repeat == Repeat::One
+            || self.player.is_ab_looping()
+            || !self.player.is_loaded()
+            || !self.player.is_playing().unwrap_or(false)
+        {
+            return Ok(false);
+        }
+        let current = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        let Some(current) = current else {
+            return Ok(false);
         };
+        // A song played from outside the queue is left to autoplay, like one that ends a
+        // shuffled queue: that draws its new order only when it starts over
+        let next = {
+            let queue = self.lock_queue()?;
+            let in_queue = queue
+                .current()
+                .is_some_and(|song| song.song_id == current.song_id);
+            if !in_queue || queue.is_gapless() {
+                return Ok(false);
+            }
+            match queue.peek_next() {
+                Some(song) => Some(song.clone()),
+                None if repeat == Repeat::All && !queue.is_shuffled() => {
+                    queue.songs().first().cloned()
+                }
+                None => None,
+            }
+        };
+        let Some(next) = next else {
+            return Ok(false);
+        };
+        let left = (self.player.duration_seconds() - self.player.position()).max(0.0)
+            / self.player.speed();
+        if left > secs + PRELOAD_LEAD_SECS {
+            return Ok(false);
+        }
 
-        // Update the currently playing song in a mutex-protected section
+        // Load the next song paused on the idle deck
+        let preloaded = self.lock_preloaded()?.clone();
+        let stream = match preloaded {
+            Some((id, stream)) if id == next.song_id => stream,
+            _ => {
+                let stream = self.fetch_url(&next.song_id).await?;
+                self.player
+                    .preload(&stream.url)
+                    .map_err(BackendError::Mpv)?;
+                *self.lock_preloaded()? = Some((next.song_id.clone(), stream.clone()));
+                stream
+            }
+        };
+        if left > secs {
+            return Ok(false);
+        }
+
+        // The outgoing song is played out, so it starts from the beginning next time
+        self.resume
+            .clear(&current.song_id)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))?;
+
+        // Hand over to the next song and mix it in
+        self.lock_preloaded()?.take();
         {
-            let mut current_song = self
-                .song
-                .lock()
-                .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?;
-            *current_song = Some(song.clone());
+            let mut queue = self.lock_queue()?;
+            if queue.next().is_none() {
+                queue.restart(&mut rand::rng());
+            }
+        }
+        let song = Song::from(next);
+        self.set_current_song(Some(song.clone()), Some(stream.clone()))?;
+        let speed = self.track_speed(&song.song_id)?;
+        let (gain_db, loudnorm) = self.loudness_gain(stream.loudness_db);
+        self.set_loudnorm(loudnorm)?;
+        self.player
+            .start_crossfade(secs, speed, gain_db)
+            .map_err(BackendError::Mpv)?;
+        self.emit(PlayerEvent::TrackChanged(song.clone()));
+        // A song repeating on its own is in history already
+        if song.song_id != current.song_id {
+            self.history
+                .add_entry(&HistoryEntry::from(song))
+                .map_err(|e| BackendError::HistoryError(e.to_string()))?;
         }
+        Ok(true)
+    }
 
-        // Play the song
-        self.player.play(&url).map_err(BackendError::Mpv)?;
+    // Locks the sleep timer
+    fn lock_sleep(&self) -> Result<MutexGuard<'_, Option<SleepTimer>>, BackendError> {
+        self.sleep
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))
+    }
 
-        // Add the song to history
-        self.history
-            .add_entry(&HistoryEntry::from(song))
-            .map_err(|e| BackendError::HistoryError(e.to_string()))?;
+    /// Starts a sleep timer, or cancels the running one with `None`.
+    pub fn set_sleep_timer(&self, mode: Option<SleepMode>) -> Result<(), BackendError> {
+        let mut sleep = self.lock_sleep()?;
+        // Undo a fade in progress
+        if let Some(volume) = sleep.as_ref().and_then(SleepTimer::fade_from) {
+            self.player.set_volume(volume).map_err(BackendError::Mpv)?;
+        }
+        *sleep = mode.map(SleepTimer::new);
+        Ok(())
+    }
+
+    /// Pushes the running sleep timer back by `by`.
+    pub fn extend_sleep_timer(&self, by: Duration) -> Result<(), BackendError> {
+        let mut sleep = self.lock_sleep()?;
+        let Some(timer) = sleep.as_mut() else {
+            return Err(BackendError::PlaybackError(
+                "No sleep timer is running".to_string(),
+            ));
+        };
+        let remaining = self.sleep_remaining_secs(timer);
+        if let Some(volume) = timer.extend(by, remaining) {
+            self.player.set_volume(volume).map_err(BackendError::Mpv)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the running sleep timer and the seconds left on it, if known.
+    pub fn sleep_timer(&self) -> Option<(SleepMode, Option<f64>)> {
+        let sleep = self.sleep.lock().ok()?;
+        let timer = sleep.as_ref()?;
+        Some((timer.mode(), self.sleep_remaining_secs(timer)))
+    }
+
+    /// Advances the sleep timer: fades the volume out near the end, then pauses
+    /// and sets the volume back for the next time playback starts.
+    pub fn tick_sleep_timer(&self) -> Result<(), BackendError> {
+        let mut sleep = self.lock_sleep()?;
+        let Some(timer) = sleep.as_mut() else {
+            return Ok(());
+        };
+        let playing = self.player.is_loaded() && self.player.is_playing().unwrap_or(false);
+        if !playing && !matches!(timer.mode(), SleepMode::At(_)) {
+            return Ok(());
+        }
+
+        let remaining = self.sleep_remaining_secs(timer);
+        match timer.tick(remaining, self.player.volume()) {
+            SleepAction::Wait => (),
+            SleepAction::Fade(volume) => {
+                if playing {
+                    self.player.set_volume(volume).map_err(BackendError::Mpv)?;
+                }
+            }
+            SleepAction::Stop(volume) => {
+                *sleep = None;
+                if playing {
+                    self.player.pause().map_err(BackendError::Mpv)?;
+                }
+                self.player.set_volume(volume).map_err(BackendError::Mpv)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Seconds until a sleep timer goes off, based on the current track and queue
+    fn sleep_remaining_secs(&self, timer: &SleepTimer) -> Option<f64> {
+        let track_left = self.player.is_loaded().then(|| {
+            (self.player.duration_seconds() - self.player.position()).max(0.0) / self.player.speed()
+        });
+        let last_in_queue = self
+            .queue
+            .lock()
+            .is_ok_and(|queue| queue.peek_next().is_none());
+        timer.remaining(track_left, last_in_queue)
+    }
+
+    /// Counts how long the current song has been heard: announces it once it plays and
+    /// submits it to the scrobbling services when it passes the listen threshold.
+    pub fn tick_scrobble(&self) -> Result<(), BackendError> {
+        let Some(scrobbler) = &self.scrobbler else {
+            return Ok(());
+        };
+        if !self.player.is_loaded() {
+            return Ok(());
+        }
+        let Ok(song) = self.current_song() else {
+            return Ok(());
+        };
+        let position = self.player.position();
+        let duration = self.player.duration_seconds();
+        let playing = self.player.is_playing().unwrap_or(false);
+
+        let mut listen = self
+            .listen
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?;
+        // A different song, or the same one started over, e.g. when repeating it
+        let started = listen.as_ref().is_none_or(|state| {
+            state.listen.song_id != song.song_id
+                || (position < RESTART_SECS && state.position > position + RESTART_SECS)
+        });
+        if started {
+            *listen = Some(ListenState {
+                listen: Listen::new(song.song_id, song.song_name, song.artist_name, None),
+                played_secs: 0.0,
+                position,
+                announced: false,
+                submitted: false,
+            });
+        }
+        let Some(state) = listen.as_mut() else {
+            return Ok(());
+        };
+        state.position = position;
+        if duration > 0.0 {
+            state.listen.duration_secs = Some(duration.round() as u64);
+        }
+        if !playing {
+            return Ok(());
+        }
+        state.played_secs += TICK_INTERVAL.as_secs_f64();
 
+        if !state.announced {
+            state.announced = true;
+            let (scrobbler, listen) = (scrobbler.clone(), state.listen.clone());
+            tokio::spawn(async move {
+                let _ = scrobbler.now_playing(&listen).await;
+            });
+        }
+        if !state.submitted && self.config.scrobble.is_listen(duration, state.played_secs) {
+            state.submitted = true;
+            let (scrobbler, listen) = (scrobbler.clone(), state.listen.clone());
+            tokio::spawn(async move {
+                let _ = scrobbler.submit(listen).await;
+            });
+        }
         Ok(())
     }
+
+    // Locks the play queue
+    fn lock_queue(&self) -> Result<MutexGuard<'_, Queue>, BackendError> {
+        self.queue
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))
+    }
+
+    /// Appends a song to the play queue.
+    pub fn enqueue(&self, song: Song) -> Result<(), BackendError> {
+        self.lock_queue()?.push(SongEntry::from(song));
+        Ok(())
+    }
+
+    /// Replaces the play queue with `songs` and plays the one at `index`, then the rest
+    /// in shuffled order if shuffle is on.
+    /// Gapless queues, e.g. albums that flow into each other, are never crossfaded.
+    pub async fn play_queue(
+        &self,
+        songs: Vec<Song>,
+        index: usize,
+        gapless: bool,
+    ) -> Result<(), BackendError> {
+        let shuffle = self.is_shuffled();
+        let song = {
+            let mut queue = self.lock_queue()?;
+            queue.set(songs.into_iter().map(SongEntry::from).collect(), index);
+            queue.set_gapless(gapless);
+            if shuffle {
+                queue.shuffle(&mut rand::rng());
+            }
+            queue.current().cloned()
+        };
+        match song {
+            Some(song) => self.play_music(Song::from(song)).await,
+            None => Err(BackendError::PlaybackError("Queue is empty".to_string())),
+        }
+    }
+
+    /// Plays the song at `index` in the play queue.
+    pub async fn play_queue_index(&self, index: usize) -> Result<(), BackendError> {
+        let song = self.lock_queue()?.jump(index).cloned();
+        match song {
+            Some(song) => self.play_music(Song::from(song)).await,
+            None => Err(BackendError::PlaybackError(
+                "No song at this position".to_string(),
+            )),
+        }
+    }
+
+    /// Plays the next song in the play queue.
+    pub async fn play_next(&self) -> Result<(), BackendError> {
+        let song = self.lock_queue()?.next().cloned();
+        match song {
+            Some(song) => self.play_music(Song::from(song)).await,
+            None => Err(BackendError::PlaybackError("End of queue".to_string())),
+        }
+    }
+
+    /// Plays the previous song in the play queue.
+    pub async fn play_previous(&self) -> Result<(), BackendError> {
+        let song = self.lock_queue()?.previous().cloned();
+        match song {
+            Some(song) => self.play_music(Song::from(song)).await,
+            None => Err(BackendError::PlaybackError("Start of queue".to_string())),
+        }
+    }
+
+    /// Captures the current playback state so it can be restored on the next start.
+    ///
+    /// # Arguments
+    /// * `pane` - Name of the active pane.
+    pub fn session(&self, pane: String) -> Result<SessionState, BackendError> {
+        let song = self
+            .song
+            .lock()
+            .map_err(|e| BackendError::MutexPoisoned(e.to_string()))?
+            .clone();
+        Ok(SessionState {
+            song: song.map(SongEntry::from),
+            position: self.player.position(),
+            queue: self.lock_queue()?.clone(),
+            volume: self.player.volume(),
+            pane,
+        })
+    }
+
+    /// Restores a saved session: volume and queue right away, then the last song paused
+    /// at its saved position so playback continues exactly where it stopped.
+    pub async fn restore_session(&self, session: SessionState) -> Result<(), BackendError> {
+        self.player
+            .set_volume(session.volume)
+            .map_err(BackendError::Mpv)?;
+        let shuffle = self.is_shuffled();
+        {
+            let mut queue = self.lock_queue()?;
+            *queue = session.queue;
+            // Follow the saved shuffle mode in case the queue was saved without it
+            if shuffle && !queue.is_shuffled() {
+                queue.shuffle(&mut rand::rng());
+            } else if !shuffle {
+                queue.unshuffle();
+            }
+        }
+        match session.song {
+            Some(song) => self.resume_music(Song::from(song), session.position).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Restores the saved session, falling back to the last played song paused at the start.
+    pub async fn restore_last_session(&self) -> Result<(), BackendError> {
+        let session = self
+            .sessions
+            .load()
+            .ok()
+            .flatten()
+            .or_else(|| self.last_played_session());
+        match session {
+            Some(session) => self.restore_session(session).await,
+            None => Ok(()),
+        }
+    }
+
+    // Builds a session from the most recently played song, paused at the start
+    fn last_played_session(&self) -> Option<SessionState> {
+        let song_id = self.history.get_last_played_song().ok()??;
+        let entry = self
+            .history
+            .get_history()
+            .ok()?
+            .into_iter()
+            .find(|entry| entry.song_id == song_id)?;
+        Some(SessionState {
+            song: Some(SongEntry::new(
+                entry.song_name,
+                entry.song_id,
+                entry.artist_name,
+            )),
+            volume: 100.0,
+            ..Default::default()
+        })
+    }
+
+    /// Saves the current playback state so the next start can resume it.
+    ///
+    /// # Arguments
+    /// * `pane` - Name of the active pane, or `None` to keep the saved one.
+    pub fn save_session(&self, pane: Option<String>) -> Result<(), BackendError> {
+        let pane = match pane {
+            Some(pane) => pane,
+            None => self
+                .sessions
+                .load()
+                .ok()
+                .flatten()
+                .map(|session| session.pane)
+                .unwrap_or_default(),
+        };
+        let session = self.session(pane)?;
+        // Keep the previous session until the restored song has loaded
+        if session.song.is_some() && !self.player.is_loaded() {
+            return Ok(());
+        }
+        if session.song.is_none() && session.queue.is_empty() {
+            return Ok(());
+        }
+        self.sessions
+            .save(&session)
+            .map_err(|e| BackendError::DatabaseError(e.to_string()))
+    }
+
+    /// Drives playback for as long as Feather runs, whether or not a TUI is attached:
+    /// crossfades and moves through the queue, runs the sleep timer, scrobbles and saves
+    /// the resume position and session from time to time.
+    pub async fn drive(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(TICK_INTERVAL);
+        let mut ticks: u32 = 0;
+        loop {
+            interval.tick().await;
+            ticks = ticks.wrapping_add(1);
+            if ticks % RESUME_SAVE_TICKS == 0 {
+                let _ = self.save_resume_position();
+            }
+            if ticks % SESSION_SAVE_TICKS == 0 {
+                let _ = self.save_session(None);
+            }
+            let _ = self.tick_sleep_timer();
+            let _ = self.tick_scrobble();
+
+            // Mix into the next queued song near the end of this one,
+            // or move on once it has ended
+            if !matches!(self.tick_crossfade().await, Ok(true)) {
+                let _ = self.tick_autoplay().await;
+            }
+        }
+    }
 }
@@ -0,0 +1,401 @@
+use crate::ipc::{
+    self, EXIT_FAILED, EXIT_NOT_FOUND, EXIT_NOT_RUNNING, EXIT_USAGE, PlaylistInfo, QueueInfo,
+    Request, Response, Track,
+};
+use feather::config::Config;
+use feather::database::{HistoryDB, PlaylistManager, SessionDB};
+use feather::queue::Queue;
+use feather::yt::YoutubeClient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+/// Subcommands handled here rather than by the TUI.
+pub const COMMANDS: &[&str] = &["search", "play", "history", "playlist", "queue", "download"];
+
+/// Usage of the `feather` command, printed for `feather help` and wrong arguments.
+pub const USAGE: &str = "Usage: feather [command]
+
+Without a command Feather starts the TUI, or attaches to the Feather that is running.
+
+Commands:
+  daemon                               Play in the background, for TUIs to attach to
+  ctl <command>                        Control the running Feather, see `feather ctl`
+  search <query> [--kind K] [--json]   Search for a track, album, artist or playlist
+  play <id|url>                        Play a song in the running Feather
+  history [--json]                     Recently played songs, most recent first
+  playlist list [--json]               Local playlists
+  playlist show <name> [--json]        Songs of a local playlist
+  playlist add <name> <id|url>         Add a song, creating the playlist if needed
+  playlist remove <name> <id|url>      Remove a song from a playlist
+  queue [--json]                       The play queue, * marks the current song
+  download <id|url>... [--output DIR]  Save the audio of songs
+
+Lists are printed as tab-separated lines, or as JSON with --json.
+
+Exit codes: 0 success, 1 command failed, 2 wrong arguments, 3 Feather is not running,
+4 nothing found";
+
+/// What `feather search` looks for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+}
+
+impl SearchKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "track" | "tracks" | "song" | "songs" => Some(SearchKind::Track),
+            "album" | "albums" => Some(SearchKind::Album),
+            "artist" | "artists" => Some(SearchKind::Artist),
+            "playlist" | "playlists" => Some(SearchKind::Playlist),
+            _ => None,
+        }
+    }
+}
+
+/// A search result as printed by `feather search`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub kind: SearchKind,     // What was found
+    pub id: String,           // Song, album, channel or playlist ID
+    pub title: String,        // Its name
+    pub artists: Vec<String>, // Artists, or channels for playlists
+}
+
+// A parsed command line
+enum Command {
+    Search {
+        query: String,
+        kind: SearchKind,
+        json: bool,
+    },
+    Download {
+        targets: Vec<String>,
+        output: PathBuf,
+    },
+    // Carried out by the running Feather if there is one, else on the databases directly
+    Request {
+        request: Request,
+        json: bool,
+    },
+}
+
+// Turns the arguments into a command
+fn parse(args: &[String]) -> Option<Command> {
+    let (command, rest) = args.split_first()?;
+    let json = rest.iter().any(|arg| arg == "--json");
+    let mut kind = SearchKind::Track;
+    let mut output = PathBuf::from(".");
+    let mut words = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        if let Some(value) = arg.strip_prefix("--kind=") {
+            kind = SearchKind::parse(value)?;
+        } else if arg == "--kind" {
+            kind = SearchKind::parse(rest.next()?)?;
+        } else if let Some(value) = arg.strip_prefix("--output=") {
+            output = PathBuf::from(value);
+        } else if arg == "--output" || arg == "-o" {
+            output = PathBuf::from(rest.next()?);
+        } else if arg != "--json" {
+            words.push(arg.clone());
+        }
+    }
+
+    let request = |request| Some(Command::Request { request, json });
+    match (command.as_str(), words.as_slice()) {
+        ("search", [_, ..]) => Some(Command::Search {
+            query: words.join(" "),
+            kind,
+            json,
+        }),
+        ("download", [_, ..]) => Some(Command::Download {
+            targets: words,
+            output,
+        }),
+        ("play", [target]) => request(Request::Play {
+            target: target.clone(),
+        }),
+        ("history", []) => request(Request::History),
+        ("queue", []) => request(Request::Queue),
+        ("playlist", [action, args @ ..]) => match (action.as_str(), args) {
+            ("list", []) => request(Request::Playlists),
+            ("show", [name]) => request(Request::Playlist { name: name.clone() }),
+            ("add", [name, target]) => request(Request::PlaylistAdd {
+                name: name.clone(),
+                target: target.clone(),
+            }),
+            ("remove", [name, target]) => request(Request::PlaylistRemove {
+                name: name.clone(),
+                target: target.clone(),
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Runs a command from `COMMANDS` and returns the exit code of the process.
+pub async fn run(args: &[String]) -> i32 {
+    let Some(command) = parse(args) else {
+        eprintln!("{}", USAGE);
+        return EXIT_USAGE;
+    };
+    match command {
+        Command::Search { query, kind, json } => search(&query, kind, json).await,
+        Command::Download { targets, output } => download(&targets, &output).await,
+        Command::Request { request, json } => {
+            let result = if ipc::is_running().await {
+                send(&request).await
+            } else {
+                offline(&request).await
+            };
+            match result {
+                Ok(data) => {
+                    print_data(&request, data, json);
+                    0
+                }
+                Err((code, error)) => {
+                    eprintln!("{}", error);
+                    code
+                }
+            }
+        }
+    }
+}
+
+// Asks the running Feather, which holds the databases
+async fn send(request: &Request) -> Result<Value, (i32, String)> {
+    let lost = |e: std::io::Error| (EXIT_FAILED, format!("Lost connection to Feather: {}", e));
+    let path = ipc::socket_path().map_err(|e| (EXIT_FAILED, e.to_string()))?;
+    let stream = UnixStream::connect(path)
+        .await
+        .map_err(|_| (EXIT_NOT_RUNNING, "Feather is not running".to_string()))?;
+    let (reader, mut writer) = stream.into_split();
+    let mut line = serde_json::to_string(request).unwrap_or_default();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await.map_err(lost)?;
+
+    let mut lines = BufReader::new(reader).lines();
+    let line = lines
+        .next_line()
+        .await
+        .map_err(lost)?
+        .ok_or_else(|| lost(std::io::ErrorKind::UnexpectedEof.into()))?;
+    let response: Response = serde_json::from_str(&line).map_err(|e| lost(e.into()))?;
+    if response.ok {
+        Ok(response.data)
+    } else {
+        Err((EXIT_FAILED, response.error.unwrap_or_default()))
+    }
+}
+
+// Answers a request from the databases while no Feather is running
+async fn offline(request: &Request) -> Result<Value, (i32, String)> {
+    let failed = |e: String| (EXIT_FAILED, e);
+    let data = match request {
+        Request::History => {
+            let history = HistoryDB::new().map_err(|e| failed(e.to_string()))?;
+            serde_json::to_value(ipc::history(&history).map_err(failed)?)
+        }
+        Request::Queue => {
+            // The queue of the last session is what plays when Feather starts again
+            let sessions = SessionDB::new().map_err(|e| failed(e.to_string()))?;
+            let queue = sessions
+                .load()
+                .map_err(|e| failed(e.to_string()))?
+                .map(|session| session.queue)
+                .unwrap_or_else(Queue::new);
+            serde_json::to_value(ipc::queue_info(&queue))
+        }
+        Request::Playlists => {
+            let playlists = PlaylistManager::new().map_err(|e| failed(e.to_string()))?;
+            serde_json::to_value(ipc::playlists(&playlists).map_err(failed)?)
+        }
+        Request::Playlist { name } => {
+            let playlists = PlaylistManager::new().map_err(|e| failed(e.to_string()))?;
+            serde_json::to_value(ipc::playlist_tracks(&playlists, name).map_err(failed)?)
+        }
+        Request::PlaylistAdd { name, target } => {
+            let playlists = PlaylistManager::new().map_err(|e| failed(e.to_string()))?;
+            ipc::playlist_add(&playlists, &YoutubeClient::new(), name, target)
+                .await
+                .map_err(failed)?;
+            Ok(Value::Null)
+        }
+        Request::PlaylistRemove { name, target } => {
+            let playlists = PlaylistManager::new().map_err(|e| failed(e.to_string()))?;
+            ipc::playlist_remove(&playlists, name, target).map_err(failed)?;
+            Ok(Value::Null)
+        }
+        _ => {
+            return Err((
+                EXIT_NOT_RUNNING,
+                "Feather is not running; start it or `feather daemon` first".to_string(),
+            ));
+        }
+    };
+    data.map_err(|e| failed(e.to_string()))
+}
+
+// Prints what a request returned, as JSON or as tab-separated lines
+fn print_data(request: &Request, data: Value, json: bool) {
+    if data.is_null() {
+        return;
+    }
+    if json {
+        println!("{}", data);
+        return;
+    }
+    match request {
+        Request::Queue => {
+            if let Ok(queue) = serde_json::from_value::<QueueInfo>(data) {
+                for (index, track) in queue.tracks.iter().enumerate() {
+                    let marker = if queue.current == Some(index) {
+                        "*"
+                    } else {
+                        ""
+                    };
+                    println!("{}\t{}", marker, track_line(track));
+                }
+            }
+        }
+        Request::Playlists => {
+            if let Ok(playlists) = serde_json::from_value::<Vec<PlaylistInfo>>(data) {
+                for playlist in playlists {
+                    println!("{}\t{}", playlist.name, playlist.songs);
+                }
+            }
+        }
+        _ => {
+            if let Ok(tracks) = serde_json::from_value::<Vec<Track>>(data) {
+                for track in &tracks {
+                    println!("{}", track_line(track));
+                }
+            }
+        }
+    }
+}
+
+// Formats a song as "ID, title, artists" separated by tabs
+fn track_line(track: &Track) -> String {
+    format!(
+        "{}\t{}\t{}",
+        track.id,
+        track.title,
+        track.artists.join(", ")
+    )
+}
+
+// Runs `feather search`, which only needs YouTube
+async fn search(query: &str, kind: SearchKind, json: bool) -> i32 {
+    let yt = YoutubeClient::new();
+    let results = match kind {
+        SearchKind::Track => yt.search(query).await.map(|tracks| {
+            tracks
+                .into_iter()
+                .map(|((title, id), artists)| (id, title, artists))
+                .collect::<Vec<_>>()
+        }),
+        SearchKind::Album => yt.search_albums(query).await.map(|albums| {
+            albums
+                .into_iter()
+                .map(|((title, id), artists)| (id, title, artists))
+                .collect()
+        }),
+        SearchKind::Artist => yt.search_artists(query).await.map(|artists| {
+            artists
+                .into_iter()
+                .map(|(name, id)| (id, name, Vec::new()))
+                .collect()
+        }),
+        SearchKind::Playlist => yt.fetch_playlist(query).await.map(|playlists| {
+            playlists
+                .into_iter()
+                .map(|(name, (id, channels))| (id, name, channels))
+                .collect()
+        }),
+    };
+    let results: Vec<SearchResult> = match results {
+        Ok(results) => results
+            .into_iter()
+            .map(|(id, title, artists)| SearchResult {
+                kind,
+                id,
+                title,
+                artists,
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILED;
+        }
+    };
+    if results.is_empty() {
+        eprintln!("No results for \"{}\"", query);
+        return EXIT_NOT_FOUND;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&results).unwrap_or_default());
+    } else {
+        for result in &results {
+            println!(
+                "{}\t{}\t{}",
+                result.id,
+                result.title,
+                result.artists.join(", ")
+            );
+        }
+    }
+    0
+}
+
+// Runs `feather download`, printing the path of every saved file
+async fn download(targets: &[String], output: &Path) -> i32 {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let yt = YoutubeClient::new();
+    // Songs restricted to signed-in users need the cookies here too
+    match config.youtube.load_cookies() {
+        Ok(Some(cookies)) => {
+            if let Err(e) = yt.set_cookies(&cookies).await {
+                eprintln!("{}", e);
+                return EXIT_FAILED;
+            }
+        }
+        Ok(None) => (),
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILED;
+        }
+    }
+    let mut code = 0;
+    for target in targets {
+        let Some(id) = ipc::parse_target(target) else {
+            eprintln!("Not a song ID or URL: {}", target);
+            code = EXIT_USAGE;
+            continue;
+        };
+        match yt.download(&id, &config.stream, output).await {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => {
+                eprintln!("{}", e);
+                code = EXIT_FAILED;
+            }
+        }
+    }
+    code
+}
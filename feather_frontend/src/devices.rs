@@ -0,0 +1,130 @@
+use crate::backend::Backend;
+use crossterm::event::{KeyCode, KeyEvent};
+use feather::player::AudioDevice;
+use ratatui::prelude::{Buffer, Color, Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Span;
+use ratatui::widgets::{
+    Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarState,
+    StatefulWidget, Widget,
+};
+use std::sync::Arc;
+
+// Defines a struct to manage the audio device picker UI
+pub struct DevicePicker {
+    selected: usize,                       // Index of currently selected item
+    vertical_scroll_state: ScrollbarState, // State for vertical scrollbar
+    devices: Vec<AudioDevice>,             // Available output devices
+    status: Option<String>,                // Result of the last action
+    backend: Arc<Backend>,                 // Backend owning the player
+}
+
+impl DevicePicker {
+    // Constructor initializing the DevicePicker struct
+    pub fn new(backend: Arc<Backend>) -> Self {
+        Self {
+            selected: 0,
+            vertical_scroll_state: ScrollbarState::default(),
+            devices: Vec::new(),
+            status: None,
+            backend,
+        }
+    }
+
+    // Reloads the device list, e.g. after headphones were plugged in
+    pub fn refresh(&mut self) {
+        match self.backend.audio_devices() {
+            Ok(devices) => {
+                self.devices = devices;
+                self.status = None;
+            }
+            Err(e) => {
+                self.devices.clear();
+                self.status = Some(format!("Failed to list devices: {}", e));
+            }
+        }
+        self.selected = self.selected.min(self.devices.len().saturating_sub(1));
+        self.vertical_scroll_state = self
+            .vertical_scroll_state
+            .content_length(self.devices.len());
+    }
+
+    // Handles keyboard input for navigation and actions
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                if !self.devices.is_empty() {
+                    self.selected = (self.selected + 1).min(self.devices.len() - 1);
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Enter => {
+                // Switch to the selected device
+                if let Some(device) = self.devices.get(self.selected) {
+                    self.status = Some(match self.backend.set_audio_device(&device.name) {
+                        Ok(()) => format!("Playing on {}", device.description),
+                        Err(e) => format!("Failed to switch device: {}", e),
+                    });
+                }
+            }
+            _ => (),
+        }
+        self.vertical_scroll_state = self.vertical_scroll_state.position(self.selected);
+    }
+
+    // Renders the device picker UI component
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        let header = self
+            .status
+            .clone()
+            .unwrap_or_else(|| "Audio output | Enter: switch | r: refresh".to_string());
+        Paragraph::new(header)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[0], buf);
+
+        let devices_area = chunks[1];
+        let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"));
+        scrollbar.render(devices_area, buf, &mut self.vertical_scroll_state);
+
+        let current = self.backend.player.audio_device();
+        let items: Vec<ListItem> = self
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(i, device)| {
+                let mut style = if i == self.selected {
+                    Style::default().fg(Color::Yellow).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                if device.name == current {
+                    // Mark the device in use
+                    style = style.add_modifier(Modifier::BOLD);
+                }
+                ListItem::new(Span::styled(device.description.clone(), style))
+            })
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        StatefulWidget::render(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL))
+                .highlight_symbol("▶"),
+            devices_area,
+            buf,
+            &mut list_state,
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::backend::{Backend, Song};
+use crossterm::event::{KeyCode, KeyEvent};
+use feather::database::SongEntry;
+use rand::seq::SliceRandom;
+use ratatui::prelude::{Buffer, Color, Constraint, Layout, Rect};
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::widgets::{
+    Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarState,
+    StatefulWidget, Widget,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+// Defines a struct to manage the favorites UI
+pub struct Favorites {
+    selected: usize,                       // Index of currently selected item
+    vertical_scroll_state: ScrollbarState, // State for vertical scrollbar
+    songs: Vec<SongEntry>,                 // Favorite songs, most recently liked first
+    backend: Arc<Backend>,                 // Backend owning the favorites store
+    tx_player: mpsc::Sender<bool>,         // Channel to communicate with player
+}
+
+impl Favorites {
+    // Constructor initializing the Favorites struct
+    pub fn new(backend: Arc<Backend>, tx_player: mpsc::Sender<bool>) -> Self {
+        Self {
+            selected: 0,
+            vertical_scroll_state: ScrollbarState::default(),
+            songs: Vec::new(),
+            backend,
+            tx_player,
+        }
+    }
+
+    // Handles keyboard input for navigation and actions
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                if !self.songs.is_empty() {
+                    self.selected = (self.selected + 1).min(self.songs.len() - 1);
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Char('f') => {
+                // Remove selected song from favorites
+                if let Some(song) = self.songs.get(self.selected).cloned() {
+                    let _ = self.backend.toggle_favorite(Song::from(song));
+                }
+            }
+            KeyCode::Char('a') => {
+                // Add selected song to the queue
+                if let Some(song) = self.songs.get(self.selected).cloned() {
+                    let _ = self.backend.enqueue(Song::from(song));
+                }
+            }
+            KeyCode::Enter => {
+                // Play all favorites starting at the selected one
+                if self.selected < self.songs.len() {
+                    self.play(self.songs.clone(), self.selected);
+                }
+            }
+            KeyCode::Char('r') => {
+                // Play all favorites in random order
+                let mut songs = self.songs.clone();
+                songs.shuffle(&mut rand::rng());
+                self.play(songs, 0);
+            }
+            _ => (),
+        }
+        self.vertical_scroll_state = self.vertical_scroll_state.position(self.selected);
+    }
+
+    // Replaces the queue with the given songs and starts playing
+    fn play(&self, songs: Vec<SongEntry>, index: usize) {
+        if songs.is_empty() {
+            return;
+        }
+        let songs = songs.into_iter().map(Song::from).collect();
+        let backend = Arc::clone(&self.backend);
+        let tx_player = self.tx_player.clone();
+        tokio::spawn(async move {
+            if backend.play_queue(songs, index, false).await.is_ok() {
+                let _ = tx_player.send(true).await;
+            }
+        });
+    }
+
+    // Renders the favorites UI component
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        Paragraph::new("Favorites | Enter: play | r: shuffle")
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[0], buf);
+
+        let favorites_area = chunks[1];
+        let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"));
+        scrollbar.render(favorites_area, buf, &mut self.vertical_scroll_state);
+
+        match self.backend.favorites.get_all() {
+            Ok(songs) => self.songs = songs,
+            Err(_) => {
+                self.songs.clear();
+                Paragraph::new("Failed to load favorites").render(favorites_area, buf);
+                return;
+            }
+        }
+        self.selected = self.selected.min(self.songs.len().saturating_sub(1));
+        self.vertical_scroll_state = self.vertical_scroll_state.content_length(self.songs.len());
+
+        let items: Vec<ListItem> = self
+            .songs
+            .iter()
+            .enumerate()
+            .map(|(i, song)| {
+                let style = if i == self.selected {
+                    Style::default().fg(Color::Yellow).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                let text = format!("♥ {} - {}", song.song_name, song.artist_name.join(", "));
+                ListItem::new(Span::styled(text, style))
+            })
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        StatefulWidget::render(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL))
+                .highlight_symbol("▶"),
+            favorites_area,
+            buf,
+            &mut list_state,
+        );
+    }
+}
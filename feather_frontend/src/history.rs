@@ -11,6 +11,13 @@ use ratatui::widgets::{
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
+// Defines which list the history pane shows
+#[derive(PartialEq)]
+enum HistoryMode {
+    Recent,            // Recently played songs
+    ContinueListening, // Partly played long tracks
+}
+
 // Defines a struct to manage playback history UI
 pub struct History {
     history: Arc<HistoryDB>,               // Database connection for history
@@ -20,6 +27,7 @@ pub struct History {
     selected_song: Option<Song>,           // Currently selected song details
     backend: Arc<Backend>,                 // Audio backend for playback
     tx_player: mpsc::Sender<bool>,         // Channel to communicate with player
+    mode: HistoryMode,                     // List currently shown
 }
 
 impl History {
@@ -37,6 +45,7 @@ impl History {
             selected_song: None,
             backend,
             tx_player,
+            mode: HistoryMode::Recent,
         }
     }
 
@@ -51,10 +60,38 @@ impl History {
                 // Move selection up
                 self.select_previous();
             }
+            KeyCode::Tab => {
+                // Switch between history and continue listening
+                self.mode = match self.mode {
+                    HistoryMode::Recent => HistoryMode::ContinueListening,
+                    HistoryMode::ContinueListening => HistoryMode::Recent,
+                };
+                self.selected = 0;
+                self.vertical_scroll_state = self.vertical_scroll_state.position(0);
+            }
             KeyCode::Char('d') => {
-                // Delete selected entry
+                // Delete selected entry, or forget its position when continuing
                 if let Some(song) = &self.selected_song {
-                    let _ = self.history.delete_entry(&song.song_id);
+                    match self.mode {
+                        HistoryMode::Recent => {
+                            let _ = self.history.delete_entry(&song.song_id);
+                        }
+                        HistoryMode::ContinueListening => {
+                            let _ = self.backend.resume.clear(&song.song_id);
+                        }
+                    }
+                }
+            }
+            KeyCode::Char('a') => {
+                // Add selected entry to the queue
+                if let Some(song) = self.selected_song.clone() {
+                    let _ = self.backend.enqueue(song);
+                }
+            }
+            KeyCode::Char('f') => {
+                // Toggle selected entry as favorite
+                if let Some(song) = self.selected_song.clone() {
+                    let _ = self.backend.toggle_favorite(song);
                 }
             }
             KeyCode::Enter => {
@@ -88,6 +125,52 @@ impl History {
         self.vertical_scroll_state = self.vertical_scroll_state.position(self.selected);
     }
 
+    // Loads the songs of the current list along with their display text
+    fn load_items(&self) -> Result<Vec<(Song, String)>, String> {
+        match self.mode {
+            HistoryMode::Recent => self
+                .history
+                .get_history()
+                .map(|items| {
+                    items
+                        .into_iter()
+                        .map(|item| {
+                            let text = format!(
+                                "{}{} - {}",
+                                self.backend.favorite_marker(&item.song_id),
+                                item.song_name,
+                                item.artist_name.join(", ")
+                            );
+                            let song = Song::new(item.song_name, item.song_id, item.artist_name);
+                            (song, text)
+                        })
+                        .collect()
+                })
+                .map_err(|e| e.to_string()),
+            HistoryMode::ContinueListening => self
+                .backend
+                .resume
+                .get_all()
+                .map(|items| {
+                    items
+                        .into_iter()
+                        .map(|item| {
+                            let text = format!(
+                                "{}{} - {}  {}/{}",
+                                self.backend.favorite_marker(&item.song.song_id),
+                                item.song.song_name,
+                                item.song.artist_name.join(", "),
+                                format_time(item.position),
+                                format_time(item.duration)
+                            );
+                            (Song::from(item.song), text)
+                        })
+                        .collect()
+                })
+                .map_err(|e| e.to_string()),
+        }
+    }
+
     // Renders the history UI component
     pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
         let chunks = Layout::default()
@@ -96,7 +179,11 @@ impl History {
             .split(area);
 
         // Render title bar
-        Paragraph::new("History")
+        let title = match self.mode {
+            HistoryMode::Recent => "History",
+            HistoryMode::ContinueListening => "Continue Listening",
+        };
+        Paragraph::new(title)
             .style(Style::default().fg(Color::White))
             .block(Block::default().borders(Borders::ALL))
             .render(chunks[0], buf);
@@ -109,22 +196,18 @@ impl History {
         scrollbar.render(history_area, buf, &mut self.vertical_scroll_state);
 
         // Fetch and render history items
-        if let Ok(items) = self.history.get_history() {
+        if let Ok(items) = self.load_items() {
             self.max_len = items.len();
             self.vertical_scroll_state = self.vertical_scroll_state.content_length(self.max_len);
 
             let view_items: Vec<ListItem> = items
                 .into_iter()
                 .enumerate()
-                .map(|(i, item)| {
+                .map(|(i, (song, text))| {
                     // Format each item for display
                     let is_selected = i == self.selected;
                     if is_selected {
-                        self.selected_song = Some(Song::new(
-                            item.song_name.clone(),
-                            item.song_id.clone(),
-                            item.artist_name.clone(),
-                        ));
+                        self.selected_song = Some(song);
                     }
                     let style = if is_selected {
                         // Highlight selected item
@@ -132,7 +215,6 @@ impl History {
                     } else {
                         Style::default()
                     };
-                    let text = format!("{} - {}", item.song_name, item.artist_name.join(", "));
                     ListItem::new(Span::styled(text, style))
                 })
                 .collect();
@@ -156,3 +238,13 @@ impl History {
         }
     }
 }
+
+// Formats seconds as H:MM:SS or MM:SS
+fn format_time(seconds: f64) -> String {
+    let total = seconds as u64;
+    if total >= 3600 {
+        format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+    } else {
+        format!("{:02}:{:02}", total / 60, total % 60)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::backend::Backend;
+use crossterm::event::{KeyCode, KeyEvent};
+use feather::database::PlaylistManager;
+use feather::import::{Importer, TrackMatch, chosen_songs, read_tracks};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Span,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tui_textarea::TextArea;
+
+// Defines possible states for the import interface
+enum ImportState {
+    Path,     // When typing the path of the file to import
+    Matching, // While tracks are being searched
+    Review,   // When choosing matches for low-confidence tracks
+}
+
+// Results sent back from the matching task
+enum ImportMessage {
+    Progress(usize, usize),        // Tracks matched so far and total
+    Done(String, Vec<TrackMatch>), // Playlist name and matches
+}
+
+pub struct ImportView<'a> {
+    textarea: TextArea<'a>,            // Text input widget for the file path
+    state: ImportState,                // Current UI state
+    playlists: Arc<PlaylistManager>,   // Local playlist store
+    backend: Arc<Backend>,             // Backend used to search YouTube Music
+    tx: mpsc::Sender<ImportMessage>,   // Sender for matching results
+    rx: mpsc::Receiver<ImportMessage>, // Receiver for matching results
+    name: String,                      // Name of the playlist being built
+    matches: Vec<TrackMatch>,          // Matches for every imported track
+    review: Vec<usize>,                // Indices of matches that need review
+    selected: usize,                   // Index into `review`
+    status: Option<String>,            // Progress or error message
+}
+
+impl ImportView<'_> {
+    // Constructor initializing the ImportView struct
+    pub fn new(playlists: Arc<PlaylistManager>, backend: Arc<Backend>) -> Self {
+        let (tx, rx) = mpsc::channel(32);
+        Self {
+            textarea: TextArea::default(),
+            state: ImportState::Path,
+            playlists,
+            backend,
+            tx,
+            rx,
+            name: String::new(),
+            matches: Vec::new(),
+            review: Vec::new(),
+            selected: 0,
+            status: None,
+        }
+    }
+
+    // Handles keyboard input, returning a message once the import is finished or cancelled
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) -> Option<String> {
+        match self.state {
+            ImportState::Path => match key.code {
+                KeyCode::Enter => self.start(),
+                _ => {
+                    self.textarea.input(key);
+                }
+            },
+            ImportState::Matching => (),
+            ImportState::Review => match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    if !self.review.is_empty() {
+                        self.selected = (self.selected + 1).min(self.review.len() - 1);
+                    }
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                KeyCode::Char('l') | KeyCode::Right => self.cycle_candidate(true),
+                KeyCode::Char('h') | KeyCode::Left => self.cycle_candidate(false),
+                KeyCode::Char('x') => {
+                    if let Some(track_match) = self.current() {
+                        track_match.chosen = None;
+                    }
+                }
+                KeyCode::Enter => return Some(self.save()),
+                _ => (),
+            },
+        }
+        None
+    }
+
+    // Reads the file and starts matching its tracks in the background
+    fn start(&mut self) {
+        let path = PathBuf::from(self.textarea.lines()[0].trim());
+        let tracks = match read_tracks(&path) {
+            Ok(tracks) => tracks,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            }
+        };
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Imported".to_string());
+
+        self.state = ImportState::Matching;
+        self.status = Some(format!("Matching 0/{} tracks...", tracks.len()));
+        let tx = self.tx.clone();
+        let backend = Arc::clone(&self.backend);
+        tokio::spawn(async move {
+            let importer = Importer::new(&backend.yt);
+            let progress_tx = tx.clone();
+            let matches = importer
+                .match_tracks(tracks, |done, total| {
+                    let _ = progress_tx.try_send(ImportMessage::Progress(done, total));
+                })
+                .await;
+            let _ = tx.send(ImportMessage::Done(name, matches)).await;
+        });
+    }
+
+    // Returns the match currently selected in the review list
+    fn current(&mut self) -> Option<&mut TrackMatch> {
+        let index = *self.review.get(self.selected)?;
+        self.matches.get_mut(index)
+    }
+
+    // Moves the chosen candidate forward or backward, passing through "skip"
+    fn cycle_candidate(&mut self, forward: bool) {
+        if let Some(track_match) = self.current() {
+            let len = track_match.candidates.len();
+            if len == 0 {
+                return;
+            }
+            track_match.chosen = match (track_match.chosen, forward) {
+                (None, true) => Some(0),
+                (None, false) => Some(len - 1),
+                (Some(i), true) if i + 1 < len => Some(i + 1),
+                (Some(i), false) if i > 0 => Some(i - 1),
+                _ => None,
+            };
+        }
+    }
+
+    // Saves the chosen songs as a local playlist
+    fn save(&mut self) -> String {
+        let songs = chosen_songs(&self.matches);
+        let count = songs.len();
+        let message = match self.playlists.save_playlist(&self.name, songs) {
+            Ok(()) => format!("Imported '{}' ({} tracks)", self.name, count),
+            Err(e) => e.to_string(),
+        };
+        *self = Self::new(Arc::clone(&self.playlists), Arc::clone(&self.backend));
+        message
+    }
+
+    // Applies results received from the matching task
+    fn receive(&mut self) {
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                ImportMessage::Progress(done, total) => {
+                    self.status = Some(format!("Matching {}/{} tracks...", done, total));
+                }
+                ImportMessage::Done(name, matches) => {
+                    self.review = matches
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, m)| m.is_low_confidence())
+                        .map(|(i, _)| i)
+                        .collect();
+                    self.status = Some(format!(
+                        "{} of {} tracks need review",
+                        self.review.len(),
+                        matches.len()
+                    ));
+                    self.name = name;
+                    self.matches = matches;
+                    self.selected = 0;
+                    self.state = ImportState::Review;
+                }
+            }
+        }
+    }
+
+    // Renders the import UI
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.receive();
+
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Path input
+                Constraint::Min(0),    // Review list
+                Constraint::Length(3), // Bottom bar
+            ])
+            .split(area);
+
+        self.textarea.set_cursor_line_style(Style::default());
+        self.textarea
+            .set_placeholder_text("Path to a CSV export or 'Artist - Title' text file");
+        self.textarea.set_style(Style::default().fg(Color::White));
+        self.textarea
+            .set_block(Block::default().title("Import").borders(Borders::ALL));
+        self.textarea.render(chunks[0], buf);
+
+        let items: Vec<ListItem> = self
+            .review
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| {
+                let track_match = &self.matches[index];
+                let choice = match track_match.chosen() {
+                    Some(candidate) => format!(
+                        "{} - {} ({:.0}%)",
+                        candidate.song.song_name,
+                        candidate.song.artist_name.join(", "),
+                        candidate.score * 100.0
+                    ),
+                    None => "(skipped)".to_string(),
+                };
+                let text = format!(
+                    "{} - {}  →  {}",
+                    track_match.track.artist, track_match.track.title, choice
+                );
+                let style = if i == self.selected {
+                    Style::default().fg(Color::Yellow).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Span::styled(text, style))
+            })
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        StatefulWidget::render(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .title("Low-confidence matches")
+                        .borders(Borders::ALL),
+                )
+                .highlight_symbol("▶"),
+            chunks[1],
+            buf,
+            &mut list_state,
+        );
+
+        let hint = match self.state {
+            ImportState::Path => "Enter: import | Tab: cancel",
+            ImportState::Matching => "Searching YouTube Music...",
+            ImportState::Review => "h/l: change match | x: skip | Enter: save playlist",
+        };
+        let text = match &self.status {
+            Some(status) => format!("{} | {}", status, hint),
+            None => hint.to_string(),
+        };
+        Paragraph::new(text)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[2], buf);
+    }
+}
@@ -0,0 +1,853 @@
+use crate::app;
+use crate::backend::{Backend, Song};
+use crate::status;
+use crossterm::{
+    cursor::Show,
+    event::{self, DisableFocusChange, EnableFocusChange, Event as TerminalEvent},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use feather::database::{HistoryDB, PlaylistManager, SongEntry};
+use feather::player::Seek;
+use feather::queue::{Queue, Repeat};
+use feather::yt::YoutubeClient;
+use feather::{ArtistName, SongId, SongName};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs::DirBuilder;
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::OwnedReadHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+/// How often subscribers are sent the changes to the player's state.
+const EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Frames an attached client may fall behind by before it is detached.
+const MAX_FRAMES_BEHIND: usize = 64;
+
+/// Exit code of `feather ctl` and the other commands when the command failed.
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code when a command was called with wrong arguments.
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code when a command needs a running Feather and there is none.
+pub const EXIT_NOT_RUNNING: i32 = 3;
+/// Exit code when a command found nothing, e.g. a search without results.
+pub const EXIT_NOT_FOUND: i32 = 4;
+
+const CTL_USAGE: &str = "Usage: feather ctl <command>
+
+Commands:
+  play <id|url>     Play a song
+  enqueue <id|url>  Add a song to the queue
+  pause | resume | toggle
+  next | previous
+  seek <to>         1:23, 50%, +10 or -10
+  volume <level>    70, +5 or -5
+  search <query>    Search for songs
+  status            Show what is playing
+    --follow        Show it again whenever it changes
+    --format <fmt>  Show it as text, e.g. \"{artist} - {title} [{position}/{duration}]\"
+  events            Print events as they happen
+
+Exit codes: 0 success, 1 command failed, 2 wrong arguments, 3 Feather is not running";
+
+/// A command sent to a running Feather, one JSON object per line,
+/// e.g. `{"cmd":"seek","to":"+10"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Play a song, given by its ID or a YouTube (Music) URL.
+    Play {
+        target: String,
+    },
+    /// Add a song to the end of the queue.
+    Enqueue {
+        target: String,
+    },
+    Pause,
+    Resume,
+    Toggle,
+    Next,
+    Previous,
+    /// Seek to a time ("1:23"), a percentage ("50%") or by seconds ("+10", "-10").
+    Seek {
+        to: String,
+    },
+    /// Set the volume ("70") or change it ("+5", "-5").
+    Volume {
+        level: String,
+    },
+    /// Search for songs.
+    Search {
+        query: String,
+    },
+    /// Return the current [`Status`].
+    Status,
+    /// Keep the connection open and send [`Event`]s as they happen.
+    Subscribe,
+    /// Return recently played songs, most recent first.
+    History,
+    /// Return the play queue as a [`QueueInfo`].
+    Queue,
+    /// Return the local playlists as [`PlaylistInfo`]s.
+    Playlists,
+    /// Return the songs of a local playlist.
+    Playlist {
+        name: String,
+    },
+    /// Add a song to a local playlist, creating the playlist if needed.
+    PlaylistAdd {
+        name: String,
+        target: String,
+    },
+    /// Remove a song from a local playlist.
+    PlaylistRemove {
+        name: String,
+        target: String,
+    },
+    /// Open a TUI of the given size for this client. After the response the connection
+    /// carries what the TUI draws to the client, and the client's terminal events, one
+    /// JSON object per line, to the TUI. Closing it detaches.
+    Attach {
+        width: u16,
+        height: u16,
+    },
+}
+
+/// The answer to a [`Request`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response {
+    pub ok: bool, // Whether the command succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>, // Why the command failed
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub data: Value, // What the command returned, if anything
+}
+
+impl Response {
+    fn ok(data: Value) -> Self {
+        Self {
+            ok: true,
+            error: None,
+            data,
+        }
+    }
+
+    fn error(error: String) -> Self {
+        Self {
+            ok: false,
+            error: Some(error),
+            data: Value::Null,
+        }
+    }
+}
+
+/// A change to the player's state, sent to subscribers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Another song was loaded, or none is any more.
+    TrackChanged { track: Option<Track> },
+    /// Playback started, paused or stopped.
+    State { state: PlayState },
+    /// The position in the current song, in seconds.
+    Position { position: f64, duration: f64 },
+}
+
+/// A song as shown to IPC clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Track {
+    pub id: SongId,               // Song ID
+    pub title: SongName,          // Name of the song
+    pub artists: Vec<ArtistName>, // Artists performing the song
+}
+
+/// The play queue as shown to IPC clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueueInfo {
+    pub tracks: Vec<Track>,     // Songs in queue order
+    pub current: Option<usize>, // Index of the current song, if any
+}
+
+/// A local playlist as shown to IPC clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlaylistInfo {
+    pub name: String,           // Name of the playlist
+    pub songs: usize,           // Number of songs
+    pub source: Option<String>, // Remote playlist it was copied from, if any
+    pub gapless: bool,          // Whether it plays without crossfading
+}
+
+/// Whether something is playing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayState {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// Everything about the player that clients may want to show.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Status {
+    pub track: Option<Track>, // Song that is loaded
+    pub state: PlayState,     // Whether it is playing
+    pub position: f64,        // Position in seconds
+    pub duration: f64,        // Length in seconds, 0 if unknown
+    pub volume: f64,          // Volume in percent
+    pub repeat: Repeat,       // Repeat mode
+    pub shuffle: bool,        // Whether the queue plays shuffled
+}
+
+impl From<Song> for Track {
+    fn from(song: Song) -> Self {
+        Self {
+            id: song.song_id,
+            title: song.song_name,
+            artists: song.artist_name,
+        }
+    }
+}
+
+impl From<SongEntry> for Track {
+    fn from(song: SongEntry) -> Self {
+        Self {
+            id: song.song_id,
+            title: song.song_name,
+            artists: song.artist_name,
+        }
+    }
+}
+
+/// Returns the directory for the control socket and status files: `$XDG_RUNTIME_DIR`, or
+/// `feather-<uid>` in the temporary directory if that is not set. As every user shares the
+/// temporary directory, that one is created private to the user, and refused if it belongs
+/// to someone else or others may enter it.
+pub fn runtime_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    // SAFETY: getuid always succeeds and touches no memory
+    let uid = unsafe { libc::getuid() };
+    let dir = env::temp_dir().join(format!("feather-{}", uid));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => (),
+    }
+    // Someone else may have created it first, or put a symlink to a directory of theirs there
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory private to this user; remove it or set XDG_RUNTIME_DIR",
+                dir.display()
+            ),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Returns the path of the control socket: `feather.sock` in the [`runtime_dir`].
+pub fn socket_path() -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join("feather.sock"))
+}
+
+/// Captures the current state of the player.
+pub fn status(backend: &Backend) -> Status {
+    let track = backend
+        .song
+        .lock()
+        .ok()
+        .and_then(|song| song.clone())
+        .map(Track::from);
+    let loaded = backend.player.is_loaded();
+    let state = if !loaded {
+        PlayState::Stopped
+    } else if backend.player.is_playing().unwrap_or(false) {
+        PlayState::Playing
+    } else {
+        PlayState::Paused
+    };
+    Status {
+        track,
+        state,
+        position: if loaded {
+            backend.player.position()
+        } else {
+            0.0
+        },
+        duration: if loaded {
+            backend.player.duration_seconds()
+        } else {
+            0.0
+        },
+        volume: backend.player.volume(),
+        repeat: backend.repeat(),
+        shuffle: backend.is_shuffled(),
+    }
+}
+
+/// Lists the recently played songs, most recent first.
+pub fn history(history: &HistoryDB) -> Result<Vec<Track>, String> {
+    let entries = history.get_history().map_err(|e| e.to_string())?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| Track {
+            id: entry.song_id,
+            title: entry.song_name,
+            artists: entry.artist_name,
+        })
+        .collect())
+}
+
+/// Describes the play queue.
+pub fn queue_info(queue: &Queue) -> QueueInfo {
+    QueueInfo {
+        tracks: queue.songs().iter().cloned().map(Track::from).collect(),
+        current: queue.cursor(),
+    }
+}
+
+/// Lists the local playlists, sorted by name.
+pub fn playlists(playlists: &PlaylistManager) -> Result<Vec<PlaylistInfo>, String> {
+    let playlists = playlists.get_playlists().map_err(|e| e.to_string())?;
+    Ok(playlists
+        .into_iter()
+        .map(|playlist| PlaylistInfo {
+            songs: playlist.songs.len(),
+            name: playlist.playlist_name,
+            source: playlist.source,
+            gapless: playlist.gapless,
+        })
+        .collect())
+}
+
+/// Lists the songs of a local playlist.
+pub fn playlist_tracks(playlists: &PlaylistManager, name: &str) -> Result<Vec<Track>, String> {
+    let playlist = playlists.get_playlist(name).map_err(|e| e.to_string())?;
+    Ok(playlist.songs.into_iter().map(Track::from).collect())
+}
+
+/// Adds a song to a local playlist, creating the playlist if it does not exist yet.
+pub async fn playlist_add(
+    playlists: &PlaylistManager,
+    yt: &YoutubeClient,
+    name: &str,
+    target: &str,
+) -> Result<(), String> {
+    let song = resolve(yt, target).await?;
+    if playlists.get_playlist(name).is_err() {
+        playlists.create_playlist(name).map_err(|e| e.to_string())?;
+    }
+    playlists
+        .add_song_to_playlist(name, SongEntry::from(song))
+        .map_err(|e| e.to_string())
+}
+
+/// Removes a song from a local playlist.
+pub fn playlist_remove(
+    playlists: &PlaylistManager,
+    name: &str,
+    target: &str,
+) -> Result<(), String> {
+    let id = parse_target(target).ok_or_else(|| format!("Not a song ID or URL: {}", target))?;
+    let playlist = playlists.get_playlist(name).map_err(|e| e.to_string())?;
+    if !playlist.songs.iter().any(|song| song.song_id == id) {
+        return Err(format!("'{}' is not in playlist '{}'", id, name));
+    }
+    playlists
+        .remove_song_from_playlist(name, &id)
+        .map_err(|e| e.to_string())
+}
+
+/// Returns whether a Feather is listening on the control socket.
+pub async fn is_running() -> bool {
+    match socket_path() {
+        Ok(path) => UnixStream::connect(path).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Extracts the song ID from a YouTube (Music) URL, or returns the input if it already is one.
+pub fn parse_target(target: &str) -> Option<SongId> {
+    let target = target.trim();
+    let id = if let Some((_, path)) = target.split_once("youtu.be/") {
+        path.split(['?', '&']).next().unwrap_or_default()
+    } else if let Some((_, query)) = target.split_once('?') {
+        query.split('&').find_map(|pair| pair.strip_prefix("v="))?
+    } else {
+        target
+    };
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+// Parses a seek target, which may also be relative: "+10" or "-10"
+fn parse_seek(to: &str) -> Option<Seek> {
+    let to = to.trim();
+    let relative = |seconds: &str| seconds.parse::<f64>().ok().filter(|s| s.is_finite());
+    if let Some(seconds) = to.strip_prefix('+') {
+        return relative(seconds).map(Seek::Relative);
+    }
+    if let Some(seconds) = to.strip_prefix('-') {
+        return relative(seconds).map(|s| Seek::Relative(-s));
+    }
+    Seek::parse(to)
+}
+
+/// Accepts connections on the control socket and carries out their commands.
+/// Fails if another Feather is already listening on it.
+///
+/// # Arguments
+/// * `backend` - Backend the commands act on.
+pub async fn serve(backend: Arc<Backend>) -> io::Result<()> {
+    let path = socket_path()?;
+    if path.exists() {
+        if is_running().await {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("Feather is already listening on {}", path.display()),
+            ));
+        }
+        // Left behind by a Feather that did not exit cleanly
+        std::fs::remove_file(&path)?;
+    }
+    // Nobody else can enter the runtime directory, so the socket cannot be reached before
+    // its own permissions are narrowed as well
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let backend = Arc::clone(&backend);
+        tokio::spawn(async move {
+            let _ = handle_client(stream, backend).await;
+        });
+    }
+}
+
+// Serializes a message as one line of the protocol
+fn to_line<T: Serialize>(message: &T) -> String {
+    let mut line = serde_json::to_string(message).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+// Answers the requests of one client until it disconnects
+async fn handle_client(stream: UnixStream, backend: Arc<Backend>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    // Responses, events and screens share the connection, so all writes go through one task.
+    // Screens wait in a bounded queue of their own, so a client that cannot keep up is
+    // dropped rather than have its frames pile up.
+    let (out, mut out_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let (frames, mut frames_rx) = mpsc::channel::<Vec<u8>>(MAX_FRAMES_BEHIND);
+    tokio::spawn(async move {
+        loop {
+            let bytes = tokio::select! {
+                // The response to attaching goes out before the first screen
+                biased;
+                Some(bytes) = out_rx.recv() => bytes,
+                Some(bytes) = frames_rx.recv() => bytes,
+                else => break,
+            };
+            if writer.write_all(&bytes).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = serde_json::from_str::<Request>(&line);
+        let response = match &request {
+            Ok(request) => match handle(&backend, request.clone()).await {
+                Ok(data) => Response::ok(data),
+                Err(e) => Response::error(e),
+            },
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+        if out.send(to_line(&response).into_bytes()).is_err() {
+            break;
+        }
+        match request {
+            Ok(Request::Subscribe) => {
+                tokio::spawn(send_events(Arc::clone(&backend), out.clone()));
+            }
+            Ok(Request::Attach { width, height }) => {
+                return run_attached(lines, frames, backend, width, height).await;
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+// Hands a client's terminal events to the TUI it attached to, until either goes away
+async fn run_attached(
+    mut lines: Lines<BufReader<OwnedReadHalf>>,
+    frames: mpsc::Sender<Vec<u8>>,
+    backend: Arc<Backend>,
+    width: u16,
+    height: u16,
+) -> io::Result<()> {
+    let (events, events_rx) = mpsc::channel(32);
+    app::attach(backend, width, height, events_rx, frames)?;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                let Ok(event) = serde_json::from_str::<TerminalEvent>(&line) else {
+                    continue;
+                };
+                if events.send(event).await.is_err() {
+                    return Ok(());
+                }
+            }
+            // The user quit the TUI, so close the connection to detach the client
+            _ = events.closed() => return Ok(()),
+        }
+    }
+}
+
+// Carries out a single request
+async fn handle(backend: &Backend, request: Request) -> Result<Value, String> {
+    let player = &backend.player;
+    match request {
+        Request::Play { target } => {
+            let song = resolve(&backend.yt, &target).await?;
+            backend.play_music(song).await.map_err(|e| e.to_string())?;
+        }
+        Request::Enqueue { target } => {
+            let song = resolve(&backend.yt, &target).await?;
+            backend.enqueue(song).map_err(|e| e.to_string())?;
+        }
+        Request::Pause | Request::Resume | Request::Toggle if !player.is_loaded() => {
+            return Err("No song is loaded".to_string());
+        }
+        Request::Pause => player.pause().map_err(|e| e.to_string())?,
+        Request::Resume => player.unpause().map_err(|e| e.to_string())?,
+        Request::Toggle => player.play_pause().map_err(|e| e.to_string())?,
+        Request::Next => {
+            backend.play_next().await.map_err(|e| e.to_string())?;
+        }
+        Request::Previous => {
+            backend.play_previous().await.map_err(|e| e.to_string())?;
+        }
+        Request::Seek { to } => {
+            let seek = parse_seek(&to).ok_or_else(|| format!("Invalid seek target: {}", to))?;
+            player.seek(seek).map_err(|e| e.to_string())?;
+        }
+        Request::Volume { level } => {
+            let level = level.trim();
+            let result = if level.starts_with('+') || level.starts_with('-') {
+                level.parse().map(|delta| player.change_volume(delta))
+            } else {
+                level.parse().map(|volume| player.set_volume(volume))
+            };
+            result
+                .map_err(|_| format!("Invalid volume: {}", level))?
+                .map_err(|e| e.to_string())?;
+        }
+        Request::Search { query } => {
+            let results = backend.yt.search(&query).await?;
+            let tracks: Vec<Track> = results
+                .into_iter()
+                .map(|((title, id), artists)| Track { id, title, artists })
+                .collect();
+            return serde_json::to_value(tracks).map_err(|e| e.to_string());
+        }
+        Request::Status => {
+            return serde_json::to_value(status(backend)).map_err(|e| e.to_string());
+        }
+        Request::History => {
+            let tracks = history(&backend.history)?;
+            return serde_json::to_value(tracks).map_err(|e| e.to_string());
+        }
+        Request::Queue => {
+            let queue = backend.queue.lock().map_err(|e| e.to_string())?;
+            return serde_json::to_value(queue_info(&queue)).map_err(|e| e.to_string());
+        }
+        Request::Playlists => {
+            let playlists = playlists(&backend.playlists)?;
+            return serde_json::to_value(playlists).map_err(|e| e.to_string());
+        }
+        Request::Playlist { name } => {
+            let tracks = playlist_tracks(&backend.playlists, &name)?;
+            return serde_json::to_value(tracks).map_err(|e| e.to_string());
+        }
+        Request::PlaylistAdd { name, target } => {
+            playlist_add(&backend.playlists, &backend.yt, &name, &target).await?;
+        }
+        Request::PlaylistRemove { name, target } => {
+            playlist_remove(&backend.playlists, &name, &target)?;
+        }
+        Request::Subscribe | Request::Attach { .. } => (),
+    }
+    Ok(Value::Null)
+}
+
+/// Looks up the song a request refers to by its ID or URL.
+pub async fn resolve(yt: &YoutubeClient, target: &str) -> Result<Song, String> {
+    let id = parse_target(target).ok_or_else(|| format!("Not a song ID or URL: {}", target))?;
+    let info = yt.fetch_track_info(&id).await?;
+    Ok(Song::new(info.name, id, info.artists))
+}
+
+// Sends a subscriber the changes to the player's state until it disconnects
+async fn send_events(backend: Arc<Backend>, out: mpsc::UnboundedSender<Vec<u8>>) {
+    let mut last: Option<Status> = None;
+    let mut interval = tokio::time::interval(EVENT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = status(&backend);
+        let mut events = Vec::new();
+        if last.as_ref().is_none_or(|last| last.track != now.track) {
+            events.push(Event::TrackChanged {
+                track: now.track.clone(),
+            });
+        }
+        if last.as_ref().is_none_or(|last| last.state != now.state) {
+            events.push(Event::State { state: now.state });
+        }
+        if now.state != PlayState::Stopped
+            && last
+                .as_ref()
+                .is_none_or(|last| last.position != now.position)
+        {
+            events.push(Event::Position {
+                position: now.position,
+                duration: now.duration,
+            });
+        }
+        for event in events {
+            if out.send(to_line(&event).into_bytes()).is_err() {
+                return;
+            }
+        }
+        last = Some(now);
+    }
+}
+
+// How `feather ctl status` shows the status
+struct StatusOutput {
+    follow: bool,           // Keep showing it as it changes
+    format: Option<String>, // Format string, or `None` for JSON
+}
+
+// Reads the options of `feather ctl status`
+fn parse_status_output(args: &[String]) -> Option<StatusOutput> {
+    let mut output = StatusOutput {
+        follow: false,
+        format: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--follow" | "-f" => output.follow = true,
+            "--format" => output.format = Some(args.next()?.clone()),
+            _ => return None,
+        }
+    }
+    Some(output)
+}
+
+// Turns the arguments of `feather ctl` into a request
+fn parse_ctl(args: &[String]) -> Option<Request> {
+    let (command, rest) = args.split_first()?;
+    let argument = || rest.first().cloned();
+    let request = match command.as_str() {
+        "play" => Request::Play {
+            target: argument()?,
+        },
+        "enqueue" | "add" => Request::Enqueue {
+            target: argument()?,
+        },
+        "pause" => Request::Pause,
+        "resume" => Request::Resume,
+        "toggle" => Request::Toggle,
+        "next" => Request::Next,
+        "previous" | "prev" => Request::Previous,
+        "seek" => Request::Seek { to: argument()? },
+        "volume" => Request::Volume { level: argument()? },
+        "search" if !rest.is_empty() => Request::Search {
+            query: rest.join(" "),
+        },
+        "status" => Request::Status,
+        "events" => Request::Subscribe,
+        _ => return None,
+    };
+    Some(request)
+}
+
+/// Runs `feather ctl`: sends one command to the running Feather and prints what it returns.
+/// Returns the exit code of the process.
+pub async fn ctl(args: &[String]) -> i32 {
+    let Some(request) = parse_ctl(args) else {
+        eprintln!("{}", CTL_USAGE);
+        return EXIT_USAGE;
+    };
+    let status_output = if request == Request::Status {
+        let Some(output) = parse_status_output(&args[1..]) else {
+            eprintln!("{}", CTL_USAGE);
+            return EXIT_USAGE;
+        };
+        Some(output)
+    } else {
+        None
+    };
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let Ok(stream) = UnixStream::connect(&path).await else {
+        eprintln!("Feather is not running (no socket at {})", path.display());
+        return EXIT_NOT_RUNNING;
+    };
+    let result = match status_output {
+        Some(output) => show_status(stream, &output).await,
+        None => send(stream, &request).await,
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Lost connection to Feather: {}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+// Sends a request and prints the response, then any events that follow it
+async fn send(stream: UnixStream, request: &Request) -> io::Result<i32> {
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(to_line(request).as_bytes()).await?;
+    let mut lines = BufReader::new(reader).lines();
+
+    let Some(line) = lines.next_line().await? else {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    };
+    let response: Response = serde_json::from_str(&line)?;
+    if !response.ok {
+        eprintln!("{}", response.error.unwrap_or_default());
+        return Ok(EXIT_FAILED);
+    }
+    if !response.data.is_null() {
+        println!("{}", response.data);
+    }
+    if *request == Request::Subscribe {
+        while let Some(line) = lines.next_line().await? {
+            println!("{}", line);
+        }
+    }
+    Ok(0)
+}
+
+// Prints the status, and with `--follow` again whenever it changes until Feather exits
+async fn show_status(stream: UnixStream, output: &StatusOutput) -> io::Result<i32> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut interval = tokio::time::interval(EVENT_INTERVAL);
+    let mut last: Option<String> = None;
+    loop {
+        interval.tick().await;
+        writer
+            .write_all(to_line(&Request::Status).as_bytes())
+            .await?;
+        let Some(line) = lines.next_line().await? else {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        };
+        let response: Response = serde_json::from_str(&line)?;
+        if !response.ok {
+            eprintln!("{}", response.error.unwrap_or_default());
+            return Ok(EXIT_FAILED);
+        }
+        let shown = match &output.format {
+            Some(format) => status::format(format, &serde_json::from_value(response.data)?),
+            None => response.data.to_string(),
+        };
+        if last.as_ref() != Some(&shown) {
+            println!("{}", shown);
+            last = Some(shown);
+        }
+        if !output.follow {
+            return Ok(0);
+        }
+    }
+}
+
+/// Attaches this terminal to the running Feather's TUI until the user quits it.
+/// Playback goes on after detaching.
+pub async fn attach() -> io::Result<()> {
+    let stream = UnixStream::connect(socket_path()?).await?;
+    let (width, height) = crossterm::terminal::size()?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(to_line(&Request::Attach { width, height }).as_bytes())
+        .await?;
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let response: Response = serde_json::from_str(&line)?;
+    if !response.ok {
+        return Err(io::Error::other(response.error.unwrap_or_default()));
+    }
+
+    enable_raw_mode()?;
+    // Focus changes decide whether desktop notifications are shown
+    execute!(io::stdout(), EnterAlternateScreen, EnableFocusChange)?;
+
+    // Reading terminal events blocks, so it gets a thread of its own
+    let (input, mut input_rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        while let Ok(terminal_event) = event::read() {
+            if input.send(terminal_event).is_err() {
+                break;
+            }
+        }
+    });
+    tokio::spawn(async move {
+        while let Some(event) = input_rx.recv().await {
+            if writer.write_all(to_line(&event).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Show what the TUI draws until it closes the connection
+    let result = copy_screen(&mut reader).await;
+    disable_raw_mode()?;
+    execute!(io::stdout(), DisableFocusChange, Show, LeaveAlternateScreen)?;
+    result
+}
+
+// Copies the screen output of an attached TUI to the terminal
+async fn copy_screen(reader: &mut BufReader<OwnedReadHalf>) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        stdout.write_all(&buf[..read])?;
+        stdout.flush()?;
+    }
+}
@@ -1,4 +1,16 @@
+pub mod app;
 pub mod backend;
+pub mod cli;
+pub mod devices;
+pub mod favorites;
 pub mod history;
+pub mod import;
+pub mod ipc;
+pub mod lyrics;
+pub mod mpris;
+pub mod notify;
 pub mod player;
+pub mod playlist;
+pub mod queue;
 pub mod search;
+pub mod status;
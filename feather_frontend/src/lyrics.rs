@@ -0,0 +1,188 @@
+use crate::backend::Backend;
+use crossterm::event::{KeyCode, KeyEvent};
+use feather::SongId;
+use feather::lyrics::Lyrics;
+use ratatui::prelude::{Buffer, Color, Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Widget};
+use std::sync::{Arc, Mutex};
+
+// Lyrics of a song as far as they are known
+enum LyricsState {
+    Loading,        // Being looked up
+    Found(Lyrics),  // Cached or fetched
+    Missing,        // The song has no lyrics
+    Failed(String), // Looking them up failed
+}
+
+// Defines a struct to manage the lyrics UI
+pub struct LyricsView {
+    backend: Arc<Backend>,                             // Backend playing the song
+    lyrics: Arc<Mutex<Option<(SongId, LyricsState)>>>, // Lyrics and the song they belong to
+    scroll: usize,                                     // First line shown
+    follow: bool,                                      // Keep the line being sung in view
+}
+
+impl LyricsView {
+    // Constructor initializing the LyricsView struct
+    pub fn new(backend: Arc<Backend>) -> Self {
+        Self {
+            backend,
+            lyrics: Arc::new(Mutex::new(None)),
+            scroll: 0,
+            follow: true,
+        }
+    }
+
+    // Handles keyboard input for scrolling
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.follow = false;
+                self.scroll += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.follow = false;
+                self.scroll = self.scroll.saturating_sub(1);
+            }
+            KeyCode::Char('c') => {
+                // Go back to following the song
+                self.follow = true;
+            }
+            _ => (),
+        }
+    }
+
+    // Starts looking up the lyrics of a song unless they belong to it already
+    fn load(&mut self, song_id: &SongId) {
+        let Ok(mut lyrics) = self.lyrics.lock() else {
+            return;
+        };
+        if lyrics.as_ref().is_some_and(|(id, _)| id == song_id) {
+            return;
+        }
+        *lyrics = Some((song_id.clone(), LyricsState::Loading));
+        self.scroll = 0;
+        self.follow = true;
+
+        let backend = Arc::clone(&self.backend);
+        let shared = Arc::clone(&self.lyrics);
+        let song_id = song_id.clone();
+        tokio::spawn(async move {
+            let state = match backend.load_lyrics(&song_id).await {
+                Ok(Some(found)) => LyricsState::Found(found),
+                Ok(None) => LyricsState::Missing,
+                Err(e) => LyricsState::Failed(e.to_string()),
+            };
+            if let Ok(mut lyrics) = shared.lock() {
+                // The song may have changed while they were looked up
+                if lyrics.as_ref().is_some_and(|(id, _)| *id == song_id) {
+                    *lyrics = Some((song_id, state));
+                }
+            }
+        });
+    }
+
+    // Renders the lyrics UI component
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        let lyrics_area = chunks[1];
+        let block = Block::default().borders(Borders::ALL);
+
+        let song = self.backend.song.lock().ok().and_then(|song| song.clone());
+        let Some(song) = song else {
+            Paragraph::new("Lyrics")
+                .style(Style::default().fg(Color::White))
+                .block(Block::default().borders(Borders::ALL))
+                .render(chunks[0], buf);
+            Paragraph::new("Nothing is playing")
+                .block(block)
+                .render(lyrics_area, buf);
+            return;
+        };
+        self.load(&song.song_id);
+
+        let Ok(lyrics) = self.lyrics.lock() else {
+            Paragraph::new("Failed to load lyrics").render(lyrics_area, buf);
+            return;
+        };
+        let state = lyrics.as_ref().map(|(_, state)| state);
+        let synced = matches!(state, Some(LyricsState::Found(found)) if found.is_synced());
+        let header = if synced && !self.follow {
+            format!("Lyrics: {} | synced, c: follow", song.song_name)
+        } else if synced {
+            format!("Lyrics: {} | synced", song.song_name)
+        } else {
+            format!("Lyrics: {}", song.song_name)
+        };
+        Paragraph::new(header)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[0], buf);
+
+        let found = match state {
+            Some(LyricsState::Found(found)) => found,
+            Some(LyricsState::Missing) => {
+                Paragraph::new("No lyrics for this song")
+                    .block(block)
+                    .render(lyrics_area, buf);
+                return;
+            }
+            Some(LyricsState::Failed(e)) => {
+                Paragraph::new(format!("Failed to load lyrics: {}", e))
+                    .style(Style::default().fg(Color::Red))
+                    .block(block)
+                    .render(lyrics_area, buf);
+                return;
+            }
+            Some(LyricsState::Loading) | None => {
+                Paragraph::new("Loading lyrics...")
+                    .block(block)
+                    .render(lyrics_area, buf);
+                return;
+            }
+        };
+
+        let current = found.current_line(self.backend.player.position());
+        let mut lines: Vec<Line> = found
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let style = match current {
+                    Some(current) if i == current => Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                    Some(current) if i < current => Style::default().fg(Color::DarkGray),
+                    _ => Style::default(),
+                };
+                Line::from(Span::styled(line.text.clone(), style))
+            })
+            .collect();
+        if !found.source.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(Span::styled(
+                found.source.clone(),
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            )));
+        }
+
+        // Keep the line being sung in the middle of the pane
+        let height = lyrics_area.height.saturating_sub(2) as usize;
+        if let Some(current) = current.filter(|_| self.follow) {
+            self.scroll = current.saturating_sub(height / 2);
+        }
+        self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll as u16, 0))
+            .render(lyrics_area, buf);
+    }
+}
@@ -0,0 +1,500 @@
+use crate::backend::{Backend, BackendError};
+use feather::SongId;
+use feather::player::{MAX_SPEED, MIN_SPEED, Seek};
+use feather::queue::Repeat;
+use feather::yt::TrackInfo;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zbus::object_server::{InterfaceRef, SignalEmitter};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{connection, fdo, interface};
+
+/// Bus name Feather registers under, as MPRIS clients look for `org.mpris.MediaPlayer2.*`.
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.feather";
+
+/// Object path both MPRIS interfaces are served at.
+pub const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// How often the player is checked for changes to announce on the bus.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Seconds the position may drift from where it should be before it counts as a seek.
+const SEEK_THRESHOLD: f64 = 2.0;
+
+/// Serves MPRIS on the session bus, so media keys, `playerctl` and status bars can control
+/// playback. Runs until the connection fails.
+///
+/// # Arguments
+/// * `backend` - Backend whose playback is exposed.
+pub async fn run(backend: Arc<Backend>) -> zbus::Result<()> {
+    serve(connection::Builder::session()?, backend).await
+}
+
+/// Serves MPRIS on the bus `builder` connects to, e.g. a private `dbus-daemon` in tests.
+pub async fn serve(builder: connection::Builder<'_>, backend: Arc<Backend>) -> zbus::Result<()> {
+    let connection = builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, Root)?
+        .serve_at(OBJECT_PATH, MprisPlayer::new(backend))?
+        .build()
+        .await?;
+    let player = connection
+        .object_server()
+        .interface::<_, MprisPlayer>(OBJECT_PATH)
+        .await?;
+    watch(player).await
+}
+
+// MPRIS counts time in microseconds
+fn to_micros(secs: f64) -> i64 {
+    (secs * 1_000_000.0) as i64
+}
+
+fn to_secs(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+// Object path that identifies a song in MPRIS. Song IDs may contain characters that
+// object paths do not allow, so everything but ASCII letters and digits is hex-escaped.
+fn track_path(song_id: &str) -> OwnedObjectPath {
+    let mut path = String::from("/org/feather/track/");
+    for byte in song_id.bytes() {
+        if byte.is_ascii_alphanumeric() {
+            path.push(byte as char);
+        } else {
+            path.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    ObjectPath::try_from(path)
+        .map(OwnedObjectPath::from)
+        .unwrap_or_else(|_| no_track())
+}
+
+// Track ID MPRIS reserves for "nothing is playing"
+fn no_track() -> OwnedObjectPath {
+    OwnedObjectPath::from(ObjectPath::from_static_str_unchecked(
+        "/org/mpris/MediaPlayer2/TrackList/NoTrack",
+    ))
+}
+
+// Converts a value for a metadata map
+fn owned<'a>(value: impl Into<Value<'a>>) -> Option<OwnedValue> {
+    OwnedValue::try_from(value.into()).ok()
+}
+
+fn to_fdo(e: BackendError) -> fdo::Error {
+    fdo::Error::Failed(e.to_string())
+}
+
+/// The `org.mpris.MediaPlayer2` interface. Feather runs in a terminal, so it can neither
+/// be raised nor quit over the bus.
+struct Root;
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {}
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Feather"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The `org.mpris.MediaPlayer2.Player` interface, driven by the backend.
+struct MprisPlayer {
+    backend: Arc<Backend>,                    // Backend whose playback is exposed
+    info: Mutex<Option<(SongId, TrackInfo)>>, // Album and cover of the current song, once fetched
+}
+
+/// The state MPRIS clients are told about when it changes.
+#[derive(PartialEq, Default)]
+struct Snapshot {
+    status: String,        // Playback status
+    song: Option<SongId>,  // Current song
+    length: i64,           // Length of the current song in microseconds
+    loop_status: String,   // Repeat mode
+    shuffle: bool,         // Shuffle mode
+    rate: f64,             // Playback speed
+    volume: f64,           // Volume from 0 to 1
+    can_go_next: bool,     // Whether there is a next song in the queue
+    can_go_previous: bool, // Whether there is a previous song in the queue
+}
+
+impl MprisPlayer {
+    fn new(backend: Arc<Backend>) -> Self {
+        Self {
+            backend,
+            info: Mutex::new(None),
+        }
+    }
+
+    // Returns the ID of the current song, if any
+    fn song_id(&self) -> Option<SongId> {
+        self.backend
+            .song
+            .lock()
+            .ok()?
+            .as_ref()
+            .map(|song| song.song_id.clone())
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            status: self.playback_status().to_string(),
+            song: self.song_id(),
+            length: to_micros(self.backend.player.duration_seconds()),
+            loop_status: self.loop_status().to_string(),
+            shuffle: self.shuffle(),
+            rate: self.rate(),
+            volume: self.volume(),
+            can_go_next: self.can_go_next(),
+            can_go_previous: self.can_go_previous(),
+        }
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl MprisPlayer {
+    async fn next(&self) -> fdo::Result<()> {
+        self.backend.play_next().await.map_err(to_fdo)
+    }
+
+    async fn previous(&self) -> fdo::Result<()> {
+        self.backend.play_previous().await.map_err(to_fdo)
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        if !self.backend.player.is_loaded() {
+            return Ok(());
+        }
+        self.backend
+            .player
+            .pause()
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn play_pause(&self) -> fdo::Result<()> {
+        if !self.backend.player.is_loaded() {
+            return Ok(());
+        }
+        self.backend
+            .player
+            .play_pause()
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    // Feather has no stopped state, so stopping pauses
+    fn stop(&self) -> fdo::Result<()> {
+        self.pause()
+    }
+
+    fn play(&self) -> fdo::Result<()> {
+        if !self.backend.player.is_loaded() {
+            return Ok(());
+        }
+        self.backend
+            .player
+            .unpause()
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn seek(&self, offset: i64) -> fdo::Result<()> {
+        if !self.backend.player.is_loaded() {
+            return Ok(());
+        }
+        self.backend
+            .player
+            .seek(Seek::Relative(to_secs(offset)))
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+        // Requests for another track or outside this one are ignored, as the spec asks
+        let current = self.song_id().map(|id| track_path(&id));
+        let length = to_micros(self.backend.player.duration_seconds());
+        if current.as_deref() != Some(&track_id) || position < 0 || position > length {
+            return Ok(());
+        }
+        self.backend
+            .player
+            .seek(Seek::Absolute(to_secs(position)))
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn open_uri(&self, _uri: &str) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(
+            "Opening URIs is not supported".to_string(),
+        ))
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        if !self.backend.player.is_loaded() {
+            "Stopped"
+        } else if self.backend.player.is_playing().unwrap_or(false) {
+            "Playing"
+        } else {
+            "Paused"
+        }
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> &str {
+        match self.backend.repeat() {
+            Repeat::Off => "None",
+            Repeat::One => "Track",
+            Repeat::All => "Playlist",
+        }
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&self, value: &str) -> fdo::Result<()> {
+        let repeat = match value {
+            "None" => Repeat::Off,
+            "Track" => Repeat::One,
+            "Playlist" => Repeat::All,
+            _ => {
+                return Err(fdo::Error::InvalidArgs(format!(
+                    "Unknown loop status: {}",
+                    value
+                )));
+            }
+        };
+        self.backend.set_repeat(repeat).map_err(to_fdo)
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.backend.player.speed()
+    }
+
+    #[zbus(property)]
+    fn set_rate(&self, value: f64) -> fdo::Result<()> {
+        // A rate of 0 would stop playback, which clients should do with Pause instead
+        if value <= 0.0 {
+            return Ok(());
+        }
+        self.backend.set_speed(value).map(|_| ()).map_err(to_fdo)
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        MIN_SPEED
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        MAX_SPEED
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.backend.is_shuffled()
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&self, value: bool) -> fdo::Result<()> {
+        self.backend.set_shuffle(value).map_err(to_fdo)
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let song = self.backend.song.lock().ok().and_then(|song| song.clone());
+        let Some(song) = song else {
+            if let Some(track_id) = owned(no_track()) {
+                metadata.insert("mpris:trackid".to_string(), track_id);
+            }
+            return metadata;
+        };
+
+        let info = self
+            .info
+            .lock()
+            .ok()
+            .and_then(|info| info.clone())
+            .filter(|(id, _)| *id == song.song_id)
+            .map(|(_, info)| info)
+            .unwrap_or_default();
+        // YouTube serves a thumbnail for every video, which stands in until the cover is known
+        let art_url = info
+            .cover_url
+            .unwrap_or_else(|| format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", song.song_id));
+        let length = to_micros(self.backend.player.duration_seconds());
+
+        let entries = [
+            ("mpris:trackid", owned(track_path(&song.song_id))),
+            ("xesam:title", owned(song.song_name)),
+            ("xesam:artist", owned(song.artist_name)),
+            ("xesam:album", info.album.and_then(owned)),
+            ("mpris:artUrl", owned(art_url)),
+            (
+                "mpris:length",
+                (length > 0).then(|| owned(length)).flatten(),
+            ),
+        ];
+        for (key, value) in entries {
+            if let Some(value) = value {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+        metadata
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.backend.player.volume() / 100.0
+    }
+
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> fdo::Result<()> {
+        self.backend
+            .player
+            .set_volume(value * 100.0)
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    // Clients are expected to poll the position, so changes are not signalled
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        to_micros(self.backend.player.position())
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.backend
+            .queue
+            .lock()
+            .is_ok_and(|queue| queue.peek_next().is_some())
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.backend
+            .queue
+            .lock()
+            .is_ok_and(|queue| queue.cursor().is_some_and(|cursor| cursor > 0))
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        self.backend.player.is_loaded()
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        self.backend.player.is_loaded()
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.backend.player.is_loaded()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+// Announces changes to the player's state, since they can also come from the TUI,
+// and fetches the album and cover of each new song
+async fn watch(player: InterfaceRef<MprisPlayer>) -> zbus::Result<()> {
+    let emitter = player.signal_emitter().clone();
+    let mut last = Snapshot::default();
+    let mut last_position = (0.0, Instant::now());
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let iface = player.get().await;
+        let now = iface.snapshot();
+        let position = iface.backend.player.position();
+        let backend = Arc::clone(&iface.backend);
+
+        if now.status != last.status {
+            iface.playback_status_changed(&emitter).await?;
+            iface.can_play_changed(&emitter).await?;
+            iface.can_pause_changed(&emitter).await?;
+            iface.can_seek_changed(&emitter).await?;
+        }
+        if now.song != last.song || now.length != last.length {
+            iface.metadata_changed(&emitter).await?;
+        }
+        if now.loop_status != last.loop_status {
+            iface.loop_status_changed(&emitter).await?;
+        }
+        if now.shuffle != last.shuffle {
+            iface.shuffle_changed(&emitter).await?;
+        }
+        if now.rate != last.rate {
+            iface.rate_changed(&emitter).await?;
+        }
+        if now.volume != last.volume {
+            iface.volume_changed(&emitter).await?;
+        }
+        if now.can_go_next != last.can_go_next {
+            iface.can_go_next_changed(&emitter).await?;
+        }
+        if now.can_go_previous != last.can_go_previous {
+            iface.can_go_previous_changed(&emitter).await?;
+        }
+
+        // A position far from where playback should have got to means someone seeked
+        let (previous, at) = last_position;
+        let expected = if last.status == "Playing" {
+            previous + at.elapsed().as_secs_f64() * now.rate
+        } else {
+            previous
+        };
+        if now.song == last.song && (position - expected).abs() > SEEK_THRESHOLD {
+            MprisPlayer::seeked(&emitter, to_micros(position)).await?;
+        }
+        last_position = (position, Instant::now());
+        drop(iface);
+
+        let new_song = now.song.clone().filter(|id| last.song.as_ref() != Some(id));
+        last = now;
+        let Some(id) = new_song else {
+            continue;
+        };
+        let Ok(info) = backend.yt.fetch_track_info(&id).await else {
+            continue;
+        };
+        let iface = player.get().await;
+        if let Ok(mut current) = iface.info.lock() {
+            *current = Some((id, info));
+        }
+        iface.metadata_changed(&emitter).await?;
+    }
+}
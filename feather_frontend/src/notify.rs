@@ -0,0 +1,154 @@
+use crate::backend::{Backend, PlayerEvent};
+use feather::config::NotifyWhen;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+use zbus::zvariant::Value;
+use zbus::{Connection, proxy};
+
+/// Name Feather shows up as in notifications.
+const APP_NAME: &str = "Feather";
+
+/// Icon of notifications about a song without a cover.
+const SONG_ICON: &str = "audio-x-generic";
+
+/// Icon of notifications about a failure.
+const ERROR_ICON: &str = "dialog-error";
+
+/// The `org.freedesktop.Notifications` interface of the desktop's notification server.
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Shows a desktop notification whenever a song starts or fails to play, as set in the
+/// `[notifications]` config section. Runs until the connection to the session bus fails.
+///
+/// # Arguments
+/// * `backend` - Backend whose player events are shown.
+pub async fn run(backend: Arc<Backend>) -> zbus::Result<()> {
+    if backend.config.notifications.when == NotifyWhen::Never {
+        return Ok(());
+    }
+    serve(&Connection::session().await?, backend).await
+}
+
+/// Shows notifications through the notification server on `connection`, e.g. one on a
+/// private `dbus-daemon` in tests.
+pub async fn serve(connection: &Connection, backend: Arc<Backend>) -> zbus::Result<()> {
+    let notifications = NotificationsProxy::new(connection).await?;
+    let config = &backend.config.notifications;
+    // Config::load turns bad intervals away, but a config built in code is not checked
+    let min_interval = Duration::try_from_secs_f64(config.min_interval_secs).unwrap_or_default();
+    let mut events = backend.subscribe();
+    let mut last: Option<(u32, Instant)> = None; // ID and time of the last notification
+
+    loop {
+        let mut event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return Ok(()),
+        };
+        // Too soon after the last one: wait, and only show what happened last by then
+        if let Some((_, shown_at)) = last {
+            // An interval too long to ever end means no more notifications
+            let Some(due) = shown_at.checked_add(min_interval) else {
+                continue;
+            };
+            loop {
+                match tokio::time::timeout_at(due, events.recv()).await {
+                    Ok(Ok(newer)) => event = newer,
+                    Ok(Err(RecvError::Lagged(_))) => (),
+                    Ok(Err(RecvError::Closed)) => return Ok(()),
+                    Err(_) => break,
+                }
+            }
+        }
+        if config.when == NotifyWhen::Never
+            || (config.when == NotifyWhen::Unfocused && backend.is_tui_focused())
+        {
+            continue;
+        }
+
+        let (summary, body, icon, cover) = describe(&backend, &event).await;
+        let mut hints = HashMap::new();
+        if let Some(cover) = &cover {
+            hints.insert("image-path", Value::from(cover.as_str()));
+        }
+        // Each notification replaces the previous one instead of piling up
+        let replaces = last.map_or(0, |(id, _)| id);
+        let id = notifications
+            .notify(
+                APP_NAME,
+                replaces,
+                icon,
+                &summary,
+                &escape(&body),
+                &[],
+                hints,
+                config.timeout_ms,
+            )
+            .await
+            .unwrap_or(replaces);
+        last = Some((id, Instant::now()));
+    }
+}
+
+// Returns the summary, body, icon and cover image URI of the notification for an event
+async fn describe(
+    backend: &Backend,
+    event: &PlayerEvent,
+) -> (String, String, &'static str, Option<String>) {
+    match event {
+        PlayerEvent::TrackChanged(song) => {
+            let info = backend.yt.fetch_track_info(&song.song_id).await.ok();
+            let album = info.as_ref().and_then(|info| info.album.clone());
+            // YouTube serves a thumbnail for every video, which stands in for a missing cover
+            let cover_url = info.and_then(|info| info.cover_url).unwrap_or_else(|| {
+                format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", song.song_id)
+            });
+            let cover = backend
+                .yt
+                .cache_cover(&song.song_id, &cover_url)
+                .await
+                .ok()
+                .map(|path| format!("file://{}", path.display()));
+
+            let mut body = song.artist_name.join(", ");
+            if let Some(album) = album {
+                body = format!("{}\n{}", body, album);
+            }
+            (song.song_name.clone(), body, SONG_ICON, cover)
+        }
+        PlayerEvent::PlaybackFailed { song, error } => (
+            format!("Could not play {}", song.song_name),
+            error.clone(),
+            ERROR_ICON,
+            None,
+        ),
+    }
+}
+
+// Escapes the characters notification servers read as markup in the body
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -1,7 +1,11 @@
 use crate::backend::{Backend, Song};
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use feather::SongId;
+use feather::player::Seek;
+use feather::queue::Repeat;
+use feather::sleep::SleepMode;
 use ratatui::prelude::{Alignment, Buffer, Rect};
-use ratatui::style::{Modifier, Style};
+use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph, Widget};
 use std::sync::{Arc, Mutex};
@@ -9,6 +13,17 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::task;
 
+const SPEED_STEP: f64 = 0.1; // Playback speed change per key press
+const SLEEP_EXTEND: Duration = Duration::from_secs(5 * 60); // Sleep timer extension per key press
+
+// Prompts that can be typed into from the player
+#[derive(Clone, Copy, PartialEq)]
+enum Prompt {
+    GoTo,     // Go to a time or percentage
+    Sleep,    // Set the sleep timer
+    SaveLoop, // Name the A-B loop to save it
+}
+
 #[derive(PartialEq, PartialOrd, Debug)]
 enum SongState {
     Idle,              // No song is playing
@@ -29,15 +44,21 @@ pub struct SongPlayer {
     songstate: Arc<Mutex<SongState>>, // Current state of the player (Idle, Playing, etc.)
     song_playing: Arc<Mutex<Option<SongDetails>>>, // Details of the currently playing song
     rx: mpsc::Receiver<bool>,         // Receiver to listen for playback events
+    tx: mpsc::Sender<bool>,           // Sender to signal playback events from the player itself
+    prompt: Option<(Prompt, String)>, // Open prompt and its input, if any
+    show_info: bool,                  // Show stream details instead of the controls
 }
 
 impl SongPlayer {
-    pub fn new(backend: Arc<Backend>, rx: mpsc::Receiver<bool>) -> Self {
+    pub fn new(backend: Arc<Backend>, tx: mpsc::Sender<bool>, rx: mpsc::Receiver<bool>) -> Self {
         let player = Self {
             backend,
             songstate: Arc::new(Mutex::new(SongState::Idle)),
             song_playing: Arc::new(Mutex::new(None)),
             rx,
+            tx,
+            prompt: None,
+            show_info: false,
         };
         player.observe_time(); // Start observing playback time
         player
@@ -47,22 +68,35 @@ impl SongPlayer {
     fn observe_time(&self) {
         let backend = Arc::clone(&self.backend);
         let song_playing = Arc::clone(&self.song_playing);
+        let tx = self.tx.clone();
 
         tokio::task::spawn(async move {
-            loop {
-                // Try to get the current playback position from MPV
-                match backend.player.player.get_property::<f64>("time-pos") {
-                    Ok(time) => {
-                        // Lock the song_playing mutex and update the current playback time
-                        if let Ok(mut song_lock) = song_playing.lock() {
-                            if let Some(song) = song_lock.as_mut() {
-                                song.current_time = format!("{:.0}", time);
-                            }
+            let mut last_song: Option<SongId> = None;
+            // Stop once the player pane is gone
+            while !tx.is_closed() {
+                // Get the current playback position from MPV, if a song is loaded
+                if backend.player.is_loaded() {
+                    let time = backend.player.position();
+                    // Lock the song_playing mutex and update the current playback time
+                    if let Ok(mut song_lock) = song_playing.lock() {
+                        if let Some(song) = song_lock.as_mut() {
+                            song.current_time = format!("{:.0}", time);
                         }
                     }
-                    Err(_) => (), // Ignore errors (e.g., if MPV is not running)
                 }
 
+                // Follow songs started elsewhere: the queue moving on, another
+                // TUI, MPRIS or `feather ctl`
+                let song = backend
+                    .song
+                    .lock()
+                    .ok()
+                    .and_then(|song| song.as_ref().map(|song| song.song_id.clone()));
+                if song.is_some() && song != last_song {
+                    let _ = tx.send(true).await;
+                }
+                last_song = song;
+
                 tokio::time::sleep(Duration::from_millis(500)).await; // Update every 500ms
             }
         });
@@ -72,26 +106,208 @@ impl SongPlayer {
     pub fn handle_keystrokes(&mut self, key: KeyEvent) {
         if let Ok(state) = self.songstate.lock() {
             if *state == SongState::Playing {
+                if let Some((prompt, input)) = self.prompt.as_mut() {
+                    // Typing into an open prompt
+                    match key.code {
+                        KeyCode::Char(c) => input.push(c),
+                        KeyCode::Backspace => {
+                            input.pop();
+                        }
+                        KeyCode::Enter => {
+                            let (prompt, input) = (*prompt, input.clone());
+                            self.prompt = None;
+                            self.submit(prompt, &input);
+                        }
+                        _ => (),
+                    }
+                    return;
+                }
+
+                let seek = &self.backend.config.seek;
+                let shifted = key.modifiers.contains(KeyModifiers::SHIFT);
                 match key.code {
                     KeyCode::Char(' ') | KeyCode::Char(';') => {
                         // Toggle play/pause
                         if let Ok(_) = self.backend.player.play_pause() {};
                     }
+                    KeyCode::Right if shifted => {
+                        // Seek forward by a large step
+                        let step = Seek::Relative(seek.large_step_secs);
+                        self.backend.player.seek(step).ok();
+                    }
+                    KeyCode::Left if shifted => {
+                        // Seek backward by a large step
+                        let step = Seek::Relative(-seek.large_step_secs);
+                        self.backend.player.seek(step).ok();
+                    }
+                    KeyCode::Char('L') => {
+                        let step = Seek::Relative(seek.large_step_secs);
+                        self.backend.player.seek(step).ok();
+                    }
+                    KeyCode::Char('J') => {
+                        let step = Seek::Relative(-seek.large_step_secs);
+                        self.backend.player.seek(step).ok();
+                    }
                     KeyCode::Right | KeyCode::Char('l') => {
                         // Seek forward
-                        self.backend.player.seek_forward().ok();
+                        let step = Seek::Relative(seek.small_step_secs);
+                        self.backend.player.seek(step).ok();
                     }
                     KeyCode::Left | KeyCode::Char('j') => {
                         // Seek backward
-                        self.backend.player.seek_backword().ok();
+                        let step = Seek::Relative(-seek.small_step_secs);
+                        self.backend.player.seek(step).ok();
+                    }
+                    KeyCode::Char(c @ '0'..='9') => {
+                        // Jump to 0-90% of the track
+                        let percent = f64::from(c as u8 - b'0') * 10.0;
+                        self.backend.player.seek(Seek::Percent(percent)).ok();
+                    }
+                    KeyCode::Char(']') => {
+                        self.backend.player.seek(Seek::ChapterNext).ok();
+                    }
+                    KeyCode::Char('[') => {
+                        self.backend.player.seek(Seek::ChapterPrevious).ok();
+                    }
+                    KeyCode::Char('g') => {
+                        // Open the "go to time" prompt
+                        self.prompt = Some((Prompt::GoTo, String::new()));
                     }
+                    KeyCode::Char('+') | KeyCode::Char('=') => {
+                        // Volume up
+                        self.backend.player.change_volume(5.0).ok();
+                    }
+                    KeyCode::Char('-') => {
+                        // Volume down
+                        self.backend.player.change_volume(-5.0).ok();
+                    }
+                    KeyCode::Char('>') => {
+                        // Speed up
+                        self.backend.change_speed(SPEED_STEP).ok();
+                    }
+                    KeyCode::Char('<') => {
+                        // Slow down
+                        self.backend.change_speed(-SPEED_STEP).ok();
+                    }
+                    KeyCode::Backspace => {
+                        // Reset speed to normal
+                        self.backend.set_speed(1.0).ok();
+                    }
+                    KeyCode::Char('t') => {
+                        // Open the sleep timer prompt
+                        self.prompt = Some((Prompt::Sleep, String::new()));
+                    }
+                    KeyCode::Char('T') => {
+                        // Give the sleep timer a few more minutes
+                        self.backend.extend_sleep_timer(SLEEP_EXTEND).ok();
+                    }
+                    KeyCode::Char('x') => {
+                        // Cancel the sleep timer
+                        self.backend.set_sleep_timer(None).ok();
+                    }
+                    KeyCode::Char('i') => {
+                        // Toggle the track info panel
+                        self.show_info = !self.show_info;
+                    }
+                    KeyCode::Char('e') => {
+                        // Switch to the next equalizer/filter preset
+                        self.backend.cycle_filter_preset().ok();
+                    }
+                    KeyCode::Char('r') => {
+                        // Cycle repeat off, all and one
+                        self.backend.cycle_repeat().ok();
+                    }
+                    KeyCode::Char('s') => {
+                        // Toggle shuffle
+                        self.backend.toggle_shuffle().ok();
+                    }
+                    KeyCode::Char('a') => {
+                        // Set loop point A, then B, then clear the loop
+                        self.backend.cycle_ab_loop().ok();
+                    }
+                    KeyCode::Char('A') => {
+                        // Clear the A-B loop
+                        self.backend.clear_ab_loop().ok();
+                    }
+                    KeyCode::Char('w') => {
+                        // Open the prompt to save the A-B loop under a name
+                        if self.backend.player.is_ab_looping() {
+                            self.prompt = Some((Prompt::SaveLoop, String::new()));
+                        }
+                    }
+                    KeyCode::Char('m') => {
+                        // Play the next loop saved for this song
+                        self.backend.next_saved_loop().ok();
+                    }
+                    KeyCode::Char('M') => {
+                        // Delete the saved loop that is playing
+                        self.backend.delete_saved_loop().ok();
+                    }
+                    KeyCode::Char('f') => {
+                        // Toggle current song as favorite
+                        self.backend.toggle_current_favorite().ok();
+                    }
+                    KeyCode::Char('n') => self.skip(true),
+                    KeyCode::Char('b') => self.skip(false),
                     _ => (),
                 };
             }
         }
     }
 
-    // Function to check whether a song is playing
+    // Describes the repeat and shuffle modes for the status line
+    fn modes_label(&self) -> String {
+        let repeat = match self.backend.repeat() {
+            Repeat::Off => "",
+            Repeat::One => "  Repeat one",
+            Repeat::All => "  Repeat all",
+        };
+        let shuffle = if self.backend.is_shuffled() {
+            "  Shuffle"
+        } else {
+            ""
+        };
+        format!("{}{}", repeat, shuffle)
+    }
+
+    // Acts on the input of a prompt once Enter is pressed
+    fn submit(&self, prompt: Prompt, input: &str) {
+        match prompt {
+            Prompt::GoTo => {
+                if let Some(seek) = Seek::parse(input) {
+                    self.backend.player.seek(seek).ok();
+                }
+            }
+            Prompt::Sleep => {
+                if matches!(input.trim(), "0" | "off") {
+                    self.backend.set_sleep_timer(None).ok();
+                } else if let Some(mode) = SleepMode::parse(input) {
+                    self.backend.set_sleep_timer(Some(mode)).ok();
+                }
+            }
+            Prompt::SaveLoop => {
+                self.backend.save_ab_loop(input).ok();
+            }
+        }
+    }
+
+    // Plays the next or previous song in the queue
+    fn skip(&self, forward: bool) {
+        let backend = Arc::clone(&self.backend);
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let result = if forward {
+                backend.play_next().await
+            } else {
+                backend.play_previous().await
+            };
+            if result.is_ok() {
+                let _ = tx.send(true).await;
+            }
+        });
+    }
+
+    // Function to check whether a song is loaded (playing or paused)
     fn check_playing(&mut self) {
         let songstate = Arc::clone(&self.songstate);
         let backend = Arc::clone(&self.backend);
@@ -105,8 +321,8 @@ impl SongPlayer {
             tokio::time::sleep(Duration::from_secs(1)).await;
 
             loop {
-                match backend.player.is_playing() {
-                    Ok(true) => {
+                match backend.player.is_loaded() {
+                    true => {
                         if let Ok(mut state) = songstate.lock() {
                             if let Ok(mut song_lock) = song_playing.lock() {
                                 if let Ok(song) = backend.song.lock() {
@@ -133,14 +349,13 @@ impl SongPlayer {
                         }
                         idle_count = 0; // Reset idle count since the song is playing
                     }
-                    Ok(false) => {
-                        // Song is not playing, set state to Idle
+                    false => {
+                        // Song is not loaded yet, set state to Idle
                         if let Ok(mut state) = songstate.lock() {
                             *state = SongState::Idle;
                         }
                         idle_count += 1;
                     }
-                    Err(_) => idle_count += 1, // Increase idle count if an error occurs
                 }
 
                 // If too many idle checks, assume an error occurred
@@ -156,6 +371,113 @@ impl SongPlayer {
         });
     }
 
+    // Line showing the A-B loop and when the sleep timer goes off, empty if neither is set
+    fn status_line(&self) -> Line<'static> {
+        let ab_loop = self.loop_label();
+        let Some((mode, remaining)) = self.backend.sleep_timer() else {
+            return Line::from(ab_loop);
+        };
+        let text = match remaining {
+            Some(secs) if secs >= 3600.0 => {
+                let secs = secs as i64;
+                format!(
+                    "Sleep in {}:{:02}:{:02}",
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60
+                )
+            }
+            Some(secs) => {
+                let secs = secs as i64;
+                format!("Sleep in {:02}:{:02}", secs / 60, secs % 60)
+            }
+            None => match mode {
+                SleepMode::EndOfQueue => "Sleep at end of queue".to_string(),
+                _ => "Sleep at end of track".to_string(),
+            },
+        };
+        if ab_loop.is_empty() {
+            Line::from(text)
+        } else {
+            Line::from(format!("{}  {}", ab_loop, text))
+        }
+    }
+
+    // Describes the A-B loop and the name it was saved under, empty if no point is set
+    fn loop_label(&self) -> String {
+        let minutes = |t: f64| format!("{}:{:02}", t as i64 / 60, t as i64 % 60);
+        match self.backend.player.ab_loop() {
+            (Some(a), Some(b)) => match self.backend.active_saved_loop() {
+                Some(saved) => format!("Loop {}-{} \"{}\"", minutes(a), minutes(b), saved.name),
+                None => format!("Loop {}-{}", minutes(a), minutes(b)),
+            },
+            (Some(a), None) => format!("Loop {}-?", minutes(a)),
+            _ => String::new(),
+        }
+    }
+
+    // Draws the progress bar, marking the A-B loop points with [ and ] and the
+    // looped section in color
+    fn progress_line(&self, width: u16) -> Line<'static> {
+        let width = width as usize;
+        let duration = self.backend.player.duration_seconds();
+        if width < 2 || duration <= 0.0 {
+            return Line::from("");
+        }
+        let column =
+            |t: f64| ((t / duration).clamp(0.0, 1.0) * (width - 1) as f64).round() as usize;
+        let position = column(self.backend.player.position());
+        let (a, b) = self.backend.player.ab_loop();
+        let (a, b) = (a.map(column), b.map(column));
+        let looped = |i: usize| a.is_some_and(|a| i >= a) && b.is_none_or(|b| i <= b);
+
+        let spans: Vec<Span> = (0..width)
+            .map(|i| {
+                let symbol = if i == position {
+                    "●"
+                } else if Some(i) == a {
+                    "["
+                } else if Some(i) == b {
+                    "]"
+                } else if i < position {
+                    "━"
+                } else {
+                    "─"
+                };
+                let style = if looped(i) {
+                    Style::default().fg(Color::Cyan)
+                } else {
+                    Style::default()
+                };
+                Span::styled(symbol, style)
+            })
+            .collect();
+        Line::from(spans)
+    }
+
+    // Lines of the track info panel: format, bitrate and loudness of the stream
+    fn info_lines(&self, song: &Song) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled(
+            format!("{} ({})", song.song_name, song.song_id),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        match self.backend.stream_info() {
+            Some(stream) => {
+                lines.push(Line::from(format!(
+                    "Format {}  Bitrate {} kbps",
+                    stream.codec,
+                    stream.bitrate / 1000
+                )));
+                lines.push(Line::from(match stream.loudness_db {
+                    Some(db) => format!("Loudness {:+.1} dB", db),
+                    None => "Loudness unknown".to_string(),
+                }));
+            }
+            None => lines.push(Line::from("No stream details")),
+        }
+        lines
+    }
+
     // Render the player UI
     pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
         // Check for playback event signals
@@ -178,6 +500,9 @@ impl SongPlayer {
                         song_playing.as_ref().map_or_else(
                             || vec![Line::from("Loading...")],
                             |song| {
+                                if self.show_info {
+                                    return self.info_lines(&song.song);
+                                }
                                 let current_time = song
                                     .current_time
                                     .parse::<i64>()
@@ -185,10 +510,42 @@ impl SongPlayer {
                                     .unwrap_or_default();
                                 vec![
                                     Line::from(Span::styled(
-                                        song.song.song_name.clone(),
+                                        format!(
+                                            "{}{}",
+                                            self.backend.favorite_marker(&song.song.song_id),
+                                            song.song.song_name
+                                        ),
                                         Style::default().add_modifier(Modifier::BOLD),
                                     )),
-                                    Line::from(format!("{}/{}", current_time, song.total_duration)),
+                                    self.progress_line(inner.width),
+                                    Line::from(format!(
+                                        "{}/{}{}  Vol {:.0}%  Speed {:.2}x  EQ {}{}",
+                                        current_time,
+                                        song.total_duration,
+                                        if self.backend.player.is_playing().unwrap_or(true) {
+                                            ""
+                                        } else {
+                                            " (paused)"
+                                        },
+                                        self.backend.player.volume(),
+                                        self.backend.player.speed(),
+                                        self.backend.filter_preset_name(),
+                                        self.modes_label()
+                                    )),
+                                    match &self.prompt {
+                                        Some((Prompt::GoTo, input)) => Line::from(format!(
+                                            "Go to (1:23, 1:02:03 or 50%): {}_",
+                                            input
+                                        )),
+                                        Some((Prompt::Sleep, input)) => Line::from(format!(
+                                            "Sleep in (minutes, track, queue or off): {}_",
+                                            input
+                                        )),
+                                        Some((Prompt::SaveLoop, input)) => {
+                                            Line::from(format!("Save loop as: {}_", input))
+                                        }
+                                        None => self.status_line(),
+                                    },
                                 ]
                             },
                         )
@@ -0,0 +1,506 @@
+use crate::backend::{Backend, Song};
+use crate::import::ImportView;
+use crossterm::event::{KeyCode, KeyEvent};
+use feather::database::{PlaylistDiff, PlaylistManager, SongEntry, UserPlaylist};
+use feather::{ChannelName, PlaylistId, PlaylistName};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::Span,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tui_textarea::TextArea;
+
+// Defines possible states for the playlist interface
+#[derive(PartialEq)]
+enum PlaylistState {
+    SearchBar,     // When typing a remote playlist query
+    RemoteResults, // When browsing remote playlists
+    Local,         // When browsing local playlists
+    Tracks,        // When browsing the tracks of the opened playlist
+    SyncReview,    // When reviewing remote changes of a local playlist
+    Import,        // When importing a track list from a file
+}
+
+// Playlist currently opened in the tracks view
+enum OpenedPlaylist {
+    Remote(PlaylistName, PlaylistId), // A YouTube Music playlist
+    Local(PlaylistName),              // A playlist from the local store
+}
+
+// Results sent back from async tasks
+enum PlaylistMessage {
+    Remote(Result<Vec<(PlaylistName, PlaylistId, Vec<ChannelName>)>, String>), // Remote search results
+    Tracks(Result<Vec<SongEntry>, String>), // Tracks of the opened remote playlist
+    Sync(Result<(PlaylistDiff, Vec<SongEntry>), String>), // Remote changes of a local playlist
+}
+
+pub struct PlaylistView<'a> {
+    textarea: TextArea<'a>, // Text input widget for remote playlist queries
+    state: PlaylistState,   // Current UI state
+    playlists: Arc<PlaylistManager>, // Local playlist store
+    backend: Arc<Backend>,  // Audio backend for search and playback
+    tx: mpsc::Sender<PlaylistMessage>, // Sender for async results
+    rx: mpsc::Receiver<PlaylistMessage>, // Receiver for async results
+    tx_player: mpsc::Sender<bool>, // Channel to communicate with player
+    remote: Vec<(PlaylistName, PlaylistId, Vec<ChannelName>)>, // Remote search results
+    local: Vec<UserPlaylist>, // Local playlists
+    opened: Option<OpenedPlaylist>, // Playlist shown in the tracks view
+    tracks: Vec<SongEntry>, // Tracks of the opened playlist
+    sync: Option<(PlaylistDiff, Vec<SongEntry>)>, // Pending diff and remote tracks
+    selected: usize,        // Index of selected item
+    status: Option<String>, // Last message shown in the bottom bar
+    import: ImportView<'a>, // Importer for CSV and text track lists
+}
+
+impl PlaylistView<'_> {
+    // Constructor initializing the PlaylistView struct
+    pub fn new(
+        playlists: Arc<PlaylistManager>,
+        backend: Arc<Backend>,
+        tx_player: mpsc::Sender<bool>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(32);
+        let import = ImportView::new(Arc::clone(&playlists), Arc::clone(&backend));
+        Self {
+            textarea: TextArea::default(),
+            state: PlaylistState::Local,
+            playlists,
+            backend,
+            tx,
+            rx,
+            tx_player,
+            remote: Vec::new(),
+            local: Vec::new(),
+            opened: None,
+            tracks: Vec::new(),
+            sync: None,
+            selected: 0,
+            status: None,
+            import,
+        }
+    }
+
+    // Handles keyboard input based on current state
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) {
+        match self.state {
+            PlaylistState::SearchBar => match key.code {
+                KeyCode::Tab => self.set_state(PlaylistState::RemoteResults),
+                KeyCode::Enter => self.search_remote(),
+                _ => {
+                    self.textarea.input(key);
+                }
+            },
+            PlaylistState::RemoteResults => match key.code {
+                KeyCode::Tab => self.set_state(PlaylistState::Local),
+                KeyCode::Char('j') | KeyCode::Down => self.select_next(self.remote.len()),
+                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+                KeyCode::Enter => {
+                    if let Some((name, id, _)) = self.remote.get(self.selected).cloned() {
+                        self.open_remote(name, id);
+                    }
+                }
+                _ => (),
+            },
+            PlaylistState::Local => match key.code {
+                KeyCode::Tab => self.set_state(PlaylistState::SearchBar),
+                KeyCode::Char('j') | KeyCode::Down => self.select_next(self.local.len()),
+                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+                KeyCode::Enter => {
+                    if let Some(playlist) = self.local.get(self.selected) {
+                        self.tracks = playlist.songs.clone();
+                        self.opened = Some(OpenedPlaylist::Local(playlist.playlist_name.clone()));
+                        self.set_state(PlaylistState::Tracks);
+                    }
+                }
+                KeyCode::Char('r') => {
+                    if let Some(playlist) = self.local.get(self.selected) {
+                        self.resync(playlist.playlist_name.clone(), playlist.source.clone());
+                    }
+                }
+                KeyCode::Char('i') => self.set_state(PlaylistState::Import),
+                KeyCode::Char('g') => {
+                    // Toggle crossfading off for albums that flow into each other
+                    if let Some(playlist) = self.local.get(self.selected) {
+                        if let Err(e) = self
+                            .playlists
+                            .set_gapless(&playlist.playlist_name, !playlist.gapless)
+                        {
+                            self.status = Some(e.to_string());
+                        }
+                    }
+                }
+                KeyCode::Char('d') => {
+                    if let Some(playlist) = self.local.get(self.selected) {
+                        if let Err(e) = self.playlists.delete_playlist(&playlist.playlist_name) {
+                            self.status = Some(e.to_string());
+                        }
+                        self.selected = self.selected.saturating_sub(1);
+                    }
+                }
+                _ => (),
+            },
+            PlaylistState::Tracks => match key.code {
+                KeyCode::Backspace => self.close_tracks(),
+                KeyCode::Char('j') | KeyCode::Down => self.select_next(self.tracks.len()),
+                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
+                KeyCode::Char('S') => self.save_as_local(),
+                KeyCode::Char('a') => {
+                    if let Some(song) = self.tracks.get(self.selected).cloned() {
+                        let _ = self.backend.enqueue(Song::from(song));
+                    }
+                }
+                KeyCode::Char('f') => {
+                    if let Some(song) = self.tracks.get(self.selected).cloned() {
+                        let _ = self.backend.toggle_favorite(Song::from(song));
+                    }
+                }
+                KeyCode::Enter => {
+                    // Play the playlist from the selected track onwards
+                    if self.selected < self.tracks.len() {
+                        let songs = self.tracks.iter().cloned().map(Song::from).collect();
+                        let index = self.selected;
+                        let gapless = self.opened_gapless();
+                        let backend = Arc::clone(&self.backend);
+                        let tx_player = self.tx_player.clone();
+                        tokio::spawn(async move {
+                            if backend.play_queue(songs, index, gapless).await.is_ok() {
+                                let _ = tx_player.send(true).await;
+                            }
+                        });
+                    }
+                }
+                _ => (),
+            },
+            PlaylistState::SyncReview => match key.code {
+                KeyCode::Backspace => {
+                    self.sync = None;
+                    self.set_state(PlaylistState::Local);
+                }
+                KeyCode::Char('a') => self.apply_sync(),
+                _ => (),
+            },
+            PlaylistState::Import => match key.code {
+                KeyCode::Tab => self.set_state(PlaylistState::Local),
+                _ => {
+                    if let Some(message) = self.import.handle_keystrokes(key) {
+                        self.status = Some(message);
+                        self.set_state(PlaylistState::Local);
+                    }
+                }
+            },
+        }
+    }
+
+    // Returns whether the opened playlist is a local playlist marked gapless
+    fn opened_gapless(&self) -> bool {
+        match &self.opened {
+            Some(OpenedPlaylist::Local(name)) => self
+                .local
+                .iter()
+                .any(|p| &p.playlist_name == name && p.gapless),
+            _ => false,
+        }
+    }
+
+    // Switches state and resets the selection
+    fn set_state(&mut self, state: PlaylistState) {
+        self.state = state;
+        self.selected = 0;
+    }
+
+    // Moves selection to next item, respecting bounds
+    fn select_next(&mut self, len: usize) {
+        if len > 0 {
+            self.selected = (self.selected + 1).min(len - 1);
+        }
+    }
+
+    // Moves selection to previous item, preventing underflow
+    fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    // Searches YouTube Music for playlists matching the query
+    fn search_remote(&mut self) {
+        let query = self.textarea.lines()[0].trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        let tx = self.tx.clone();
+        let backend = Arc::clone(&self.backend);
+        tokio::spawn(async move {
+            let result = backend.yt.fetch_playlist(&query).await.map(|playlists| {
+                playlists
+                    .into_iter()
+                    .map(|(name, (id, channels))| (name, id, channels))
+                    .collect()
+            });
+            let _ = tx.send(PlaylistMessage::Remote(result)).await;
+        });
+        self.status = Some("Searching playlists...".to_string());
+        self.set_state(PlaylistState::RemoteResults);
+    }
+
+    // Opens a remote playlist and loads its tracks in order
+    fn open_remote(&mut self, name: PlaylistName, id: PlaylistId) {
+        self.tracks.clear();
+        self.opened = Some(OpenedPlaylist::Remote(name, id.clone()));
+        self.set_state(PlaylistState::Tracks);
+        self.status = Some("Loading tracks...".to_string());
+
+        let tx = self.tx.clone();
+        let backend = Arc::clone(&self.backend);
+        tokio::spawn(async move {
+            let result = fetch_remote_tracks(&backend, id).await;
+            let _ = tx.send(PlaylistMessage::Tracks(result)).await;
+        });
+    }
+
+    // Leaves the tracks view
+    fn close_tracks(&mut self) {
+        let state = match self.opened.take() {
+            Some(OpenedPlaylist::Remote(..)) => PlaylistState::RemoteResults,
+            _ => PlaylistState::Local,
+        };
+        self.tracks.clear();
+        self.set_state(state);
+    }
+
+    // Copies the opened remote playlist into the local store
+    fn save_as_local(&mut self) {
+        if let Some(OpenedPlaylist::Remote(name, id)) = &self.opened {
+            if self.tracks.is_empty() {
+                return;
+            }
+            self.status = Some(
+                match self
+                    .playlists
+                    .save_remote_playlist(name, id.clone(), self.tracks.clone())
+                {
+                    Ok(()) => format!("Saved '{}' ({} tracks)", name, self.tracks.len()),
+                    Err(e) => e.to_string(),
+                },
+            );
+        }
+    }
+
+    // Fetches the source of a local playlist and computes what changed since the last sync
+    fn resync(&mut self, name: PlaylistName, source: Option<PlaylistId>) {
+        let Some(id) = source else {
+            self.status = Some(format!("'{}' has no remote source", name));
+            return;
+        };
+        self.opened = Some(OpenedPlaylist::Local(name.clone()));
+        self.status = Some("Checking remote playlist...".to_string());
+
+        let tx = self.tx.clone();
+        let backend = Arc::clone(&self.backend);
+        let playlists = Arc::clone(&self.playlists);
+        tokio::spawn(async move {
+            let result = match fetch_remote_tracks(&backend, id).await {
+                Ok(remote) => playlists
+                    .sync_diff(&name, &remote)
+                    .map(|diff| (diff, remote))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            let _ = tx.send(PlaylistMessage::Sync(result)).await;
+        });
+    }
+
+    // Applies the reviewed remote changes to the local playlist
+    fn apply_sync(&mut self) {
+        if let (Some((_, remote)), Some(OpenedPlaylist::Local(name))) =
+            (self.sync.take(), self.opened.take())
+        {
+            self.status = Some(match self.playlists.apply_sync(&name, remote) {
+                Ok(diff) => format!(
+                    "Synced '{}': {} added, {} removed",
+                    name,
+                    diff.added.len(),
+                    diff.removed.len()
+                ),
+                Err(e) => e.to_string(),
+            });
+        }
+        self.set_state(PlaylistState::Local);
+    }
+
+    // Applies results received from async tasks
+    fn receive(&mut self) {
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                PlaylistMessage::Remote(Ok(remote)) => {
+                    self.remote = remote;
+                    self.status = None;
+                }
+                PlaylistMessage::Tracks(Ok(tracks)) => {
+                    self.tracks = tracks;
+                    self.status = None;
+                }
+                PlaylistMessage::Sync(Ok((diff, remote))) => {
+                    if diff.is_empty() {
+                        self.status = Some("Already up to date".to_string());
+                    } else {
+                        self.sync = Some((diff, remote));
+                        self.status = None;
+                        self.set_state(PlaylistState::SyncReview);
+                    }
+                }
+                PlaylistMessage::Remote(Err(e))
+                | PlaylistMessage::Tracks(Err(e))
+                | PlaylistMessage::Sync(Err(e)) => self.status = Some(e),
+            }
+        }
+    }
+
+    // Renders the playlist UI
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.receive();
+        if self.state == PlaylistState::Import {
+            self.import.render(area, buf);
+            return;
+        }
+        if let Ok(local) = self.playlists.get_playlists() {
+            self.local = local;
+        }
+
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Search bar height
+                Constraint::Min(0),    // List area
+                Constraint::Length(3), // Bottom bar
+            ])
+            .split(area);
+
+        // Render search bar
+        self.textarea.set_cursor_line_style(Style::default());
+        self.textarea
+            .set_placeholder_text("Search YouTube Music playlists");
+        self.textarea.set_style(Style::default().fg(Color::White));
+        self.textarea
+            .set_block(Block::default().title("Playlists").borders(Borders::ALL));
+        self.textarea.render(chunks[0], buf);
+
+        let (title, lines): (String, Vec<String>) =
+            match self.state {
+                PlaylistState::SearchBar | PlaylistState::RemoteResults => (
+                    "Remote Playlists".to_string(),
+                    self.remote
+                        .iter()
+                        .map(|(name, _, channels)| format!("{} - {}", name, channels.join(", ")))
+                        .collect(),
+                ),
+                PlaylistState::Local => (
+                    "Local Playlists".to_string(),
+                    self.local
+                        .iter()
+                        .map(|p| {
+                            let source = if p.source.is_some() { " (synced)" } else { "" };
+                            let gapless = if p.gapless { " (gapless)" } else { "" };
+                            format!(
+                                "{} [{}]{}{}",
+                                p.playlist_name,
+                                p.songs.len(),
+                                source,
+                                gapless
+                            )
+                        })
+                        .collect(),
+                ),
+                PlaylistState::Tracks => (
+                    match &self.opened {
+                        Some(OpenedPlaylist::Remote(name, _)) => format!("{} (remote)", name),
+                        Some(OpenedPlaylist::Local(name)) => name.clone(),
+                        None => String::new(),
+                    },
+                    self.tracks
+                        .iter()
+                        .map(|s| {
+                            format!(
+                                "{}{} - {}",
+                                self.backend.favorite_marker(&s.song_id),
+                                s.song_name,
+                                s.artist_name.join(", ")
+                            )
+                        })
+                        .collect(),
+                ),
+                PlaylistState::SyncReview => (
+                    "Changes since last sync".to_string(),
+                    self.sync.as_ref().map_or_else(Vec::new, |(diff, _)| {
+                        diff.added
+                            .iter()
+                            .map(|s| format!("+ {} - {}", s.song_name, s.artist_name.join(", ")))
+                            .chain(diff.removed.iter().map(|s| {
+                                format!("- {} - {}", s.song_name, s.artist_name.join(", "))
+                            }))
+                            .collect()
+                    }),
+                ),
+            };
+
+        let focused = self.state != PlaylistState::SearchBar;
+        let items: Vec<ListItem> = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let style = if focused && i == self.selected {
+                    Style::default().fg(Color::Yellow).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Span::styled(text, style))
+            })
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        StatefulWidget::render(
+            List::new(items)
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .highlight_symbol("▶"),
+            chunks[1],
+            buf,
+            &mut list_state,
+        );
+
+        // Render bottom bar with status or key hints
+        let hint = match self.state {
+            PlaylistState::SearchBar => "Enter: search | Tab: results",
+            PlaylistState::RemoteResults => "Enter: open | Tab: local playlists",
+            PlaylistState::Local => {
+                "Enter: open | r: re-sync | i: import | g: gapless | d: delete | Tab: search"
+            }
+            PlaylistState::Tracks => "Enter: play | a: queue | S: save as local | Backspace: back",
+            PlaylistState::SyncReview => "a: apply changes | Backspace: cancel",
+        };
+        let hint = self.status.clone().unwrap_or_else(|| hint.to_string());
+        Paragraph::new(hint)
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[2], buf);
+    }
+}
+
+// Fetches the tracks of a remote playlist in order
+async fn fetch_remote_tracks(
+    backend: &Backend,
+    playlist_id: PlaylistId,
+) -> Result<Vec<SongEntry>, String> {
+    backend
+        .yt
+        .fetch_playlist_songs(playlist_id)
+        .await
+        .map(|songs| {
+            songs
+                .into_iter()
+                .map(|((song_name, song_id), artists)| SongEntry::new(song_name, song_id, artists))
+                .collect()
+        })
+}
@@ -0,0 +1,140 @@
+use crate::backend::{Backend, Song};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::{Buffer, Color, Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Span;
+use ratatui::widgets::{
+    Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarState,
+    StatefulWidget, Widget,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+// Defines a struct to manage the play queue UI
+pub struct QueueView {
+    selected: usize,                       // Index of currently selected item
+    vertical_scroll_state: ScrollbarState, // State for vertical scrollbar
+    max_len: usize,                        // Total number of queued songs
+    backend: Arc<Backend>,                 // Backend owning the queue
+    tx_player: mpsc::Sender<bool>,         // Channel to communicate with player
+}
+
+impl QueueView {
+    // Constructor initializing the QueueView struct
+    pub fn new(backend: Arc<Backend>, tx_player: mpsc::Sender<bool>) -> Self {
+        Self {
+            selected: 0,
+            vertical_scroll_state: ScrollbarState::default(),
+            max_len: 0,
+            backend,
+            tx_player,
+        }
+    }
+
+    // Handles keyboard input for navigation and actions
+    pub fn handle_keystrokes(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                if self.max_len > 0 {
+                    self.selected = (self.selected + 1).min(self.max_len - 1);
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            KeyCode::Char('f') => {
+                // Toggle selected song as favorite
+                let song = self
+                    .backend
+                    .queue
+                    .lock()
+                    .ok()
+                    .and_then(|queue| queue.songs().get(self.selected).cloned());
+                if let Some(song) = song {
+                    let _ = self.backend.toggle_favorite(Song::from(song));
+                }
+            }
+            KeyCode::Char('d') => {
+                // Remove selected song from the queue
+                if let Ok(mut queue) = self.backend.queue.lock() {
+                    queue.remove(self.selected);
+                }
+            }
+            KeyCode::Enter => {
+                // Play selected song
+                let backend = Arc::clone(&self.backend);
+                let tx_player = self.tx_player.clone();
+                let index = self.selected;
+                tokio::spawn(async move {
+                    if backend.play_queue_index(index).await.is_ok() {
+                        let _ = tx_player.send(true).await;
+                    }
+                });
+            }
+            _ => (),
+        }
+        self.vertical_scroll_state = self.vertical_scroll_state.position(self.selected);
+    }
+
+    // Renders the queue UI component
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let chunks = Layout::default()
+            .direction(ratatui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        Paragraph::new("Queue")
+            .style(Style::default().fg(Color::White))
+            .block(Block::default().borders(Borders::ALL))
+            .render(chunks[0], buf);
+
+        let queue_area = chunks[1];
+        let scrollbar = Scrollbar::new(ratatui::widgets::ScrollbarOrientation::VerticalRight)
+            .begin_symbol(Some("↑"))
+            .end_symbol(Some("↓"));
+        scrollbar.render(queue_area, buf, &mut self.vertical_scroll_state);
+
+        let Ok(queue) = self.backend.queue.lock() else {
+            Paragraph::new("Failed to load queue").render(queue_area, buf);
+            return;
+        };
+        self.max_len = queue.len();
+        self.selected = self.selected.min(self.max_len.saturating_sub(1));
+        self.vertical_scroll_state = self.vertical_scroll_state.content_length(self.max_len);
+
+        let items: Vec<ListItem> = queue
+            .songs()
+            .iter()
+            .enumerate()
+            .map(|(i, song)| {
+                let mut style = if i == self.selected {
+                    Style::default().fg(Color::Yellow).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                if Some(i) == queue.cursor() {
+                    // Mark the song being played
+                    style = style.add_modifier(Modifier::BOLD);
+                }
+                let text = format!(
+                    "{}{} - {}",
+                    self.backend.favorite_marker(&song.song_id),
+                    song.song_name,
+                    song.artist_name.join(", ")
+                );
+                ListItem::new(Span::styled(text, style))
+            })
+            .collect();
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(self.selected));
+        StatefulWidget::render(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL))
+                .highlight_symbol("▶"),
+            queue_area,
+            buf,
+            &mut list_state,
+        );
+    }
+}
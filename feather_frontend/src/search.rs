@@ -116,6 +116,18 @@ impl Search<'_> {
                     self.selected = self.selected.saturating_sub(1);
                     self.vertical_scroll_state = self.vertical_scroll_state.position(self.selected);
                 }
+                KeyCode::Char('a') => {
+                    // Add selected song to the queue
+                    if let Some(song) = self.selected_song.clone() {
+                        let _ = self.backend.enqueue(song);
+                    }
+                }
+                KeyCode::Char('f') => {
+                    // Toggle selected song as favorite
+                    if let Some(song) = self.selected_song.clone() {
+                        let _ = self.backend.toggle_favorite(song);
+                    }
+                }
                 KeyCode::Enter => {
                     // Play selected song
                     if let Some(song) = self.selected_song.clone() {
@@ -197,7 +209,12 @@ impl Search<'_> {
                             } else {
                                 Style::default()
                             };
-                            let text = format!("{} - {}", song, artists.join(", "));
+                            let text = format!(
+                                "{}{} - {}",
+                                self.backend.favorite_marker(&songid),
+                                song,
+                                artists.join(", ")
+                            );
                             ListItem::new(Span::styled(text, style))
                         })
                         .collect();
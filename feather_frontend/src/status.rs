@@ -0,0 +1,283 @@
+use crate::backend::Backend;
+use crate::ipc::{self, PlayState, Status};
+use feather::queue::Repeat;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the status files are brought up to date.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Contents of the JSON status file: the status and its text as set by the format string.
+#[derive(Serialize)]
+struct StatusFile<'a> {
+    #[serde(flatten)]
+    status: &'a Status, // Status of the player
+    text: String, // The status formatted
+}
+
+/// Returns the path of the JSON status file: `feather-status.json` in the
+/// [`ipc::runtime_dir`].
+pub fn json_path() -> io::Result<PathBuf> {
+    Ok(ipc::runtime_dir()?.join("feather-status.json"))
+}
+
+/// Returns the path of the text status file next to the JSON one.
+pub fn text_path() -> io::Result<PathBuf> {
+    Ok(ipc::runtime_dir()?.join("feather-status.txt"))
+}
+
+/// Keeps the status files up to date while Feather runs, if enabled in the `[status]`
+/// config section. Both are replaced in one step, so readers never see half a file.
+/// A write that fails is shown to the user and tried again on the next update.
+///
+/// # Arguments
+/// * `backend` - Backend whose status is published.
+pub async fn run(backend: Arc<Backend>) {
+    if !backend.config.status.file {
+        return;
+    }
+    let mut last: Option<Status> = None;
+    let mut failure: Option<String> = None; // Error of the last write, shown only once
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let status = ipc::status(&backend);
+        // Only whole seconds are shown, so smaller steps are not worth a rewrite
+        let mut shown = status.clone();
+        shown.position = shown.position.floor();
+        if last.as_ref() == Some(&shown) {
+            continue;
+        }
+
+        match write_files(&backend.config.status.format, &status) {
+            Ok(()) => {
+                last = Some(shown);
+                failure = None;
+            }
+            Err(e) => {
+                let message = format!("Failed to write the status files: {}", e);
+                if failure.as_ref() != Some(&message) {
+                    backend.report(message.clone());
+                }
+                failure = Some(message);
+            }
+        }
+    }
+}
+
+// Writes the status, formatted as set by `format`, to both files
+fn write_files(format_string: &str, status: &Status) -> io::Result<()> {
+    let text = format(format_string, status);
+    write_atomically(&text_path()?, format!("{}\n", text).as_bytes())?;
+    let file = StatusFile { status, text };
+    write_atomically(&json_path()?, serde_json::to_string(&file)?.as_bytes())
+}
+
+/// Removes the status files, so nothing stale is shown once Feather has exited.
+pub fn remove_files() {
+    for path in [json_path(), text_path()].into_iter().flatten() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// Replaces a file by writing a temporary one next to it and renaming that. The temporary
+// file is always created anew, so nothing left in its place, e.g. a link, is written through.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".part");
+    match std::fs::remove_file(&temporary) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temporary)?;
+    file.write_all(contents)?;
+    std::fs::rename(&temporary, path)
+}
+
+/// Fills in the placeholders of a status format string, e.g. `"{artist} - {title}"`.
+/// Unknown placeholders are kept as they are, `{{` and `}}` give literal braces, and the
+/// text is empty while no song is loaded.
+///
+/// Placeholders: `title`, `artist`, `id`, `position`, `duration`, `percent`, `state`
+/// (playing, paused or stopped), `icon`, `paused` (true or false), `volume`, `repeat`
+/// (off, one or all) and `shuffle` (on or off).
+pub fn format(format: &str, status: &Status) -> String {
+    if status.track.is_none() {
+        return String::new();
+    }
+    let mut text = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find(['{', '}']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{{") {
+            text.push('{');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}") {
+            text.push('}');
+            rest = after;
+        } else if let Some(end) = rest.find('}').filter(|_| rest.starts_with('{')) {
+            match placeholder(&rest[1..end], status) {
+                Some(value) => text.push_str(&value),
+                None => text.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        } else {
+            text.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// Returns the value of a placeholder, or `None` if there is no such placeholder
+fn placeholder(name: &str, status: &Status) -> Option<String> {
+    let track = status.track.as_ref()?;
+    let value = match name {
+        "title" => track.title.clone(),
+        "artist" => track.artists.join(", "),
+        "id" => track.id.clone(),
+        "position" => format_time(status.position),
+        "duration" => format_time(status.duration),
+        "percent" => {
+            let percent = if status.duration > 0.0 {
+                status.position / status.duration * 100.0
+            } else {
+                0.0
+            };
+            format!("{:.0}", percent)
+        }
+        "state" => match status.state {
+            PlayState::Playing => "playing",
+            PlayState::Paused => "paused",
+            PlayState::Stopped => "stopped",
+        }
+        .to_string(),
+        "icon" => match status.state {
+            PlayState::Playing => "▶",
+            PlayState::Paused => "⏸",
+            PlayState::Stopped => "⏹",
+        }
+        .to_string(),
+        "paused" => (status.state == PlayState::Paused).to_string(),
+        "volume" => format!("{:.0}", status.volume),
+        "repeat" => match status.repeat {
+            Repeat::Off => "off",
+            Repeat::One => "one",
+            Repeat::All => "all",
+        }
+        .to_string(),
+        "shuffle" => if status.shuffle { "on" } else { "off" }.to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+// Formats seconds as H:MM:SS or M:SS
+fn format_time(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    if total >= 3600 {
+        format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+    } else {
+        format!("{}:{:02}", total / 60, total % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::Track;
+
+    fn status() -> Status {
+        Status {
+            track: Some(Track {
+                id: "abc123".to_string(),
+                title: "Song".to_string(),
+                artists: vec!["First".to_string(), "Second".to_string()],
+            }),
+            state: PlayState::Playing,
+            position: 75.6,
+            duration: 3725.0,
+            volume: 80.4,
+            repeat: Repeat::All,
+            shuffle: true,
+        }
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let status = status();
+        assert_eq!(
+            format("{artist} - {title}", &status),
+            "First, Second - Song"
+        );
+        assert_eq!(
+            format("{id} {position}/{duration} {percent}%", &status),
+            "abc123 1:15/1:02:05 2%"
+        );
+        assert_eq!(
+            format("{state} {icon} {paused} {volume}", &status),
+            "playing ▶ false 80"
+        );
+        assert_eq!(format("{repeat} {shuffle}", &status), "all on");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_escaped_braces() {
+        let status = status();
+        assert_eq!(format("{album} {title}", &status), "{album} Song");
+        assert_eq!(format("{{title}} }}{{", &status), "{title} }{");
+        assert_eq!(format("{title", &status), "{title");
+        assert_eq!(format("title}", &status), "title}");
+        assert_eq!(format("", &status), "");
+    }
+
+    #[test]
+    fn shows_paused_and_unknown_lengths() {
+        let status = Status {
+            state: PlayState::Paused,
+            position: 30.0,
+            duration: 0.0,
+            repeat: Repeat::Off,
+            shuffle: false,
+            ..status()
+        };
+        assert_eq!(
+            format("{icon} {paused} {position}/{duration} {percent}%", &status),
+            "⏸ true 0:30/0:00 0%"
+        );
+        assert_eq!(format("{repeat} {shuffle}", &status), "off off");
+    }
+
+    #[test]
+    fn is_empty_without_a_song() {
+        let status = Status {
+            track: None,
+            state: PlayState::Stopped,
+            ..status()
+        };
+        assert_eq!(format("{artist} - {title}", &status), "");
+    }
+
+    #[test]
+    fn does_not_write_through_a_planted_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.txt");
+        let target = dir.path().join("target");
+        std::fs::write(&target, "untouched").unwrap();
+        std::os::unix::fs::symlink(&target, dir.path().join("status.txt.part")).unwrap();
+
+        write_atomically(&path, b"written").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "written");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "untouched");
+        assert!(!dir.path().join("status.txt.part").exists());
+    }
+}
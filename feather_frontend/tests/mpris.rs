@@ -0,0 +1,238 @@
+// Checks the MPRIS interfaces against a private session bus. Skipped when dbus-daemon is
+// not installed, and the playback half when mpv cannot play a file.
+mod common;
+
+use feather::config::Config;
+use feather::database::HistoryDB;
+use feather_frontend::backend::Backend;
+use feather_frontend::mpris::{self, BUS_NAME, OBJECT_PATH};
+use futures_util::StreamExt;
+use std::fmt::Debug;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{Connection, MatchRule, MessageStream, connection, message};
+
+const ROOT: &str = "org.mpris.MediaPlayer2";
+const PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+
+/// How long to wait for the bus or the player before failing.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// A private session bus, stopped when dropped
+struct Bus {
+    daemon: Child,   // The dbus-daemon process
+    address: String, // Address clients connect to
+}
+
+impl Bus {
+    // Starts a bus, or returns None if dbus-daemon is not installed
+    fn start() -> Option<Self> {
+        let daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut bus = Self {
+            daemon,
+            address: String::new(),
+        };
+        let stdout = bus.daemon.stdout.take()?;
+        BufReader::new(stdout).read_line(&mut bus.address).ok()?;
+        bus.address.truncate(bus.address.trim_end().len());
+        Some(bus)
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+#[test]
+fn serves_mpris() {
+    let Some(bus) = Bus::start() else {
+        eprintln!("dbus-daemon is not installed, skipping");
+        return;
+    };
+    let data = tempfile::tempdir().unwrap();
+    for var in ["XDG_DATA_HOME", "XDG_CACHE_HOME", "XDG_CONFIG_HOME"] {
+        // SAFETY: this is the only test in the binary and no other thread has started yet.
+        // The databases go to a fresh directory instead of the user's own.
+        unsafe { std::env::set_var(var, data.path()) };
+    }
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(check_mpris(&bus.address, data.path()));
+}
+
+async fn check_mpris(address: &str, data: &Path) {
+    let history = Arc::new(HistoryDB::new().unwrap());
+    let backend = Arc::new(Backend::new(history, Config::default(), None).unwrap());
+    let builder = connection::Builder::address(address).unwrap();
+    tokio::spawn(mpris::serve(builder, Arc::clone(&backend)));
+
+    let client = connection::Builder::address(address)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let properties = PropertiesProxy::builder(&client)
+        .destination(BUS_NAME)
+        .unwrap()
+        .path(OBJECT_PATH)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    wait_for_name(&properties).await;
+
+    // Feather lives in a terminal, so it can be neither raised nor quit
+    assert!(!get::<bool>(&properties, ROOT, "CanQuit").await);
+    assert!(!get::<bool>(&properties, ROOT, "CanRaise").await);
+    assert!(!get::<bool>(&properties, ROOT, "HasTrackList").await);
+
+    assert_eq!(
+        get::<String>(&properties, PLAYER, "PlaybackStatus").await,
+        "Stopped"
+    );
+    assert_eq!(
+        get::<String>(&properties, PLAYER, "LoopStatus").await,
+        "None"
+    );
+    assert!(!get::<bool>(&properties, PLAYER, "Shuffle").await);
+    assert!(get::<bool>(&properties, PLAYER, "CanControl").await);
+    assert_eq!(get::<f64>(&properties, PLAYER, "Rate").await, 1.0);
+
+    // Writable properties reach the backend, and bad values are turned away
+    set(&properties, "LoopStatus", Value::from("Playlist"))
+        .await
+        .unwrap();
+    assert_eq!(
+        get::<String>(&properties, PLAYER, "LoopStatus").await,
+        "Playlist"
+    );
+    assert!(
+        set(&properties, "LoopStatus", Value::from("Sometimes"))
+            .await
+            .is_err()
+    );
+    set(&properties, "LoopStatus", Value::from("None"))
+        .await
+        .unwrap();
+
+    // Without a track the controls do nothing
+    call(&client, "PlayPause", &()).await;
+    assert_eq!(
+        get::<String>(&properties, PLAYER, "PlaybackStatus").await,
+        "Stopped"
+    );
+
+    let file = data.join("silence.wav");
+    common::write_silence(&file, 60);
+    backend
+        .player
+        .play_at(file.to_str().unwrap(), None, true)
+        .unwrap();
+    if !wait_for_status(&properties, "Paused").await {
+        eprintln!("mpv could not play the test file, skipping the playback checks");
+        return;
+    }
+
+    call(&client, "PlayPause", &()).await;
+    assert!(wait_for_status(&properties, "Playing").await);
+    call(&client, "PlayPause", &()).await;
+    assert!(wait_for_status(&properties, "Paused").await);
+
+    // Seeking from outside is announced like any other jump in position
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface(PLAYER)
+        .unwrap()
+        .member("Seeked")
+        .unwrap()
+        .build();
+    let mut seeked = MessageStream::for_match_rule(rule, &client, None)
+        .await
+        .unwrap();
+    call(&client, "Seek", &(30_000_000i64)).await;
+    let signal = tokio::time::timeout(TIMEOUT, seeked.next())
+        .await
+        .expect("no Seeked signal")
+        .unwrap()
+        .unwrap();
+    let position: i64 = signal.body().deserialize().unwrap();
+    assert!(
+        (29_000_000..=31_000_000).contains(&position),
+        "{}",
+        position
+    );
+    let reported = get::<i64>(&properties, PLAYER, "Position").await;
+    assert!(
+        (29_000_000..=31_000_000).contains(&reported),
+        "{}",
+        reported
+    );
+}
+
+// Waits until Feather owns its name on the bus
+async fn wait_for_name(properties: &PropertiesProxy<'_>) {
+    let started = Instant::now();
+    let root = InterfaceName::try_from(ROOT).unwrap();
+    while properties.get(root.clone(), "Identity").await.is_err() {
+        assert!(started.elapsed() < TIMEOUT, "MPRIS was not served");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(get::<String>(properties, ROOT, "Identity").await, "Feather");
+}
+
+// Waits until the playback status is `status`, returning false if it never is
+async fn wait_for_status(properties: &PropertiesProxy<'_>, status: &str) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < TIMEOUT {
+        if get::<String>(properties, PLAYER, "PlaybackStatus").await == status {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    false
+}
+
+// Reads a property
+async fn get<T>(properties: &PropertiesProxy<'_>, interface: &str, name: &str) -> T
+where
+    T: TryFrom<OwnedValue>,
+    T::Error: Debug,
+{
+    let interface = InterfaceName::try_from(interface).unwrap();
+    let value = properties.get(interface, name).await.unwrap();
+    T::try_from(value).unwrap()
+}
+
+// Writes a property of the player
+async fn set(
+    properties: &PropertiesProxy<'_>,
+    name: &str,
+    value: Value<'_>,
+) -> zbus::fdo::Result<()> {
+    let interface = InterfaceName::try_from(PLAYER).unwrap();
+    properties.set(interface, name, value).await
+}
+
+// Calls a method of the player
+async fn call<B>(client: &Connection, method: &str, body: &B)
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    client
+        .call_method(Some(BUS_NAME), OBJECT_PATH, Some(PLAYER), method, body)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,61 @@
+// Checks that a song played to its end forgets where it was left off. Skipped when mpv
+// cannot play a file.
+mod common;
+
+use feather::config::Config;
+use feather::database::{HistoryDB, ResumePosition, SongEntry};
+use feather_frontend::backend::{Backend, Song};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the song to end before giving up.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[test]
+fn clears_resume_position_on_finish() {
+    let data = tempfile::tempdir().unwrap();
+    for var in ["XDG_DATA_HOME", "XDG_CACHE_HOME", "XDG_CONFIG_HOME"] {
+        // SAFETY: this is the only test in the binary and no other thread has started yet.
+        // The databases go to a fresh directory instead of the user's own.
+        unsafe { std::env::set_var(var, data.path()) };
+    }
+    let file = data.path().join("silence.wav");
+    common::write_silence(&file, 2);
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(check_clear_on_finish(file.to_str().unwrap()));
+}
+
+async fn check_clear_on_finish(file: &str) {
+    let mut config = Config::default();
+    config.resume.min_length_secs = 0;
+    let history = Arc::new(HistoryDB::new().unwrap());
+    let backend = Backend::new(history, config, None).unwrap();
+
+    // A song played from outside the queue, with a position saved while it played
+    let song = Song::new(
+        "Song".to_string(),
+        "abc123".to_string(),
+        vec!["Artist".to_string()],
+    );
+    *backend.song.lock().unwrap() = Some(song.clone());
+    backend
+        .resume
+        .set(&ResumePosition::new(SongEntry::from(song), 1.0, 2.0))
+        .unwrap();
+    assert!(backend.resume.get("abc123").unwrap().is_some());
+
+    backend.player.play_at(file, None, false).unwrap();
+    let started = Instant::now();
+    while !backend.player.is_finished() {
+        if started.elapsed() > TIMEOUT {
+            eprintln!("mpv could not play the test file, skipping");
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // Nothing follows the song, but it still counts as played out
+    assert!(!backend.tick_autoplay().await.unwrap());
+    assert!(backend.resume.get("abc123").unwrap().is_none());
+}